@@ -0,0 +1,293 @@
+use crate::types::SourceFile;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_MB: u64 = 512;
+const ARTIFACTS_DIR: &str = "artifacts";
+const LAST_USED_FILE: &str = "last_used";
+
+/// On-disk cache of compiled artifacts, keyed by a hash of everything that affects the
+/// build, so identical submissions skip compilation even across agent restarts. Least
+/// recently used entries are evicted once the cache grows beyond `max_bytes`.
+///
+/// Each entry is `<root>/<key>/artifacts/...` plus a `last_used` timestamp file.
+#[derive(Debug, Clone)]
+pub struct CompileCache {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+impl CompileCache {
+    pub fn new(root: impl Into<PathBuf>, max_bytes: u64) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root).with_context(|| format!("creating {}", root.display()))?;
+        Ok(Self { root, max_bytes })
+    }
+
+    /// Opt-in via `BUILDIT_COMPILE_CACHE_DIR`; `BUILDIT_COMPILE_CACHE_MAX_MB` caps its size
+    /// (default 512 MB).
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(root) = std::env::var_os("BUILDIT_COMPILE_CACHE_DIR") else {
+            return Ok(None);
+        };
+        let max_mb = std::env::var("BUILDIT_COMPILE_CACHE_MAX_MB")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_MB);
+        Self::new(root, max_mb * 1024 * 1024).map(Some)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Cache key for compiling `code` as `file_name`, next to the other source `files`, with
+    /// the given command and arguments.
+    pub fn key(
+        language: &str,
+        compile_command: &str,
+        compile_args: &[String],
+        file_name: &str,
+        code: &str,
+        files: &[SourceFile],
+    ) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update((compile_args.len() as u64).to_le_bytes());
+        let parts = [language, compile_command, file_name]
+            .into_iter()
+            .chain(compile_args.iter().map(String::as_str))
+            .chain([code])
+            .chain(
+                files
+                    .iter()
+                    .flat_map(|f| [f.name.as_str(), f.content.as_str()]),
+            );
+        // Length-prefix every part so different splits can't hash the same
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Copy the cached artifacts for `key` into `work_dir`. Returns false on a miss.
+    pub async fn restore(&self, key: &str, work_dir: &Path) -> Result<bool> {
+        let (cache, key, work_dir) = (self.clone(), key.to_string(), work_dir.to_path_buf());
+        tokio::task::spawn_blocking(move || cache.restore_blocking(&key, &work_dir)).await?
+    }
+
+    /// Save everything in `work_dir` except the source file as the artifacts for `key`,
+    /// then evict old entries if the cache is over its size limit.
+    pub async fn store(&self, key: &str, work_dir: &Path, source_file: &str) -> Result<()> {
+        let (cache, key, work_dir) = (self.clone(), key.to_string(), work_dir.to_path_buf());
+        let source_file = source_file.to_string();
+        tokio::task::spawn_blocking(move || {
+            cache.store_blocking(&key, &work_dir, &source_file)?;
+            cache.evict()
+        })
+        .await?
+    }
+
+    fn restore_blocking(&self, key: &str, work_dir: &Path) -> Result<bool> {
+        let entry = self.root.join(key);
+        let artifacts = entry.join(ARTIFACTS_DIR);
+        if !artifacts.is_dir() {
+            return Ok(false);
+        }
+        copy_dir_contents(&artifacts, work_dir, None)?;
+        touch(&entry)?;
+        Ok(true)
+    }
+
+    fn store_blocking(&self, key: &str, work_dir: &Path, source_file: &str) -> Result<()> {
+        let entry = self.root.join(key);
+        if entry.exists() {
+            return Ok(());
+        }
+        // Build the entry next to its final place and rename it in, so a crash never
+        // leaves a half-written entry behind a valid key
+        let staging = tempfile::Builder::new()
+            .prefix(".staging-")
+            .tempdir_in(&self.root)?;
+        copy_dir_contents(
+            work_dir,
+            &staging.path().join(ARTIFACTS_DIR),
+            Some(source_file),
+        )?;
+        touch(staging.path())?;
+        let staged = staging.keep();
+        if std::fs::rename(&staged, &entry).is_err() {
+            // Another run stored the same key first
+            std::fs::remove_dir_all(&staged)?;
+        }
+        Ok(())
+    }
+
+    // Remove least recently used entries until the cache fits in `max_bytes`
+    fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for dir_entry in std::fs::read_dir(&self.root)? {
+            let path = dir_entry?.path();
+            let is_staging = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            if is_staging || !path.is_dir() {
+                continue;
+            }
+            let size = dir_size(&path)?;
+            let last_used = std::fs::read_to_string(path.join(LAST_USED_FILE))
+                .ok()
+                .and_then(|s| s.trim().parse::<u128>().ok())
+                .unwrap_or(0);
+            total += size;
+            entries.push((last_used, size, path));
+        }
+
+        entries.sort();
+        for (_, size, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            std::fs::remove_dir_all(&path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+fn touch(entry: &Path) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    std::fs::write(entry.join(LAST_USED_FILE), now.to_string())?;
+    Ok(())
+}
+
+// Recursively copy the contents of `from` into `to`, skipping the top-level `skip` entry
+fn copy_dir_contents(from: &Path, to: &Path, skip: Option<&str>) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if skip.is_some_and(|skip| entry.file_name() == skip) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_contents(&entry.path(), &target, None)?;
+        } else {
+            // `fs::copy` keeps permissions, so executables stay executable
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        size += if meta.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            meta.len()
+        };
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_key_depends_on_every_input() {
+        let base = CompileCache::key("gcc", "gcc", &args(&["-O2"]), "main.c", "int x;", &[]);
+        assert_eq!(
+            base,
+            CompileCache::key("gcc", "gcc", &args(&["-O2"]), "main.c", "int x;", &[])
+        );
+        assert_ne!(
+            base,
+            CompileCache::key("clang", "gcc", &args(&["-O2"]), "main.c", "int x;", &[])
+        );
+        assert_ne!(
+            base,
+            CompileCache::key("gcc", "gcc", &args(&["-O0"]), "main.c", "int x;", &[])
+        );
+        assert_ne!(
+            base,
+            CompileCache::key("gcc", "gcc", &args(&["-O2"]), "main.c", "int y;", &[])
+        );
+        // Moving text between parts must change the key
+        assert_ne!(
+            CompileCache::key("gcc", "gcc", &args(&["a", "b"]), "main.c", "", &[]),
+            CompileCache::key("gcc", "gcc", &args(&["a"]), "main.c", "b", &[])
+        );
+        let helper = SourceFile {
+            name: "helper.h".to_string(),
+            content: "int y;".to_string(),
+        };
+        assert_ne!(
+            base,
+            CompileCache::key("gcc", "gcc", &args(&["-O2"]), "main.c", "int x;", &[helper])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_then_restore_skips_source() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(cache_dir.path(), u64::MAX).unwrap();
+
+        let build = tempfile::tempdir().unwrap();
+        std::fs::write(build.path().join("main.c"), "source").unwrap();
+        std::fs::write(build.path().join("main"), "binary").unwrap();
+        std::fs::create_dir(build.path().join("classes")).unwrap();
+        std::fs::write(build.path().join("classes/A.class"), "class").unwrap();
+
+        assert!(!cache.restore("k", build.path()).await.unwrap());
+        cache.store("k", build.path(), "main.c").await.unwrap();
+
+        let fresh = tempfile::tempdir().unwrap();
+        assert!(cache.restore("k", fresh.path()).await.unwrap());
+        assert_eq!(
+            std::fs::read_to_string(fresh.path().join("main")).unwrap(),
+            "binary"
+        );
+        assert!(fresh.path().join("classes/A.class").exists());
+        assert!(!fresh.path().join("main.c").exists());
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        // Room for two 100-byte artifacts plus their timestamps, not three
+        let cache = CompileCache::new(cache_dir.path(), 280).unwrap();
+
+        let build = tempfile::tempdir().unwrap();
+        std::fs::write(build.path().join("main"), [0u8; 100]).unwrap();
+        cache.store("a", build.path(), "main.c").await.unwrap();
+        cache.store("b", build.path(), "main.c").await.unwrap();
+        // Using "a" makes "b" the least recently used
+        let scratch = tempfile::tempdir().unwrap();
+        assert!(cache.restore("a", scratch.path()).await.unwrap());
+        cache.store("c", build.path(), "main.c").await.unwrap();
+
+        assert!(cache.root().join("a").exists());
+        assert!(!cache.root().join("b").exists());
+        assert!(cache.root().join("c").exists());
+    }
+}
@@ -1,35 +1,255 @@
-use crate::language::{generate_language_configs, get_installed_languages, LanguageConfig};
-use crate::types::{CaseResult, ExecuteRequest, ExecuteResponse, ExecutionStatus};
+use crate::compile_cache::CompileCache;
+use crate::extract::{FieldError, InvalidRequest, JsonBody};
+use crate::language::{
+    detect_languages_within, languages_file_path, load_language_configs, LanguageConfig,
+    DEFAULT_DETECTION_DEADLINE,
+};
+use crate::net;
+use crate::rate_limit::{RateLimiter, Rejection};
+use crate::rusq::{
+    Consumer, Message, MetricsSnapshot, MpmcQueue, Producer, QueueDepths, RusqConfig, RusqError,
+};
+use crate::types::{
+    current_platform, CaseResult, Comparison, ExecuteRequest, ExecuteResponse, ExecutionStatus,
+    InputEncoding, LineMismatch, ReferenceSolution, ResolvedCommands, Scoreboard, SubtaskScore,
+    TestCase, Verdict, AGENT_VERSION,
+};
 use anyhow::Result;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Path, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
-use serde::Serialize;
-use tower_http::cors;
-use std::collections::{HashMap, HashSet};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use futures::{FutureExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::ffi::OsString;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     Arc,
 };
 use std::time::Instant;
-use tempfile;
+use sysinfo::{Pid, ProcessRefreshKind, System};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::sync::{oneshot, Notify, RwLock, Semaphore};
 use tokio::time;
 
 #[derive(Clone)]
 struct AppState {
-    configs: Arc<HashMap<String, LanguageConfig>>, // language key -> config
-    available: Arc<HashSet<String>>,               // installed language keys
-    langs_list: Arc<Vec<LanguageSummary>>,         // for GET /languages
-    jobs: Arc<RwLock<HashMap<u64, JobState>>>,
-    sender: mpsc::Sender<(u64, ExecuteRequest)>,
+    languages: Arc<std::sync::RwLock<Arc<LanguageRegistry>>>, // swapped on reload
+    retain_work_dir: bool,                                     // keep work dirs of failed runs
+    jobs: Arc<RwLock<JobStore>>,
+    queue: Arc<JobQueue>,
     next_id: Arc<AtomicU64>,
+    compile_cache: Option<Arc<CompileCache>>, // opt-in, persists across restarts
+    work_root: Option<std::path::PathBuf>,    // parent of run dirs; system temp dir if unset
+    admin_token: Option<String>,              // admin endpoints are disabled without one
+    report_commands: bool,                    // debugging aid: include spawned commands
+    shutting_down: Arc<AtomicBool>,           // set on Ctrl+C; running jobs stop between cases
+    rate_limiter: Option<Arc<RateLimiter>>,   // per-client limit on submitting jobs
+    activity: Arc<Activity>,                  // when the executor was last busy, for idle shutdown
+    limits: RunLimits,                        // how a job's test cases run
+    allow_unsafe_env: bool,                   // requests may set PATH, LD_PRELOAD and the like
+    pipeline: Arc<PipelineStats>,             // totals over every job the worker has finished
+    run_slots: Arc<Semaphore>,                // submissions executing at once, on any endpoint
+    // Re-read on SIGHUP or POST /languages/reload
+    languages_path: Option<std::path::PathBuf>,
+}
+
+impl AppState {
+    // Snapshot of the current language set; stays valid even if a reload swaps it out
+    fn languages(&self) -> Arc<LanguageRegistry> {
+        self.languages.read().unwrap().clone()
+    }
+}
+
+/// Tracks whether any job is executing and when the executor was last busy.
+#[derive(Debug)]
+struct Activity {
+    running: AtomicUsize,
+    last_active: std::sync::Mutex<Instant>,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            running: AtomicUsize::new(0),
+            last_active: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    // Record activity now, e.g. a job arriving
+    fn touch(&self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+    }
+
+    // Mark a job as executing until the returned guard is dropped
+    fn start(self: &Arc<Self>) -> ActiveGuard {
+        self.running.fetch_add(1, Ordering::SeqCst);
+        self.touch();
+        ActiveGuard(self.clone())
+    }
+
+    // How long nothing has been executing, or `None` while something is
+    fn idle_for(&self) -> Option<time::Duration> {
+        if self.running.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        Some(self.last_active.lock().unwrap().elapsed())
+    }
+}
+
+struct ActiveGuard(Arc<Activity>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        // Finishing counts as activity, so the idle period starts now
+        self.0.touch();
+        self.0.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Running totals of the job pipeline behind POST /execute. Unlike the job store they
+/// survive eviction, so they cover every job since startup.
+#[derive(Debug, Default)]
+struct PipelineStats {
+    jobs_processed: AtomicU64,    // finished by the worker, completed or failed
+    jobs_completed: AtomicU64,    // those of them that produced a response
+    total_duration_ms: AtomicU64, // summed over the completed ones
+}
+
+impl PipelineStats {
+    fn record(&self, result: &std::result::Result<ExecuteResponse, JobError>) {
+        self.jobs_processed.fetch_add(1, Ordering::Relaxed);
+        if let Ok(resp) = result {
+            self.jobs_completed.fetch_add(1, Ordering::Relaxed);
+            let duration_ms = resp.total_duration_ms;
+            self.total_duration_ms.fetch_add(duration_ms, Ordering::Relaxed);
+        }
+    }
+
+    // Mean `total_duration_ms` of the completed jobs, if there are any
+    fn average_duration_ms(&self) -> Option<f64> {
+        let completed = self.jobs_completed.load(Ordering::Relaxed);
+        let total = self.total_duration_ms.load(Ordering::Relaxed);
+        (completed > 0).then(|| total as f64 / completed as f64)
+    }
+}
+
+#[derive(Debug, Default)]
+struct LanguageRegistry {
+    configs: HashMap<String, LanguageConfig>, // language key -> config
+    available: HashSet<String>,               // installed language keys
+    langs_list: Vec<LanguageSummary>,         // for GET /languages
+}
+
+impl LanguageRegistry {
+    // Detect which of `configs` are installed on this machine
+    async fn detect(configs: HashMap<String, LanguageConfig>) -> Self {
+        let detection = detect_languages_within(&configs, DEFAULT_DETECTION_DEADLINE).await;
+        if !detection.pending.is_empty() {
+            println!(
+                "Language detection deadline reached; skipping still-pending: {}",
+                detection.pending.join(", ")
+            );
+        }
+        let installed = detection.installed;
+        let available: HashSet<String> = installed.iter().map(|li| li.name.clone()).collect();
+        let langs_list: Vec<LanguageSummary> = installed
+            .into_iter()
+            .map(|li| LanguageSummary {
+                display_name: li.display_name,
+                language: li.name,
+            })
+            .collect();
+
+        Self {
+            configs,
+            available,
+            langs_list,
+        }
+    }
+}
+
+type QueuedJob = (u64, ExecuteRequest);
+
+/// Priority-aware job queue backed by `MpmcQueue`. Higher-priority jobs are
+/// handed to the worker first; `notify` wakes the worker when a job arrives.
+struct JobQueue {
+    queue: MpmcQueue<QueuedJob>,
+    producer: Producer<QueuedJob>,
+    consumer: Consumer<QueuedJob>,
+    notify: Notify,
+}
+
+impl JobQueue {
+    fn new(capacity: usize) -> Self {
+        let queue = MpmcQueue::new(RusqConfig {
+            capacity: Some(capacity),
+            ..Default::default()
+        });
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        Self {
+            queue,
+            producer,
+            consumer,
+            notify: Notify::new(),
+        }
+    }
+
+    fn push(&self, id: u64, req: ExecuteRequest) -> Result<(), RusqError> {
+        let priority = req.priority;
+        self.producer
+            .send_with_priority((id, req), "execute".to_string(), priority)?;
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    /// Stop handing out jobs; a worker waiting in `pop` gets `None`.
+    fn shutdown(&self) {
+        self.queue.shutdown();
+        self.notify.notify_one();
+    }
+
+    fn is_empty(&self) -> bool {
+        let depths = self.queue.depths();
+        depths.critical + depths.high + depths.normal + depths.low == 0
+    }
+
+    /// Remove every waiting job without running it, returning their ids.
+    fn drain(&self) -> Vec<u64> {
+        let mut ids = Vec::new();
+        while let Ok(message) = self.consumer.try_recv() {
+            ids.push(message.payload.0);
+            self.consumer.ack(message);
+        }
+        ids
+    }
+
+    /// Wait for the next job, highest priority first. Returns `None` once the queue is shut down.
+    /// The job counts as in flight until it is handed back to `done`.
+    async fn pop(&self) -> Option<Message<QueuedJob>> {
+        loop {
+            match self.consumer.try_recv() {
+                Ok(message) => return Some(message),
+                Err(RusqError::Empty) => self.notify.notified().await,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn done(&self, job: Message<QueuedJob>) {
+        self.consumer.ack(job);
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -48,95 +268,507 @@ struct LanguageSummary {
     language: String,
 }
 
+/// Entry of `GET /languages/all`: a configured language and whether it is installed.
+#[derive(Debug, Clone, Serialize)]
+struct LanguageAvailability {
+    display_name: String,
+    language: String,
+    available: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "status", rename_all = "lowercase")]
 enum JobStatusResponse {
     Queued,
     Running,
-    Completed { result: ExecuteResponse },
-    Error { error: String },
+    Completed {
+        result: Box<ExecuteResponse>,
+    },
+    Error {
+        error: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        work_dir: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone)]
 enum JobState {
     Queued,
-    Running,
-    Completed(ExecuteResponse),
-    Error(String),
+    Running(Arc<Notify>), // notified to cancel the job
+    Completed(Box<ExecuteResponse>),
+    Error(JobError),
 }
 
-pub async fn run(ready_tx: Option<oneshot::Sender<()>>) -> Result<()> {
-    // Build language configs and detect installed ones once at startup
-    let configs = generate_language_configs();
-    let installed = get_installed_languages(&configs).await;
-    let available: HashSet<String> = installed.iter().map(|li| li.name.clone()).collect();
-    let langs_list: Vec<LanguageSummary> = installed
-        .into_iter()
-        .map(|li| LanguageSummary {
-            display_name: li.display_name,
-            language: li.name,
-        })
-        .collect();
+impl JobState {
+    fn is_finished(&self) -> bool {
+        matches!(self, JobState::Completed(_) | JobState::Error(_))
+    }
+}
+
+/// In-memory job table. Finished jobs are capped at `max_finished`; once over the cap the
+/// job that finished earliest is evicted, along with its spilled output files. With a TTL,
+/// finished jobs are also purged once they are older than it. Queued and running jobs are
+/// never evicted.
+#[derive(Debug)]
+struct JobStore {
+    jobs: HashMap<u64, JobState>,
+    finished: VecDeque<(u64, Instant)>,    // finished job ids and finish times, oldest first
+    max_finished: usize,
+    ttl: Option<time::Duration>,           // how long a finished job is kept, if limited
+    spill_root: Option<tempfile::TempDir>, // holds a directory of spilled stdout per job
+}
+
+impl JobStore {
+    fn new(max_finished: usize) -> Self {
+        Self {
+            jobs: HashMap::new(),
+            finished: VecDeque::new(),
+            max_finished,
+            ttl: None,
+            spill_root: None,
+        }
+    }
+
+    fn with_spill_root(mut self, root: tempfile::TempDir) -> Self {
+        self.spill_root = Some(root);
+        self
+    }
+
+    fn with_ttl(mut self, ttl: time::Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    // Where job `id` writes the stdout of cases that spill to a file
+    fn spill_dir(&self, id: u64) -> Option<std::path::PathBuf> {
+        let root = self.spill_root.as_ref()?;
+        Some(root.path().join(id.to_string()))
+    }
+
+    fn insert(&mut self, id: u64, state: JobState) {
+        let finished = state.is_finished();
+        let was_finished = self.jobs.insert(id, state).is_some_and(|s| s.is_finished());
+        if finished && !was_finished {
+            self.finished.push_back((id, Instant::now()));
+            while self.finished.len() > self.max_finished {
+                self.evict_oldest();
+            }
+        }
+    }
+
+    // Drop the finished jobs that are older than the TTL at `now`; returns how many
+    fn purge_expired(&mut self, now: Instant) -> usize {
+        let Some(ttl) = self.ttl else {
+            return 0;
+        };
+        let mut purged = 0;
+        while self
+            .finished
+            .front()
+            .is_some_and(|&(_, finished_at)| now.saturating_duration_since(finished_at) >= ttl)
+        {
+            self.evict_oldest();
+            purged += 1;
+        }
+        purged
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some((oldest, _)) = self.finished.pop_front() {
+            self.jobs.remove(&oldest);
+            if let Some(dir) = self.spill_dir(oldest) {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    fn get(&self, id: &u64) -> Option<&JobState> {
+        self.jobs.get(id)
+    }
+
+    fn retained_finished(&self) -> usize {
+        self.finished.len()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct QueueMetricsResponse {
+    #[serde(flatten)]
+    metrics: MetricsSnapshot,
+    depths: QueueDepths,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MetricsResponse {
+    retained_jobs: usize,
+    max_retained_jobs: usize,
+    jobs: JobCounts,
+    queue_depth: usize, // jobs waiting in the queue, dead letters excluded
+    jobs_processed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_total_duration_ms: Option<f64>,
+}
+
+/// Jobs in the store by state; finished jobs only while they are retained.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct JobCounts {
+    queued: usize,
+    running: usize,
+    completed: usize,
+    error: usize,
+}
+
+#[derive(Debug, Clone)]
+struct JobError {
+    message: String,
+    work_dir: Option<String>, // only set when work dir retention is enabled
+}
+
+impl JobError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            work_dir: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for JobError {
+    fn from(e: anyhow::Error) -> Self {
+        JobError::new(e.to_string())
+    }
+}
+
+const DEFAULT_MAX_RETAINED_JOBS: usize = 1000;
+/// How long finished jobs are kept unless `BUILDIT_JOB_TTL_SECS` says otherwise
+const DEFAULT_JOB_TTL: time::Duration = time::Duration::from_secs(60 * 60);
+/// Longest gap between two sweeps for expired jobs
+const JOB_PURGE_INTERVAL: time::Duration = time::Duration::from_secs(60);
+
+// Pick the executor's bind address (`BUILDIT_EXECUTOR_HOST`, loopback by default). Anything
+// else exposes arbitrary code execution to the network, so it needs an explicit opt-in.
+fn executor_bind_ip(host: Option<&str>, allow_non_loopback: bool) -> Result<IpAddr> {
+    let ip = match host {
+        Some(host) => host
+            .parse::<IpAddr>()
+            .map_err(|e| anyhow::anyhow!("Invalid BUILDIT_EXECUTOR_HOST {:?}: {}", host, e))?,
+        None => IpAddr::V4(Ipv4Addr::LOCALHOST),
+    };
+    if ip.is_loopback() {
+        return Ok(ip);
+    }
+    if !allow_non_loopback {
+        eprintln!(
+            "🚨 Refusing to bind the executor to non-loopback address {}: anyone who can \
+             reach it could run code on this machine. Set \
+             BUILDIT_EXECUTOR_ALLOW_NON_LOOPBACK=1 to allow it.",
+            ip
+        );
+        anyhow::bail!("Executor bind address {} is not loopback", ip);
+    }
+    eprintln!(
+        "🚨 WARNING: executor is bound to non-loopback address {}; code execution is exposed \
+         to the network",
+        ip
+    );
+    Ok(ip)
+}
+
+/// Port of the executor unless `BUILDIT_EXECUTOR_PORT` overrides it
+pub const DEFAULT_PORT: u16 = 8910;
 
-    println!("Executor detected {} languages:", langs_list.len());
-    for (i, lang) in langs_list.iter().enumerate() {
+/// Serve the executor on `port` until `shutdown` resolves, e.g. on Ctrl+C (see
+/// `net::shutdown_requested`), or until it has been idle for `BUILDIT_IDLE_SHUTDOWN_SECS`.
+/// `ready_tx` is told once connections are accepted.
+pub async fn run(
+    port: u16,
+    ready_tx: Option<oneshot::Sender<()>>,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    // Validate the bind address before doing any work
+    let bind_ip = executor_bind_ip(
+        std::env::var("BUILDIT_EXECUTOR_HOST").ok().as_deref(),
+        matches!(
+            std::env::var("BUILDIT_EXECUTOR_ALLOW_NON_LOOPBACK").as_deref(),
+            Ok("1") | Ok("true")
+        ),
+    )?;
+    let cors = net::cors_layer(std::env::var("BUILDIT_CORS_ORIGINS").ok().as_deref())?;
+
+    // Build language configs and detect installed ones at startup
+    let languages_path = languages_file_path();
+    let configs = load_language_configs(languages_path.as_deref())?;
+    let registry = LanguageRegistry::detect(configs).await;
+
+    println!("Executor detected {} languages:", registry.langs_list.len());
+    for (i, lang) in registry.langs_list.iter().enumerate() {
         println!("{}: {} ({})", i + 1, lang.display_name, lang.language);
     }
 
-    let (tx, rx) = mpsc::channel::<(u64, ExecuteRequest)>(100);
+    // Debugging aid: keep the temp dir of failed runs and report its path
+    let retain_work_dir = matches!(
+        std::env::var("BUILDIT_RETAIN_WORKDIR_ON_FAILURE").as_deref(),
+        Ok("1") | Ok("true")
+    );
+    if retain_work_dir {
+        println!("⚠️ Work directories of failed runs will be retained");
+    }
+
+    // Debugging aid: report the exact compile and run commands spawned for each job
+    let report_commands = matches!(
+        std::env::var("BUILDIT_REPORT_COMMANDS").as_deref(),
+        Ok("1") | Ok("true")
+    );
+
+    // Hard cap on finished jobs kept for GET /status, independent of any time-based cleanup
+    let max_retained_jobs = std::env::var("BUILDIT_MAX_RETAINED_JOBS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_RETAINED_JOBS);
+    // Finished jobs are purged once older than this; 0 keeps them until the cap evicts them
+    let job_ttl = match std::env::var("BUILDIT_JOB_TTL_SECS").ok().and_then(|v| v.parse().ok()) {
+        Some(0) => None,
+        Some(secs) => Some(time::Duration::from_secs(secs)),
+        None => Some(DEFAULT_JOB_TTL),
+    };
+
+    let compile_cache = CompileCache::from_env()?.map(Arc::new);
+    if let Some(cache) = &compile_cache {
+        println!("Caching compiled artifacts in {}", cache.root().display());
+    }
+
+    // Preflight: an unusable work root fails every submission, so say so at startup. The
+    // directory doubles as the home of spilled outputs, kept until their job is evicted.
+    let work_root = std::env::var_os("BUILDIT_WORK_ROOT").map(std::path::PathBuf::from);
+    let mut jobs = JobStore::new(max_retained_jobs);
+    if let Some(ttl) = job_ttl {
+        jobs = jobs.with_ttl(ttl);
+    }
+    match create_work_dir(work_root.as_deref()) {
+        Ok(spill_root) => jobs = jobs.with_spill_root(spill_root),
+        Err(e) => eprintln!("🚨 {}", e),
+    }
+
+    // Requests can't point the toolchain at other programs or libraries unless this is set
+    let allow_unsafe_env = matches!(
+        std::env::var("BUILDIT_ALLOW_UNSAFE_ENV").as_deref(),
+        Ok("1") | Ok("true")
+    );
+    if allow_unsafe_env {
+        println!("⚠️ Requests may set any environment variable, including PATH and LD_PRELOAD");
+    }
+
+    let rate_limiter = RateLimiter::from_env().map(Arc::new);
+    if rate_limiter.is_some() {
+        println!("Rate limiting POST /execute per client");
+    }
+
+    let max_concurrent_runs = std::env::var("BUILDIT_MAX_CONCURRENT_RUNS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n: &usize| n > 0)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_RUNS);
+
     let state = AppState {
-        configs: Arc::new(configs),
-        available: Arc::new(available),
-        langs_list: Arc::new(langs_list),
-        jobs: Arc::new(RwLock::new(HashMap::new())),
-        sender: tx,
+        languages: Arc::new(std::sync::RwLock::new(Arc::new(registry))),
+        retain_work_dir,
+        jobs: Arc::new(RwLock::new(jobs)),
+        queue: Arc::new(JobQueue::new(100)),
         next_id: Arc::new(AtomicU64::new(1)),
+        compile_cache,
+        work_root,
+        admin_token: std::env::var("BUILDIT_ADMIN_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty()),
+        report_commands,
+        shutting_down: Arc::new(AtomicBool::new(false)),
+        rate_limiter,
+        activity: Arc::new(Activity::new()),
+        limits: RunLimits::from_env(),
+        allow_unsafe_env,
+        pipeline: Arc::default(),
+        run_slots: Arc::new(Semaphore::new(max_concurrent_runs)),
+        languages_path,
     };
 
+    // Scale-to-zero: exit once no job has arrived or run for this long
+    let idle_timeout = std::env::var("BUILDIT_IDLE_SHUTDOWN_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&secs| secs > 0)
+        .map(time::Duration::from_secs);
+    if let Some(timeout) = idle_timeout {
+        println!("Executor exits after {}s without jobs", timeout.as_secs());
+    }
+
+    #[cfg(unix)]
+    reload_on_sighup(state.clone())?;
+
     // Spawn worker loop
-    tokio::spawn(worker_loop(state.clone(), rx));
+    let worker = tokio::spawn(worker_loop(state.clone()));
+    if let Some(ttl) = job_ttl {
+        tokio::spawn(purge_expired_jobs(state.jobs.clone(), ttl));
+    }
 
     let app = Router::new()
         .route("/health", get(health_handler))
         .route("/languages", get(languages_handler))
-        .route("/execute", post(enqueue_handler))
+        .route("/languages/all", get(all_languages_handler))
+        .route(
+            "/execute",
+            post(enqueue_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit)),
+        )
+        .route(
+            "/execute/sync",
+            post(sync_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit)),
+        )
+        .route("/execute/validate", post(validate_handler))
+        .route("/execute/bulk", post(bulk_handler))
+        .route("/selftest", post(selftest_handler))
+        .route("/jobs/drain", post(drain_handler))
+        .route("/languages/reload", post(reload_languages_handler))
         .route("/status/:id", get(status_handler))
-        .with_state(state)
-        .layer(
-            cors::CorsLayer::new()
-                .allow_origin(cors::Any)
-                .allow_methods(cors::Any)
-                .allow_headers(cors::Any),
-        );
+        .route("/job/:id", delete(cancel_handler))
+        .route("/status/:id/stdout/:case_id", get(spilled_stdout_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/queue-metrics", get(queue_metrics_handler))
+        .with_state(state.clone())
+        .layer(cors);
 
-    let port = 8910;
-    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+    let addr = SocketAddr::new(bind_ip, port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     println!("🟢 Executor is running...");
     if let Some(tx) = ready_tx {
         let _ = tx.send(());
     }
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal(state, idle_timeout, shutdown))
+        .await?;
+    // Let the running job wrap up; it skips whatever cases it has not started yet
+    let _ = worker.await;
+    println!("🔴 Executor stopped");
     Ok(())
 }
 
-async fn worker_loop(state: AppState, mut rx: mpsc::Receiver<(u64, ExecuteRequest)>) {
-    while let Some((id, req)) = rx.recv().await {
-        {
-            let mut jobs = state.jobs.write().await;
-            jobs.insert(id, JobState::Running);
+// Resolve on `stop` (Ctrl+C or SIGTERM), or once idle for `idle_timeout`, after telling the
+// worker and any running job to stop
+async fn shutdown_signal(
+    state: AppState,
+    idle_timeout: Option<time::Duration>,
+    stop: impl std::future::Future<Output = ()>,
+) {
+    let idle = async {
+        match idle_timeout {
+            Some(timeout) => wait_until_idle(&state, timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        _ = stop => println!("Shutting down the executor..."),
+        _ = idle => println!("No jobs for a while; shutting down the executor..."),
+    }
+    state.shutting_down.store(true, Ordering::SeqCst);
+    state.queue.shutdown();
+}
+
+// Resolve once no job has been queued or executing for `timeout`
+async fn wait_until_idle(state: &AppState, timeout: time::Duration) {
+    loop {
+        let idle = state
+            .activity
+            .idle_for()
+            .filter(|_| state.queue.is_empty());
+        let wait = match idle {
+            Some(idle) if idle >= timeout => return,
+            Some(idle) => timeout - idle,
+            None => timeout,
+        };
+        time::sleep(wait).await;
+    }
+}
+
+// Re-read the language configs, re-run detection, and swap the registry in.
+async fn reload_languages(state: &AppState, path: Option<&std::path::Path>) -> Result<()> {
+    let registry = LanguageRegistry::detect(load_language_configs(path)?).await;
+
+    let old = state.languages();
+    let mut added: Vec<&String> = registry.available.difference(&old.available).collect();
+    let mut removed: Vec<&String> = old.available.difference(&registry.available).collect();
+    added.sort();
+    removed.sort();
+    println!(
+        "Reloaded languages ({} available): added [{}], removed [{}]",
+        registry.available.len(),
+        added.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", "),
+        removed.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+    );
+
+    *state.languages.write().unwrap() = Arc::new(registry);
+    Ok(())
+}
+
+// Reload language configs on SIGHUP (Unix only; the handler is registered before returning)
+#[cfg(unix)]
+fn reload_on_sighup(state: AppState) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            if let Err(e) = reload_languages(&state, state.languages_path.as_deref()).await {
+                eprintln!("Failed to reload language configs: {:#}", e);
+            }
         }
+    });
+    Ok(())
+}
+
+// Periodically drop finished jobs older than `ttl`, so a long-running agent doesn't keep
+// every result it ever produced
+async fn purge_expired_jobs(jobs: Arc<RwLock<JobStore>>, ttl: time::Duration) {
+    let mut sweep = time::interval(JOB_PURGE_INTERVAL.min(ttl));
+    loop {
+        sweep.tick().await;
+        jobs.write().await.purge_expired(Instant::now());
+    }
+}
+
+async fn worker_loop(state: AppState) {
+    while let Some(job) = state.queue.pop().await {
+        let (id, req) = &job.payload;
+        let id = *id;
+        let cancel = Arc::new(Notify::new());
+        let spill_dir = {
+            let mut jobs = state.jobs.write().await;
+            // Cancelled or drained while it was waiting, and possibly evicted since
+            if !matches!(jobs.get(&id), Some(JobState::Queued)) {
+                drop(jobs);
+                state.queue.done(job);
+                continue;
+            }
+            jobs.insert(id, JobState::Running(cancel.clone()));
+            jobs.spill_dir(id)
+        };
 
-        let res = execute_request(&req, &state).await;
+        // Giving up on the run kills the program of the case being run
+        let res = tokio::select! {
+            res = execute_request(req, &state, spill_dir.as_deref()) => res,
+            _ = cancel.notified() => Err(JobError::new("cancelled")),
+        };
         let mut jobs = state.jobs.write().await;
+        // Counted under the store's lock, so /metrics never sees a job twice or not at all
+        state.pipeline.record(&res);
         match res {
             Ok(resp) => {
-                jobs.insert(id, JobState::Completed(resp));
+                jobs.insert(id, JobState::Completed(Box::new(resp)));
             }
             Err(e) => {
-                jobs.insert(id, JobState::Error(e.to_string()));
+                jobs.insert(id, JobState::Error(e));
             }
         }
+        state.queue.done(job);
     }
 }
 
@@ -146,195 +778,4748 @@ async fn health_handler() -> impl IntoResponse {
 
 async fn languages_handler(State(state): State<AppState>) -> impl IntoResponse {
     // Clone the inner Vec to avoid lifetime issues and Arc serialization concerns
-    let list: Vec<LanguageSummary> = state.langs_list.as_ref().clone();
+    let list: Vec<LanguageSummary> = state.languages().langs_list.clone();
     Json(list)
 }
 
-async fn enqueue_handler(
-    State(state): State<AppState>,
-    Json(req): Json<ExecuteRequest>,
-) -> Response {
-    // Validate requested language is available
-    if !state.available.contains(&req.language) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": format!("Unsupported or unavailable language: {}", req.language)
-            })),
-        )
-            .into_response();
-    }
-
-    // Normalize language casing to exact key
-    // (no-op because we expect correct key)
-    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
-    {
-        let mut jobs = state.jobs.write().await;
-        jobs.insert(id, JobState::Queued);
-    }
-    // Ensure code is written against the configured filename
-    // We don't modify request here; execution uses config info
-    if let Err(e) = state.sender.send((id, req.clone())).await {
-        let mut jobs = state.jobs.write().await;
-        jobs.insert(id, JobState::Error(format!("queue error: {}", e)));
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to enqueue job"})),
-        )
-            .into_response();
-    }
-
-    (StatusCode::ACCEPTED, Json(IdResponse { id })).into_response()
+// Every configured language, including those whose toolchain wasn't found, so clients can
+// explain why a submission was rejected
+async fn all_languages_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let registry = state.languages();
+    let mut list: Vec<LanguageAvailability> = registry
+        .configs
+        .iter()
+        .map(|(language, config)| LanguageAvailability {
+            display_name: config.display_name.clone(),
+            language: language.clone(),
+            available: registry.available.contains(language),
+        })
+        .collect();
+    list.sort_by(|a, b| a.language.cmp(&b.language));
+    Json(list)
 }
 
-async fn status_handler(State(state): State<AppState>, Path(id): Path<u64>) -> impl IntoResponse {
-    let jobs = state.jobs.read().await;
-    if let Some(st) = jobs.get(&id) {
-        let body = match st {
-            JobState::Queued => JobStatusResponse::Queued,
-            JobState::Running => JobStatusResponse::Running,
-            JobState::Completed(res) => JobStatusResponse::Completed {
-                result: res.clone(),
-            },
-            JobState::Error(err) => JobStatusResponse::Error { error: err.clone() },
-        };
-        (StatusCode::OK, Json(body)).into_response()
-    } else {
-        (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Job not found"})),
-        )
-            .into_response()
-    }
-}
+/// Largest accepted source file
+const MAX_CODE_BYTES: usize = 1024 * 1024;
+/// Most test cases accepted in one request
+const MAX_TESTCASES: usize = 1000;
+/// Per-case timeout when the test case doesn't set one
+const DEFAULT_CASE_TIMEOUT_MS: u64 = 5000;
+/// Environment variables a request may only set with `BUILDIT_ALLOW_UNSAFE_ENV`, because they
+/// change which programs, libraries or startup code the toolchain loads. Anything starting
+/// with `LD_` or `DYLD_` counts too.
+const UNSAFE_ENV_KEYS: &[&str] = &[
+    "PATH",
+    "PATHEXT",
+    "COMSPEC",
+    "BASH_ENV",
+    "ENV",
+    "NODE_OPTIONS",
+    "JAVA_TOOL_OPTIONS",
+    "_JAVA_OPTIONS",
+    "PYTHONPATH",
+    "PYTHONHOME",
+    "PYTHONSTARTUP",
+    "RUBYOPT",
+    "PERL5OPT",
+];
 
-async fn execute_request(req: &ExecuteRequest, state: &AppState) -> Result<ExecuteResponse> {
-    let cfg = state
-        .configs
-        .get(&req.language)
-        .ok_or_else(|| anyhow::anyhow!("Unknown language: {}", req.language))?
-        .clone();
+// Names are compared case-insensitively, as Windows does
+fn is_unsafe_env_key(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    key.starts_with("LD_") || key.starts_with("DYLD_") || UNSAFE_ENV_KEYS.contains(&key.as_str())
+}
+/// Most a case may write to stdout or stderr before it is killed
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+/// Most a case may write to a spilled stdout file before it is killed
+const DEFAULT_MAX_SPILL_BYTES: usize = 256 * 1024 * 1024;
 
-    let temp_dir = tempfile::tempdir()?;
-    let work_dir = temp_dir.path().to_path_buf();
+/// Longest a `POST /execute/sync` request may run before it is answered with a 504
+const DEFAULT_SYNC_TIMEOUT: time::Duration = time::Duration::from_secs(60);
 
-    // Always write using configured file_name so compilers/runtimes find it
-    let source_path = work_dir.join(&cfg.file_name);
-    tokio::fs::write(&source_path, &req.code).await?;
+/// Agent-wide limits on running jobs and their test cases.
+#[derive(Debug, Clone, Copy)]
+struct RunLimits {
+    max_parallel_cases: usize,    // cases of one job running at the same time
+    max_output_bytes: usize,      // per output stream of a case kept in memory
+    max_spill_bytes: usize,       // per spilled stdout file of a case
+    sync_timeout: time::Duration, // whole run of a submission to POST /execute/sync
+}
 
-    // Compile if needed
-    let mut compiled = false;
-    if let Some(compile_command) = &cfg.compile_command {
-        let mut cmd = if cfg!(windows) {
-            let mut c = Command::new("cmd");
-            c.args(&["/C", compile_command]);
-            c
-        } else {
-            Command::new(compile_command)
-        };
-        cmd.current_dir(&work_dir);
-        cmd.args(&cfg.compile_args);
-        let output = cmd.output().await?;
-        if !output.status.success() {
-            return Ok(ExecuteResponse {
-                compiled: false,
-                language: req.language.clone(),
-                status: Some(ExecutionStatus::CompileError),
-                message: Some(String::from_utf8_lossy(&output.stderr).to_string()),
-                results: vec![],
-                total_duration_ms: 0,
-            });
+impl Default for RunLimits {
+    // One case at a time, as the self-test and tests expect
+    fn default() -> Self {
+        Self {
+            max_parallel_cases: 1,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            max_spill_bytes: DEFAULT_MAX_SPILL_BYTES,
+            sync_timeout: DEFAULT_SYNC_TIMEOUT,
         }
-        compiled = true;
     }
+}
 
-    let mut results = Vec::with_capacity(req.testcases.len());
-    let mut total_duration_ms: u64 = 0;
-    for tc in &req.testcases {
-        let mut cmd = if cfg!(windows) {
-            let mut c = Command::new("cmd");
-            c.args(&["/C", &cfg.run_command]);
-            c
-        } else {
-            Command::new(&cfg.run_command)
+impl RunLimits {
+    // Cases run concurrently, one per core unless `BUILDIT_MAX_PARALLEL_CASES` says
+    // otherwise; `BUILDIT_MAX_OUTPUT_BYTES` caps their output, `BUILDIT_MAX_SPILL_BYTES`
+    // their spilled stdout and `BUILDIT_SYNC_TIMEOUT_SECS` the synchronous endpoint
+    fn from_env() -> Self {
+        let env = |name| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &usize| n > 0)
         };
-        cmd.current_dir(&work_dir);
-        cmd.args(&cfg.run_args);
-        cmd.stdin(std::process::Stdio::piped());
-        cmd.stdout(std::process::Stdio::piped());
-        cmd.stderr(std::process::Stdio::piped());
-
-        let mut child = cmd.spawn()?;
-        let start = Instant::now();
-
-        // Write stdin then close
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(tc.input.as_bytes()).await?;
+        Self {
+            max_parallel_cases: env("BUILDIT_MAX_PARALLEL_CASES").unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, usize::from)
+            }),
+            max_output_bytes: env("BUILDIT_MAX_OUTPUT_BYTES").unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
+            max_spill_bytes: env("BUILDIT_MAX_SPILL_BYTES").unwrap_or(DEFAULT_MAX_SPILL_BYTES),
+            sync_timeout: env("BUILDIT_SYNC_TIMEOUT_SECS")
+                .map_or(DEFAULT_SYNC_TIMEOUT, |secs| time::Duration::from_secs(secs as u64)),
         }
+    }
+}
 
-        // Capture stdout/stderr concurrently
-        let mut out_pipe = child.stdout.take().unwrap();
-        let mut err_pipe = child.stderr.take().unwrap();
-        let out_handle = tokio::spawn(async move {
-            let mut buf = Vec::new();
-            let _ = out_pipe.read_to_end(&mut buf).await;
-            buf
-        });
-        let err_handle = tokio::spawn(async move {
-            let mut buf = Vec::new();
-            let _ = err_pipe.read_to_end(&mut buf).await;
-            buf
-        });
+// Checks shared by POST /execute and POST /execute/validate. Collects every problem
+// instead of stopping at the first so clients can fix them in one go.
+fn validate_request(
+    req: &ExecuteRequest,
+    languages: &LanguageRegistry,
+    allow_unsafe_env: bool,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
 
-        let timeout_ms = tc.timeout_ms.unwrap_or(5000);
-        let mut timed_out = false;
-        let status = tokio::select! {
-            res = child.wait() => { res? }
-            _ = time::sleep(time::Duration::from_millis(timeout_ms)) => {
-                timed_out = true;
-                let _ = child.kill().await; // Best-effort
-                child.wait().await?
+    match languages.configs.get(&req.language) {
+        Some(cfg) if languages.available.contains(&req.language) => {
+            if cfg.compile_command.is_some() {
+                if let Err(e) = cfg.compile_args_for(req.optimization.as_deref()) {
+                    errors.push(FieldError::new("optimization", e.to_string()));
+                }
             }
-        };
+        }
+        _ => errors.push(FieldError::new(
+            "language",
+            format!("Unsupported or unavailable language: {}", req.language),
+        )),
+    }
+
+    let entry_file = languages.configs.get(&req.language).map(|cfg| &cfg.file_name);
+    let entry_in_files = req.files.iter().any(|f| Some(&f.name) == entry_file);
+    if entry_in_files {
+        if !req.code.is_empty() {
+            errors.push(FieldError::new(
+                "code",
+                "Code must be empty when `files` contains the entry file",
+            ));
+        }
+    } else if req.code.trim().is_empty() {
+        errors.push(FieldError::new("code", "Code must not be empty"));
+    }
+    let code_bytes = req.code.len() + req.files.iter().map(|f| f.content.len()).sum::<usize>();
+    if code_bytes > MAX_CODE_BYTES {
+        let field = if req.files.is_empty() { "code" } else { "files" };
+        errors.push(FieldError::new(
+            field,
+            format!("Code exceeds {} bytes", MAX_CODE_BYTES),
+        ));
+    }
+    let mut seen_names = HashSet::new();
+    for (i, file) in req.files.iter().enumerate() {
+        if !is_relative_file_name(&file.name) {
+            errors.push(FieldError::new(
+                format!("files[{}].name", i),
+                "File names must be relative paths without `..` components",
+            ));
+        } else if !seen_names.insert(&file.name) {
+            errors.push(FieldError::new(
+                format!("files[{}].name", i),
+                format!("Duplicate file name {}", file.name),
+            ));
+        }
+    }
+
+    if req.testcases.len() > MAX_TESTCASES {
+        errors.push(FieldError::new(
+            "testcases",
+            format!("At most {} test cases are allowed", MAX_TESTCASES),
+        ));
+    }
+    let mut seen_ids = HashSet::new();
+    for (i, tc) in req.testcases.iter().enumerate() {
+        if !seen_ids.insert(tc.id) {
+            errors.push(FieldError::new(
+                format!("testcases[{}].id", i),
+                format!("Duplicate test case id {}", tc.id),
+            ));
+        }
+        if tc.input_from_previous && i == 0 {
+            errors.push(FieldError::new(
+                format!("testcases[{}].input_from_previous", i),
+                "The first test case has no previous case to read from",
+            ));
+        }
+        if tc.input_from_previous && !tc.input.is_empty() {
+            errors.push(FieldError::new(
+                format!("testcases[{}].input", i),
+                "Input must be empty when it comes from the previous case",
+            ));
+        }
+        if tc.stdin_bytes().is_err() {
+            errors.push(FieldError::new(
+                format!("testcases[{}].input", i),
+                "Input is not valid base64",
+            ));
+        }
+        if tc.memory_limit_kb == Some(0) {
+            errors.push(FieldError::new(
+                format!("testcases[{}].memory_limit_kb", i),
+                "Memory limit must be greater than zero",
+            ));
+        }
+        if tc.soft_time_limit_ms == Some(0) {
+            errors.push(FieldError::new(
+                format!("testcases[{}].soft_time_limit_ms", i),
+                "Soft time limit must be greater than zero",
+            ));
+        }
+        if tc.timeout_ms == Some(0) {
+            errors.push(FieldError::new(
+                format!("testcases[{}].timeout_ms", i),
+                "Timeout must be greater than zero",
+            ));
+        }
+    }
+    if req.total_timeout_ms == Some(0) {
+        errors.push(FieldError::new(
+            "total_timeout_ms",
+            "Total timeout must be greater than zero",
+        ));
+    }
+    if let Some(bytes) = req.spill_output_bytes {
+        if bytes == 0 {
+            errors.push(FieldError::new(
+                "spill_output_bytes",
+                "Spill threshold must be greater than zero",
+            ));
+        }
+        if req.merge_output {
+            errors.push(FieldError::new(
+                "spill_output_bytes",
+                "Spilled output cannot be merged with stderr",
+            ));
+        }
+        if req.testcases.iter().any(|tc| tc.input_from_previous) {
+            errors.push(FieldError::new(
+                "spill_output_bytes",
+                "Spilled output cannot be piped into the next test case",
+            ));
+        }
+    }
+    // Sorted so the errors come out in a stable order
+    let mut env: Vec<(&String, &String)> = req.env.iter().collect();
+    env.sort();
+    for (key, value) in env {
+        let field = format!("env.{}", key);
+        if key.is_empty() || key.contains(['=', '\0']) || value.contains('\0') {
+            errors.push(FieldError::new(
+                field,
+                "Environment variable names must be non-empty and free of `=` and NUL, and \
+                 values free of NUL",
+            ));
+        } else if !allow_unsafe_env && is_unsafe_env_key(key) {
+            errors.push(FieldError::new(
+                field,
+                format!(
+                    "`{}` may not be set: it changes which programs or libraries are loaded",
+                    key
+                ),
+            ));
+        }
+    }
+    for group in req.group_weights.keys() {
+        if !req.testcases.iter().any(|tc| tc.group.as_ref() == Some(group)) {
+            errors.push(FieldError::new(
+                format!("group_weights.{}", group),
+                "No test case belongs to this group",
+            ));
+        }
+    }
+
+    if req.compile_only && req.reference.is_some() {
+        errors.push(FieldError::new(
+            "reference",
+            "A compile-only request runs nothing, including a reference solution",
+        ));
+    }
+    if let Some(reference) = &req.reference {
+        let language = reference.language.as_ref().unwrap_or(&req.language);
+        if !languages.available.contains(language) {
+            errors.push(FieldError::new(
+                "reference.language",
+                format!("Unsupported or unavailable language: {}", language),
+            ));
+        }
+        if reference.code.trim().is_empty() {
+            errors.push(FieldError::new("reference.code", "Code must not be empty"));
+        } else if reference.code.len() > MAX_CODE_BYTES {
+            errors.push(FieldError::new(
+                "reference.code",
+                format!("Code exceeds {} bytes", MAX_CODE_BYTES),
+            ));
+        }
+        for (i, tc) in req.testcases.iter().enumerate() {
+            if tc.expected.is_some() {
+                errors.push(FieldError::new(
+                    format!("testcases[{}].expected", i),
+                    "Expected output comes from the reference solution",
+                ));
+            }
+        }
+    }
+
+    errors
+}
+
+// Whether a submitted file `name` stays inside the work directory once joined onto it
+fn is_relative_file_name(name: &str) -> bool {
+    let path = std::path::Path::new(name);
+    !name.is_empty()
+        && path
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+// Fill in defaults so clients see exactly what would be executed
+fn normalize_request(mut req: ExecuteRequest, languages: &LanguageRegistry) -> ExecuteRequest {
+    let cfg = languages.configs.get(&req.language);
+    for tc in &mut req.testcases {
+        if let Some(cfg) = cfg {
+            if let Some(with_defaults) = with_language_defaults(tc, cfg) {
+                *tc = with_defaults;
+            }
+        }
+        tc.timeout_ms.get_or_insert(DEFAULT_CASE_TIMEOUT_MS);
+    }
+    req
+}
+
+// `tc` with the limits it leaves unset taken from its language's defaults, or `None` when
+// the language has nothing to add. The global defaults apply below these.
+fn with_language_defaults(tc: &TestCase, cfg: &LanguageConfig) -> Option<TestCase> {
+    let timeout_ms = tc.timeout_ms.or(cfg.default_timeout_ms);
+    let memory_limit_kb = tc.memory_limit_kb.or(cfg.default_memory_limit_kb);
+    if timeout_ms == tc.timeout_ms && memory_limit_kb == tc.memory_limit_kb {
+        return None;
+    }
+    Some(TestCase {
+        timeout_ms,
+        memory_limit_kb,
+        ..tc.clone()
+    })
+}
+
+fn invalid_request_response(errors: Vec<FieldError>) -> Response {
+    InvalidRequest::new(errors).into_response()
+}
+
+async fn validate_handler(
+    State(state): State<AppState>,
+    JsonBody(req): JsonBody<ExecuteRequest>,
+) -> Response {
+    let errors = validate_request(&req, &state.languages(), state.allow_unsafe_env);
+    if !errors.is_empty() {
+        return invalid_request_response(errors);
+    }
+    Json(serde_json::json!({
+        "valid": true,
+        "request": normalize_request(req, &state.languages()),
+    }))
+    .into_response()
+}
+
+async fn enqueue_handler(
+    State(state): State<AppState>,
+    JsonBody(req): JsonBody<ExecuteRequest>,
+) -> Response {
+    let errors = validate_request(&req, &state.languages(), state.allow_unsafe_env);
+    if !errors.is_empty() {
+        return invalid_request_response(errors);
+    }
+
+    // Normalize language casing to exact key
+    // (no-op because we expect correct key)
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+    state.activity.touch();
+    {
+        let mut jobs = state.jobs.write().await;
+        jobs.insert(id, JobState::Queued);
+    }
+    // Ensure code is written against the configured filename
+    // We don't modify request here; execution uses config info
+    if let Err(e) = state.queue.push(id, req) {
+        let mut jobs = state.jobs.write().await;
+        jobs.insert(id, JobState::Error(JobError::new(format!("queue error: {}", e))));
+        let status = if e == RusqError::QueueFull {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        return (
+            status,
+            Json(serde_json::json!({"error": "Failed to enqueue job"})),
+        )
+            .into_response();
+    }
+
+    (StatusCode::ACCEPTED, Json(IdResponse { id })).into_response()
+}
+
+// POST /execute/sync: run a submission right away and answer with its `ExecuteResponse`,
+// for clients that would rather wait than poll. It bypasses the job queue and store, so no
+// job id is created; a run that outlasts the sync timeout is killed and answered with 504.
+async fn sync_handler(
+    State(state): State<AppState>,
+    JsonBody(req): JsonBody<ExecuteRequest>,
+) -> Response {
+    let errors = validate_request(&req, &state.languages(), state.allow_unsafe_env);
+    if !errors.is_empty() {
+        return invalid_request_response(errors);
+    }
+    state.activity.touch();
+    let req = normalize_request(req, &state.languages());
+    let timeout = state.limits.sync_timeout;
+    match time::timeout(timeout, execute_request(&req, &state, None)).await {
+        Ok(Ok(resp)) => Json(resp).into_response(),
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(JobStatusResponse::Error {
+                error: e.message,
+                work_dir: e.work_dir,
+            }),
+        )
+            .into_response(),
+        Err(_) => (
+            StatusCode::GATEWAY_TIMEOUT,
+            Json(serde_json::json!({
+                "error": format!("Execution exceeded {} ms", timeout.as_millis()),
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Longest accepted line of a bulk request: a maximal submission plus JSON escaping
+const MAX_BULK_LINE_BYTES: usize = 4 * MAX_CODE_BYTES;
+/// Submissions of one bulk request that are read and waiting on a run slot at the same time
+const BULK_CONCURRENCY: usize = 4;
+/// Submissions executing at the same time across the job queue, sync and bulk endpoints,
+/// unless `BUILDIT_MAX_CONCURRENT_RUNS` says otherwise
+const DEFAULT_MAX_CONCURRENT_RUNS: usize = 4;
+
+/// One line of a bulk request: a submission plus a client-chosen id echoed in its result.
+#[derive(Debug, Deserialize)]
+struct BulkItem {
+    #[serde(default)]
+    id: serde_json::Value,
+    #[serde(flatten)]
+    request: ExecuteRequest,
+}
+
+/// One line of a bulk response.
+#[derive(Debug, Serialize)]
+struct BulkResult {
+    id: serde_json::Value,
+    #[serde(flatten)]
+    outcome: BulkOutcome,
+}
 
-        let out_bytes = out_handle.await.unwrap_or_else(|_| Vec::new());
-        let err_bytes = err_handle.await.unwrap_or_else(|_| Vec::new());
-        let stdout = String::from_utf8_lossy(&out_bytes).to_string();
-        let stderr = String::from_utf8_lossy(&err_bytes).to_string();
-        let exit_code = status.code();
-        let success = status.success();
-
-        let duration_ms = start.elapsed().as_millis() as u64;
-        total_duration_ms += duration_ms;
-
-        let ok = success && !timed_out;
-        let passed = match &tc.expected {
-            Some(exp) => stdout == *exp,
-            None => false,
-        };
-
-        results.push(CaseResult {
-            id: tc.id,
-            ok,
-            passed,
-            input: tc.input.clone(),
-            expected: tc.expected.clone(),
-            stdout,
-            stderr,
-            timed_out,
-            duration_ms,
-            memory_kb: 0,
-            exit_code,
-            term_signal: None,
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum BulkOutcome {
+    Completed(Box<ExecuteResponse>),
+    Failed {
+        error: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        errors: Vec<FieldError>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        work_dir: Option<String>,
+    },
+}
+
+impl BulkResult {
+    fn failed(id: serde_json::Value, error: impl Into<String>, errors: Vec<FieldError>) -> Self {
+        Self {
+            id,
+            outcome: BulkOutcome::Failed {
+                error: error.into(),
+                errors,
+                work_dir: None,
+            },
+        }
+    }
+}
+
+// Split a streamed body into lines as chunks arrive, so the first submissions can start
+// before the client has finished sending. A line over `max_line` bytes or a broken body
+// ends the stream with an error.
+fn ndjson_lines(body: Body, max_line: usize) -> impl Stream<Item = Result<String, String>> {
+    let state = (body.into_data_stream(), Vec::new(), false);
+    futures::stream::unfold(state, move |(mut data, mut buf, mut done)| async move {
+        loop {
+            if let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buf.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line).into_owned();
+                return Some((Ok(line), (data, buf, done)));
+            }
+            if done {
+                if buf.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&buf).into_owned();
+                return Some((Ok(line), (data, Vec::new(), done)));
+            }
+            if buf.len() > max_line {
+                let error = format!("Line exceeds {} bytes", max_line);
+                return Some((Err(error), (data, Vec::new(), true)));
+            }
+            match data.next().await {
+                Some(Ok(chunk)) => buf.extend_from_slice(&chunk),
+                Some(Err(e)) => {
+                    let error = format!("Failed to read request body: {}", e);
+                    return Some((Err(error), (data, Vec::new(), true)));
+                }
+                None => done = true,
+            }
+        }
+    })
+}
+
+async fn run_bulk_line(
+    state: &AppState,
+    client: (Option<&str>, IpAddr),
+    line_no: usize,
+    line: Result<String, String>,
+) -> BulkResult {
+    let line = match line {
+        Ok(line) => line,
+        Err(e) => return BulkResult::failed(serde_json::Value::Null, e, vec![]),
+    };
+    let item: BulkItem = match serde_json::from_str(&line) {
+        Ok(item) => item,
+        Err(e) => {
+            // Still echo the id when only the submission itself is malformed
+            let id = serde_json::from_str::<serde_json::Value>(&line)
+                .ok()
+                .and_then(|v| v.get("id").cloned())
+                .unwrap_or_default();
+            let error = format!("Line {}: invalid request: {}", line_no, e);
+            return BulkResult::failed(id, error, vec![]);
+        }
+    };
+
+    // Each submission costs a token, just as if it had been sent to POST /execute
+    if let Some(limiter) = &state.rate_limiter {
+        let (api_key, ip) = client;
+        if let Err(rejection) = limiter.check(api_key, ip, Instant::now()) {
+            let error = match rejection {
+                Rejection::UnknownKey => "Unknown API key".to_string(),
+                Rejection::Throttled(retry_after) => format!(
+                    "Rate limit exceeded; retry after {} s",
+                    retry_after_secs(retry_after)
+                ),
+            };
+            return BulkResult::failed(item.id, error, vec![]);
+        }
+    }
+
+    let errors = validate_request(&item.request, &state.languages(), state.allow_unsafe_env);
+    if !errors.is_empty() {
+        return BulkResult::failed(item.id, errors[0].message.clone(), errors);
+    }
+    let req = normalize_request(item.request, &state.languages());
+    let outcome = match execute_request(&req, state, None).await {
+        Ok(resp) => BulkOutcome::Completed(Box::new(resp)),
+        Err(e) => BulkOutcome::Failed {
+            error: e.message,
+            errors: vec![],
+            work_dir: e.work_dir,
+        },
+    };
+    BulkResult {
+        id: item.id,
+        outcome,
+    }
+}
+
+// POST /execute/bulk: one `ExecuteRequest` (plus an `id`) per NDJSON line in, one result
+// per line out in completion order. Submissions run directly rather than through the job
+// queue, sharing its run slots, and neither the requests nor the results are buffered as a
+// whole. Each line is charged to the client's rate limit.
+async fn bulk_handler(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: header::HeaderMap,
+    body: Body,
+) -> Response {
+    let api_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    if let Some(limiter) = &state.rate_limiter {
+        if !limiter.knows_key(api_key.as_deref()) {
+            return rejection_response(Rejection::UnknownKey);
+        }
+    }
+    let results = ndjson_lines(body, MAX_BULK_LINE_BYTES)
+        .enumerate()
+        .filter(|(_, line)| {
+            futures::future::ready(!matches!(line, Ok(line) if line.trim().is_empty()))
+        })
+        .map(move |(i, line)| {
+            let state = state.clone();
+            let api_key = api_key.clone();
+            async move {
+                let client = (api_key.as_deref(), addr.ip());
+                run_bulk_line(&state, client, i + 1, line).await
+            }
+        })
+        .buffer_unordered(BULK_CONCURRENCY)
+        .map(|result| {
+            let mut line = serde_json::to_vec(&result).expect("bulk results serialize to JSON");
+            line.push(b'\n');
+            Ok::<_, Infallible>(Bytes::from(line))
         });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(results),
+    )
+        .into_response()
+}
+
+/// Upper bound on one language's canary, compile step included
+const SELFTEST_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+/// Canaries of one self-test that run at the same time
+const SELFTEST_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Serialize)]
+struct SelfTestResult {
+    language: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SelfTestResponse {
+    all_ok: bool,
+    languages: Vec<SelfTestResult>,
+    /// Available languages without a canary program
+    skipped: Vec<String>,
+}
+
+// Why a canary run didn't print `hello`, or `None` when it did
+fn canary_error(resp: &ExecuteResponse) -> Option<String> {
+    if matches!(resp.status, Some(ExecutionStatus::CompileError)) {
+        let message = resp.message.as_deref().unwrap_or_default().trim();
+        return Some(format!("Compile error: {}", message));
+    }
+    let Some(result) = resp.results.first() else {
+        return Some("Canary produced no result".to_string());
+    };
+    if result.passed == Some(true) {
+        return None;
+    }
+    let detail = if result.stderr.trim().is_empty() {
+        format!("stdout: {:?}", result.stdout)
+    } else {
+        format!("stderr: {}", result.stderr.trim())
+    };
+    Some(format!("{:?} ({})", result.verdict, detail))
+}
+
+async fn run_canary(
+    language: String,
+    cfg: LanguageConfig,
+    code: String,
+    work_root: Option<std::path::PathBuf>,
+    shutting_down: Arc<AtomicBool>,
+    run_slots: Arc<Semaphore>,
+) -> SelfTestResult {
+    let req = ExecuteRequest {
+        language: language.clone(),
+        code,
+        testcases: vec![TestCase {
+            id: 1,
+            input: String::new(),
+            expected: Some("hello\n".to_string()),
+            timeout_ms: Some(DEFAULT_CASE_TIMEOUT_MS),
+            input_from_previous: false,
+            group: None,
+            input_encoding: InputEncoding::Text,
+            memory_limit_kb: None,
+            soft_time_limit_ms: None,
+            args: vec![],
+        }],
+        priority: Default::default(),
+        comparison: Comparison::default(),
+        optimization: None,
+        group_weights: HashMap::new(),
+        seed: None,
+        echo_stdin: false,
+        merge_output: false,
+        reference: None,
+        spill_output_bytes: None,
+        env: HashMap::new(),
+        compile_only: false,
+        files: vec![],
+        total_timeout_ms: None,
+    };
+
+    // Counts against the same limit as submissions; waiting for a slot isn't timed
+    let _slot = run_slots.acquire().await.expect("run slots are never closed");
+    let start = Instant::now();
+    // Bypass the compile cache: the point is to exercise the toolchain itself
+    let run = async {
+        let temp_dir = create_work_dir(work_root.as_deref())?;
+        let limits = RunLimits::default();
+        let dir = temp_dir.path();
+        execute_in_dir(&req, &cfg, dir, None, &shutting_down, None, limits, None).await
+    };
+    let error = match time::timeout(SELFTEST_TIMEOUT, run).await {
+        Ok(Ok(resp)) => canary_error(&resp),
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("Timed out after {}s", SELFTEST_TIMEOUT.as_secs())),
+    };
+    SelfTestResult {
+        language,
+        ok: error.is_none(),
+        error,
+        duration_ms: start.elapsed().as_millis() as u64,
     }
+}
 
-    Ok(ExecuteResponse {
-        compiled,
-        language: req.language.clone(),
-        status: Some(ExecutionStatus::Success),
-        message: None,
-        results,
-        total_duration_ms,
+// POST /selftest (admin): compile and run every available language's canary and report
+// which languages really work end to end, not just answer a version check.
+async fn selftest_handler(State(state): State<AppState>, headers: header::HeaderMap) -> Response {
+    if let Some(denied) = require_admin(&state, &headers) {
+        return denied;
+    }
+    let _active = state.activity.start();
+    let languages = state.languages();
+    let mut skipped = Vec::new();
+    let mut canaries = Vec::new();
+    for name in &languages.available {
+        let Some(cfg) = languages.configs.get(name) else {
+            continue;
+        };
+        match &cfg.canary {
+            Some(code) => canaries.push(run_canary(
+                name.clone(),
+                cfg.clone(),
+                code.clone(),
+                state.work_root.clone(),
+                state.shutting_down.clone(),
+                state.run_slots.clone(),
+            )),
+            None => skipped.push(name.clone()),
+        }
+    }
+
+    let mut results: Vec<SelfTestResult> = futures::stream::iter(canaries)
+        .buffer_unordered(SELFTEST_CONCURRENCY)
+        .collect()
+        .await;
+    results.sort_by(|a, b| a.language.cmp(&b.language));
+    skipped.sort();
+    Json(SelfTestResponse {
+        all_ok: results.iter().all(|r| r.ok),
+        languages: results,
+        skipped,
+    })
+    .into_response()
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> Json<MetricsResponse> {
+    let jobs = state.jobs.read().await;
+    let mut counts = JobCounts::default();
+    for job in jobs.jobs.values() {
+        match job {
+            JobState::Queued => counts.queued += 1,
+            JobState::Running(_) => counts.running += 1,
+            JobState::Completed(_) => counts.completed += 1,
+            JobState::Error(_) => counts.error += 1,
+        }
+    }
+    let depths = state.queue.queue.depths();
+    Json(MetricsResponse {
+        retained_jobs: jobs.retained_finished(),
+        max_retained_jobs: jobs.max_finished,
+        jobs: counts,
+        queue_depth: depths.critical + depths.high + depths.normal + depths.low,
+        jobs_processed: state.pipeline.jobs_processed.load(Ordering::Relaxed),
+        average_total_duration_ms: state.pipeline.average_duration_ms(),
+    })
+}
+
+async fn queue_metrics_handler(State(state): State<AppState>) -> Json<QueueMetricsResponse> {
+    Json(QueueMetricsResponse {
+        metrics: state.queue.queue.metrics(),
+        depths: state.queue.queue.depths(),
     })
 }
+
+/// Header identifying a client for rate limiting; callers without one are told apart by IP.
+/// Only keys configured in `BUILDIT_RATE_LIMIT_API_KEYS` are accepted.
+const API_KEY_HEADER: &str = "x-api-key";
+
+// Reject a client's request with 429 and `Retry-After` once it goes over its rate limit, or
+// with 401 when it identifies itself with an API key that isn't configured
+async fn rate_limit(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if let Some(limiter) = &state.rate_limiter {
+        let api_key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok());
+        if let Err(rejection) = limiter.check(api_key, addr.ip(), Instant::now()) {
+            return rejection_response(rejection);
+        }
+    }
+    next.run(req).await
+}
+
+// Whole seconds a throttled client is told to wait, never zero
+fn retry_after_secs(retry_after: time::Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
+fn rejection_response(rejection: Rejection) -> Response {
+    match rejection {
+        Rejection::UnknownKey => (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Unknown API key" })),
+        )
+            .into_response(),
+        Rejection::Throttled(retry_after) => {
+            let secs = retry_after_secs(retry_after);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, secs.to_string())],
+                Json(serde_json::json!({
+                    "error": "Rate limit exceeded",
+                    "retry_after_secs": secs,
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+// Check the `Authorization: Bearer <token>` header against `BUILDIT_ADMIN_TOKEN`. Returns
+// the error response to send when the caller is not allowed in.
+fn require_admin(state: &AppState, headers: &header::HeaderMap) -> Option<Response> {
+    let (status, error) = match &state.admin_token {
+        None => (
+            StatusCode::FORBIDDEN,
+            "Admin endpoints are disabled; set BUILDIT_ADMIN_TOKEN to enable them",
+        ),
+        Some(expected) => {
+            let provided = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "));
+            if provided == Some(expected.as_str()) {
+                return None;
+            }
+            (StatusCode::UNAUTHORIZED, "Missing or invalid admin token")
+        }
+    };
+    Some((status, Json(serde_json::json!({ "error": error }))).into_response())
+}
+
+// POST /jobs/drain (admin): fail every queued job with "drained" without running it. A job
+// that is already running is left to finish.
+async fn drain_handler(State(state): State<AppState>, headers: header::HeaderMap) -> Response {
+    if let Some(denied) = require_admin(&state, &headers) {
+        return denied;
+    }
+    let ids = state.queue.drain();
+    let mut jobs = state.jobs.write().await;
+    for id in &ids {
+        // A cancelled job stays cancelled
+        if !jobs.get(id).is_some_and(JobState::is_finished) {
+            jobs.insert(*id, JobState::Error(JobError::new("drained")));
+        }
+    }
+    Json(serde_json::json!({ "drained": ids.len() })).into_response()
+}
+
+// POST /languages/reload (admin): re-read the language configs, the HTTP equivalent of
+// SIGHUP. A file that fails to parse leaves the current languages in place.
+async fn reload_languages_handler(
+    State(state): State<AppState>,
+    headers: header::HeaderMap,
+) -> Response {
+    if let Some(denied) = require_admin(&state, &headers) {
+        return denied;
+    }
+    if let Err(e) = reload_languages(&state, state.languages_path.as_deref()).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": format!("Failed to reload language configs: {:#}", e)
+            })),
+        )
+            .into_response();
+    }
+    let mut available: Vec<String> = state.languages().available.iter().cloned().collect();
+    available.sort();
+    Json(serde_json::json!({ "available": available })).into_response()
+}
+
+// DELETE /job/:id: cancel a job. A queued job fails with "cancelled" without running; a
+// running one has its current program killed, skips its remaining cases and then fails the
+// same way.
+async fn cancel_handler(State(state): State<AppState>, Path(id): Path<u64>) -> Response {
+    let mut jobs = state.jobs.write().await;
+    match jobs.get(&id) {
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Job not found"})),
+        )
+            .into_response(),
+        Some(JobState::Queued) => {
+            let error = JobError::new("cancelled");
+            let body = JobStatusResponse::Error {
+                error: error.message.clone(),
+                work_dir: None,
+            };
+            jobs.insert(id, JobState::Error(error));
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        // The worker records the cancellation once the run has stopped
+        Some(JobState::Running(cancel)) => {
+            cancel.notify_one();
+            (StatusCode::ACCEPTED, Json(JobStatusResponse::Running)).into_response()
+        }
+        Some(_) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "Job has already finished"})),
+        )
+            .into_response(),
+    }
+}
+
+async fn status_handler(State(state): State<AppState>, Path(id): Path<u64>) -> impl IntoResponse {
+    let jobs = state.jobs.read().await;
+    if let Some(st) = jobs.get(&id) {
+        let body = match st {
+            JobState::Queued => JobStatusResponse::Queued,
+            JobState::Running(_) => JobStatusResponse::Running,
+            JobState::Completed(res) => JobStatusResponse::Completed {
+                result: res.clone(),
+            },
+            JobState::Error(err) => JobStatusResponse::Error {
+                error: err.message.clone(),
+                work_dir: err.work_dir.clone(),
+            },
+        };
+        (StatusCode::OK, Json(body)).into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Job not found"})),
+        )
+            .into_response()
+    }
+}
+
+// Stream the full stdout of a case whose output was spilled to a file, for as long as
+// its job is retained
+async fn spilled_stdout_handler(
+    State(state): State<AppState>,
+    Path((id, case_id)): Path<(u64, i32)>,
+) -> impl IntoResponse {
+    let path = {
+        let jobs = state.jobs.read().await;
+        jobs.get(&id)
+            .and(jobs.spill_dir(id))
+            .map(|dir| spill_path(&dir, case_id))
+    };
+    let file = match path {
+        Some(path) => tokio::fs::File::open(path).await.ok(),
+        None => None,
+    };
+    let Some(file) = file else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "No spilled output for this test case"})),
+        )
+            .into_response();
+    };
+
+    let chunks = futures::stream::unfold(file, |mut file| async move {
+        let mut chunk = vec![0u8; 64 * 1024];
+        match file.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(n) => {
+                chunk.truncate(n);
+                Some((Ok(Bytes::from(chunk)), file))
+            }
+            Err(e) => Some((Err(e), file)),
+        }
+    });
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+/// Time allowed for compiling the reference solution, on top of its cases' timeouts
+const REFERENCE_COMPILE_ALLOWANCE: time::Duration = time::Duration::from_secs(30);
+
+// Run the reference solution on every case's input and return `req` with the reference's
+// output as each case's expected output. The whole reference run is bounded by its cases'
+// timeouts plus a compile allowance, so a submission costs at most about twice its budget.
+// Returns `None` when the submission's total timeout (`deadline`) passes first.
+async fn with_reference_outputs(
+    req: &ExecuteRequest,
+    reference: &ReferenceSolution,
+    state: &AppState,
+    deadline: Option<Instant>,
+) -> std::result::Result<Option<ExecuteRequest>, JobError> {
+    let language = reference.language.clone().unwrap_or_else(|| req.language.clone());
+    let cfg = state
+        .languages()
+        .configs
+        .get(&language)
+        .ok_or_else(|| anyhow::anyhow!("Unknown reference language: {}", language))?
+        .clone();
+    let reference_req = ExecuteRequest {
+        language,
+        code: reference.code.clone(),
+        testcases: req
+            .testcases
+            .iter()
+            .map(|tc| TestCase {
+                expected: None,
+                ..tc.clone()
+            })
+            .collect(),
+        priority: req.priority,
+        comparison: Comparison::default(),
+        optimization: None,
+        group_weights: HashMap::new(),
+        seed: req.seed,
+        echo_stdin: false,
+        merge_output: req.merge_output,
+        reference: None,
+        spill_output_bytes: None,
+        env: req.env.clone(),
+        compile_only: false,
+        files: vec![],
+        total_timeout_ms: None,
+    };
+
+    let budget_ms: u64 = req
+        .testcases
+        .iter()
+        .map(|tc| {
+            let timeout_ms = tc.timeout_ms.or(cfg.default_timeout_ms);
+            timeout_ms.unwrap_or(DEFAULT_CASE_TIMEOUT_MS)
+        })
+        .sum();
+    let budget = REFERENCE_COMPILE_ALLOWANCE + time::Duration::from_millis(budget_ms);
+    let temp_dir = create_work_dir(state.work_root.as_deref())?;
+    let cache = state.compile_cache.as_deref();
+    let shutting_down = &state.shutting_down;
+    let dir = temp_dir.path();
+    let limits = state.limits;
+    let run = execute_in_dir(&reference_req, &cfg, dir, cache, shutting_down, None, limits, None);
+    let budget_end = Instant::now() + budget;
+    let stop = deadline.map_or(budget_end, |deadline| deadline.min(budget_end));
+    let Ok(resp) = time::timeout_at(stop.into(), run).await else {
+        if stop < budget_end {
+            return Ok(None);
+        }
+        return Err(JobError::new(format!(
+            "Reference solution did not finish within {} ms",
+            budget.as_millis()
+        )));
+    };
+    let resp = resp?;
+
+    if matches!(resp.status, Some(ExecutionStatus::CompileError)) {
+        let message = resp.message.unwrap_or_default();
+        return Err(JobError::new(format!(
+            "Reference solution failed to compile: {}",
+            message.trim()
+        )));
+    }
+    let mut filled = req.clone();
+    for (tc, result) in filled.testcases.iter_mut().zip(&resp.results) {
+        // A wrong key would mark every correct submission as failing
+        if !result.ok {
+            return Err(JobError::new(format!(
+                "Reference solution failed on test case {}: {:?}",
+                tc.id, result.verdict
+            )));
+        }
+        let output = result.output.as_ref().unwrap_or(&result.stdout);
+        tc.expected = Some(output.clone());
+    }
+    Ok(Some(filled))
+}
+
+// Run one submission. Spilled outputs go to `spill_dir` when given, so they outlive the
+// work directory; otherwise only their preview is kept.
+async fn execute_request(
+    req: &ExecuteRequest,
+    state: &AppState,
+    spill_dir: Option<&std::path::Path>,
+) -> std::result::Result<ExecuteResponse, JobError> {
+    // Shared by the worker, sync and bulk submissions, so none can run more at once
+    let _slot = state.run_slots.acquire().await.expect("run slots are never closed");
+    let _active = state.activity.start();
+    // The total timeout covers the reference run as well, so its clock starts before it
+    let deadline = req
+        .total_timeout_ms
+        .map(|ms| Instant::now() + time::Duration::from_millis(ms));
+    let filled;
+    let req = match &req.reference {
+        Some(reference) => match with_reference_outputs(req, reference, state, deadline).await? {
+            Some(with_expected) => {
+                filled = with_expected;
+                &filled
+            }
+            None => {
+                let message = total_timeout_message(req);
+                return Ok(not_run_response(req, ExecutionStatus::Timeout, message, None));
+            }
+        },
+        None => req,
+    };
+    let cfg = state
+        .languages()
+        .configs
+        .get(&req.language)
+        .ok_or_else(|| anyhow::anyhow!("Unknown language: {}", req.language))?
+        .clone();
+
+    let temp_dir = create_work_dir(state.work_root.as_deref())?;
+    let cache = state.compile_cache.as_deref();
+    let shutting_down = &state.shutting_down;
+    let limits = state.limits;
+    let dir = temp_dir.path();
+    let run = execute_in_dir(req, &cfg, dir, cache, shutting_down, spill_dir, limits, deadline);
+    let mut result = run.await;
+    if state.report_commands {
+        if let Ok(resp) = &mut result {
+            resp.commands = Some(resolved_commands(req, &cfg, temp_dir.path()));
+        }
+    }
+    if !state.retain_work_dir {
+        return result.map_err(JobError::from);
+    }
+
+    // Keep the directory around (and say where) only when the run went wrong
+    match result {
+        Ok(resp) if !matches!(resp.status, Some(ExecutionStatus::CompileError)) => Ok(resp),
+        Ok(mut resp) => {
+            resp.work_dir = Some(temp_dir.keep().display().to_string());
+            Ok(resp)
+        }
+        Err(e) => Err(JobError {
+            message: e.to_string(),
+            work_dir: Some(temp_dir.keep().display().to_string()),
+        }),
+    }
+}
+
+// Create the scratch directory for one run under `root` (the system temp dir by default).
+// The usual failures on locked-down machines get a message saying what to fix.
+fn create_work_dir(root: Option<&std::path::Path>) -> Result<tempfile::TempDir> {
+    let root = root.map_or_else(std::env::temp_dir, std::path::Path::to_path_buf);
+    tempfile::tempdir_in(&root).map_err(|e| anyhow::anyhow!(work_dir_error(&root, &e)))
+}
+
+fn work_dir_error(root: &std::path::Path, e: &std::io::Error) -> String {
+    use std::io::ErrorKind;
+    let reason = match e.kind() {
+        ErrorKind::StorageFull => "the disk is full",
+        ErrorKind::PermissionDenied => "permission denied",
+        ErrorKind::ReadOnlyFilesystem => "the file system is read-only",
+        ErrorKind::NotFound => "the directory does not exist",
+        ErrorKind::NotADirectory => "the path is not a directory",
+        _ => "the directory is not writable",
+    };
+    format!(
+        "Cannot create a work directory in {}: {} ({}). Free up space there, make it \
+         writable, or set BUILDIT_WORK_ROOT to a writable directory",
+        root.display(),
+        reason,
+        e
+    )
+}
+
+// Run `req` in `work_dir`, giving up on whatever is left once `deadline` (the submission's
+// total timeout, started by the caller) has passed
+#[allow(clippy::too_many_arguments)]
+async fn execute_in_dir(
+    req: &ExecuteRequest,
+    cfg: &LanguageConfig,
+    work_dir: &std::path::Path,
+    cache: Option<&CompileCache>,
+    shutting_down: &AtomicBool,
+    spill_dir: Option<&std::path::Path>,
+    limits: RunLimits,
+    deadline: Option<Instant>,
+) -> Result<ExecuteResponse> {
+    // Always write using configured file_name so compilers/runtimes find it
+    let source_path = work_dir.join(&cfg.file_name);
+    tokio::fs::write(&source_path, &req.code).await?;
+    // Written after `code`, so an entry file given in `files` takes its place
+    for file in &req.files {
+        anyhow::ensure!(
+            is_relative_file_name(&file.name),
+            "Source file name {:?} must be a relative path inside the work directory",
+            file.name
+        );
+        let path = work_dir.join(&file.name);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &file.content).await?;
+    }
+
+    // Compile if needed, reusing the artifacts of an identical earlier build when cached
+    let mut compiled = false;
+    let mut compile_exit_code = None;
+    let mut diagnostics = None; // compiler output, reported by compile-only requests
+    if let Some(compile_command) = &cfg.compile_command {
+        let compile_args = cfg.compile_args_for(req.optimization.as_deref())?;
+        let cache_key = CompileCache::key(
+            &req.language,
+            compile_command,
+            &compile_args,
+            &cfg.file_name,
+            &req.code,
+            &req.files,
+        );
+        // The environment can change what gets built, so such builds are not cached
+        let cache = cache.filter(|_| req.env.is_empty());
+        // A cached build has no diagnostics to show, so compile-only requests always build
+        if let Some(cache) = cache.filter(|_| !req.compile_only) {
+            compiled = cache.restore(&cache_key, work_dir).await.unwrap_or_else(|e| {
+                eprintln!("Compile cache lookup failed: {}", e);
+                false
+            });
+        }
+
+        if !compiled {
+            let mut cmd = spawnable(&compile_argv(compile_command, &compile_args));
+            cmd.envs(&req.env);
+            cmd.current_dir(work_dir);
+            // A caller that gives up on the run (e.g. the self-test timeout) stops the compiler
+            cmd.kill_on_drop(true);
+            let output = cmd.output();
+            let output = match deadline {
+                Some(deadline) => time::timeout_at(deadline.into(), output).await.ok(),
+                None => Some(output.await),
+            };
+            let Some(output) = output else {
+                let message = total_timeout_message(req);
+                return Ok(not_run_response(req, ExecutionStatus::Timeout, message, None));
+            };
+            let output = output?;
+            compile_exit_code = output.status.code();
+            // Some compilers, like tsc, print their diagnostics on stdout
+            let printed = if output.stderr.is_empty() {
+                &output.stdout
+            } else {
+                &output.stderr
+            };
+            let printed = String::from_utf8_lossy(printed).to_string();
+            if !output.status.success() {
+                let status = ExecutionStatus::CompileError;
+                return Ok(not_run_response(req, status, printed, compile_exit_code));
+            }
+            if let Some(cache) = cache {
+                if let Err(e) = cache.store(&cache_key, work_dir, &cfg.file_name).await {
+                    eprintln!("Failed to cache compiled artifacts: {}", e);
+                }
+            }
+            compiled = true;
+            diagnostics = Some(printed).filter(|text| !text.is_empty());
+        }
+    }
+
+    // Nothing is run, and there is nothing to compile for interpreted languages
+    if req.compile_only {
+        return Ok(ExecuteResponse {
+            compiled,
+            language: req.language.clone(),
+            status: Some(ExecutionStatus::Success),
+            message: diagnostics,
+            results: vec![],
+            total_duration_ms: 0,
+            work_dir: None,
+            compile_exit_code,
+            platform: current_platform().to_string(),
+            agent_version: AGENT_VERSION.to_string(),
+            subtasks: None,
+            scoreboard: Scoreboard::default(),
+            commands: None,
+        });
+    }
+
+    let spill_dir = spill_dir.unwrap_or(work_dir);
+    if req.spill_output_bytes.is_some() {
+        tokio::fs::create_dir_all(spill_dir).await?;
+    }
+
+    // A case reading its predecessor's output has to wait for it, so such cases form a chain
+    // run in order. Separate chains run concurrently, at most `max_parallel_cases` at a time.
+    let permits = &tokio::sync::Semaphore::new(limits.max_parallel_cases.max(1));
+    let cancelled = &AtomicBool::new(false);
+    let expired = &AtomicBool::new(false); // the total timeout passed
+    let chains = req
+        .testcases
+        .chunk_by(|_, next| next.input_from_previous)
+        .map(|chain| async move {
+            let mut results = Vec::with_capacity(chain.len());
+            let mut previous_stdout: Option<String> = None;
+            for tc in chain {
+                let _permit = permits.acquire().await?;
+                // Checked before every case so a shutdown doesn't wait out the whole job
+                if cancelled.load(Ordering::SeqCst) || shutting_down.load(Ordering::SeqCst) {
+                    cancelled.store(true, Ordering::SeqCst);
+                    results.push(CaseResult::not_run(tc));
+                    continue;
+                }
+                let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                if remaining.is_some_and(|left| left.is_zero()) {
+                    expired.store(true, Ordering::SeqCst);
+                    results.push(CaseResult::not_run(tc));
+                    continue;
+                }
+                let with_defaults;
+                let tc = match with_language_defaults(tc, cfg) {
+                    Some(tc) => {
+                        with_defaults = tc;
+                        &with_defaults
+                    }
+                    None => tc,
+                };
+                let piped;
+                let tc = if tc.input_from_previous {
+                    let input = previous_stdout.take().ok_or_else(|| {
+                        anyhow::anyhow!("Test case {} has no previous case to read from", tc.id)
+                    })?;
+                    piped = TestCase {
+                        input,
+                        input_encoding: InputEncoding::Text,
+                        ..tc.clone()
+                    };
+                    &piped
+                } else {
+                    tc
+                };
+                // A case may not run past the total timeout
+                let remaining_ms = remaining.map(|left| (left.as_millis() as u64).max(1));
+                let cut_short = remaining_ms.filter(|&left| {
+                    left < tc.timeout_ms.unwrap_or(DEFAULT_CASE_TIMEOUT_MS)
+                });
+                let shortened;
+                let tc = match cut_short {
+                    Some(left) => {
+                        shortened = TestCase {
+                            timeout_ms: Some(left),
+                            ..tc.clone()
+                        };
+                        &shortened
+                    }
+                    None => tc,
+                };
+                let spill = req.spill_output_bytes.map(|preview_bytes| Spill {
+                    path: spill_path(spill_dir, tc.id),
+                    preview_bytes,
+                    max_bytes: limits.max_spill_bytes,
+                });
+                let opts = CaseOptions {
+                    comparison: req.comparison,
+                    seed: req.seed,
+                    env: Some(&req.env),
+                    merge_output: req.merge_output,
+                    spill: spill.as_ref(),
+                    max_output_bytes: limits.max_output_bytes,
+                };
+                let mut result = run_testcase(cfg, work_dir, tc, opts).await?;
+                if result.timed_out && cut_short.is_some() {
+                    expired.store(true, Ordering::SeqCst);
+                }
+                if req.echo_stdin {
+                    result.stdin_base64 = Some(BASE64_STANDARD.encode(tc.stdin_bytes()?));
+                }
+                // Pipelines forward whatever was graded: the merged text when output is merged
+                previous_stdout =
+                    Some(result.output.clone().unwrap_or_else(|| result.stdout.clone()));
+                results.push(result);
+            }
+            Ok::<_, anyhow::Error>(results)
+        });
+    // Chains are consecutive runs of cases, so joining them keeps the request's order
+    let results: Vec<CaseResult> = futures::future::try_join_all(chains)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect();
+    let total_duration_ms = results.iter().map(|r| r.duration_ms).sum();
+    let cancelled = cancelled.load(Ordering::SeqCst);
+    let expired = expired.load(Ordering::SeqCst);
+
+    // An empty run is not the same as "everything passed"
+    let status = if expired {
+        ExecutionStatus::Timeout
+    } else if cancelled {
+        ExecutionStatus::Cancelled
+    } else if req.testcases.is_empty() {
+        ExecutionStatus::NoTestsRun
+    } else if results.iter().any(|r| r.memory_exceeded) {
+        ExecutionStatus::MemoryLimitExceeded
+    } else if results.iter().any(crashed) {
+        ExecutionStatus::RuntimeError
+    } else {
+        ExecutionStatus::Success
+    };
+
+    let subtasks = SubtaskScore::compute(&req.testcases, &results, &req.group_weights);
+    let scoreboard = Scoreboard::compute(&results, 0, subtasks.as_ref());
+    Ok(ExecuteResponse {
+        compiled,
+        language: req.language.clone(),
+        status: Some(status),
+        message: expired.then(|| total_timeout_message(req)),
+        results,
+        total_duration_ms,
+        work_dir: None,
+        compile_exit_code,
+        platform: current_platform().to_string(),
+        agent_version: AGENT_VERSION.to_string(),
+        subtasks,
+        scoreboard,
+        commands: None,
+    })
+}
+
+fn total_timeout_message(req: &ExecuteRequest) -> String {
+    let ms = req.total_timeout_ms.unwrap_or_default();
+    format!("The submission did not finish within its total timeout of {} ms", ms)
+}
+
+// Response for a submission whose program never got to run, e.g. because it didn't compile
+fn not_run_response(
+    req: &ExecuteRequest,
+    status: ExecutionStatus,
+    message: String,
+    compile_exit_code: Option<i32>,
+) -> ExecuteResponse {
+    // Nothing ran, so every subtask scores zero
+    let subtasks = SubtaskScore::compute(&req.testcases, &[], &req.group_weights);
+    let scoreboard = Scoreboard::compute(&[], req.testcases.len(), subtasks.as_ref());
+    ExecuteResponse {
+        compiled: false,
+        language: req.language.clone(),
+        status: Some(status),
+        message: Some(message),
+        results: vec![],
+        total_duration_ms: 0,
+        work_dir: None,
+        compile_exit_code,
+        platform: current_platform().to_string(),
+        agent_version: AGENT_VERSION.to_string(),
+        subtasks,
+        scoreboard,
+        commands: None,
+    }
+}
+
+// Whether a case's program was ended by a signal it did not get from us for exceeding a limit
+fn crashed(result: &CaseResult) -> bool {
+    result.term_signal.is_some()
+        && result.verdict == Verdict::RuntimeError
+        && !result.output_truncated
+}
+
+// Environment pinning a program's randomness: `SEED` for programs that read it, and
+// Python's hash seed, which must fit in 32 bits
+fn seed_env(seed: u64) -> [(&'static str, String); 2] {
+    [
+        ("SEED", seed.to_string()),
+        ("PYTHONHASHSEED", (seed & u64::from(u32::MAX)).to_string()),
+    ]
+}
+
+// A case's stdout written straight to a file instead of being held in memory
+struct Spill {
+    path: std::path::PathBuf,
+    preview_bytes: usize, // kept in `CaseResult::stdout`
+    max_bytes: usize,     // written to the file before the program is killed
+}
+
+// How one case is run and graded, besides the case itself
+#[derive(Clone, Copy)]
+struct CaseOptions<'a> {
+    comparison: Comparison,
+    seed: Option<u64>,
+    env: Option<&'a HashMap<String, String>>, // the request's, applied after `seed`'s
+    merge_output: bool,
+    spill: Option<&'a Spill>,
+    max_output_bytes: usize,
+}
+
+#[cfg(test)]
+impl Default for CaseOptions<'_> {
+    fn default() -> Self {
+        Self {
+            comparison: Comparison::default(),
+            seed: None,
+            env: None,
+            merge_output: false,
+            spill: None,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+        }
+    }
+}
+
+fn spill_path(dir: &std::path::Path, case_id: i32) -> std::path::PathBuf {
+    dir.join(format!("{}.stdout", case_id))
+}
+
+// Cap the program's data segment (heap and private mappings) at `limit_kb` as it starts, so
+// an allocation past the limit fails even when it happens between two memory samples. Only
+// test case runs are limited; compilers run without it.
+#[cfg(target_os = "linux")]
+fn limit_data_segment(cmd: &mut Command, limit_kb: u64) {
+    let bytes = limit_kb.saturating_mul(1024) as libc::rlim_t;
+    let limit = libc::rlimit {
+        rlim_cur: bytes,
+        rlim_max: bytes,
+    };
+    // SAFETY: runs in the forked child before exec and only calls `setrlimit`, which is
+    // async-signal-safe
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_DATA, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// How close to its limit (in percent) a failed program's peak must have come for the
+/// failure to be put down to the data segment limit
+const MEMORY_NEAR_LIMIT_PERCENT: u64 = 90;
+
+fn peak_near_limit(peak_kb: u64, limit_kb: u64) -> bool {
+    peak_kb.saturating_mul(100) >= limit_kb.saturating_mul(MEMORY_NEAR_LIMIT_PERCENT)
+}
+
+// Run a single test case against the already prepared work directory
+async fn run_testcase(
+    cfg: &LanguageConfig,
+    work_dir: &std::path::Path,
+    tc: &TestCase,
+    opts: CaseOptions<'_>,
+) -> Result<CaseResult> {
+    let CaseOptions {
+        comparison,
+        seed,
+        env,
+        merge_output,
+        spill,
+        max_output_bytes,
+    } = opts;
+    let mut cmd = spawnable(&run_argv(cfg, work_dir));
+    // After the script name for interpreters, to the binary itself for compiled languages
+    cmd.args(&tc.args);
+    cmd.current_dir(work_dir);
+    // Abandoning the run, e.g. to cancel its job, stops the program
+    cmd.kill_on_drop(true);
+    if let Some(seed) = seed {
+        cmd.envs(seed_env(seed));
+    }
+    cmd.envs(env.into_iter().flatten());
+    #[cfg(target_os = "linux")]
+    if let Some(limit_kb) = tc.memory_limit_kb {
+        limit_data_segment(&mut cmd, limit_kb);
+    }
+    cmd.stdin(std::process::Stdio::piped());
+    // Merged output shares one pipe between both streams (`2>&1`), so the text keeps the
+    // order in which the program wrote it
+    let merged_pipe = if merge_output {
+        let (reader, writer) = std::io::pipe()?;
+        cmd.stdout(writer.try_clone()?);
+        cmd.stderr(writer);
+        Some(reader)
+    } else {
+        cmd.stdout(std::process::Stdio::piped());
+        cmd.stderr(std::process::Stdio::piped());
+        None
+    };
+
+    let started_at = epoch_millis();
+    let mut child = cmd.spawn()?;
+    let start = Instant::now();
+    // The command holds its own copies of the merged pipe's write end; the reader only sees
+    // EOF once those are closed
+    drop(cmd);
+
+    // Feed stdin concurrently with reading the output: a program that echoes while it reads
+    // would otherwise fill the stdout pipe and block while we are still blocked writing input.
+    // Dropping the handle at the end closes stdin so the program sees EOF.
+    let mut stdin = child.stdin.take().unwrap();
+    let input = tc.stdin_bytes()?;
+    let case_id = tc.id;
+    let in_handle = tokio::spawn(async move {
+        match stdin.write_all(&input).await {
+            // The program exited or closed stdin before reading all of its input. That is
+            // not wrong on its part, so the case is graded on whatever it printed.
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            Err(e) => eprintln!("Failed to write input for test case {}: {}", case_id, e),
+            Ok(()) => {}
+        }
+    });
+
+    // Capture stdout/stderr concurrently, into buffers that keep everything read so far
+    let cap = OutputCap {
+        max_bytes: max_output_bytes,
+        exceeded: Arc::new(Notify::new()),
+    };
+    let out_buf = OutputBuffer::default();
+    let out_handle = match (merged_pipe, child.stdout.take()) {
+        (Some(reader), _) => {
+            let (buf, cap) = (out_buf.clone(), cap.clone());
+            Some(tokio::task::spawn_blocking(move || capture_blocking(reader, buf, cap)))
+        }
+        (None, Some(out_pipe)) => match spill {
+            Some(spill) => {
+                let file = tokio::fs::File::create(&spill.path).await?;
+                let exceeded = cap.exceeded.clone();
+                Some(tokio::spawn(spill_capped(out_pipe, file, spill.max_bytes, exceeded)))
+            }
+            None => Some(tokio::spawn(capture(out_pipe, out_buf.clone(), cap.clone()))),
+        },
+        (None, None) => None,
+    };
+    let err_buf = OutputBuffer::default();
+    let err_handle = child
+        .stderr
+        .take()
+        .map(|err_pipe| tokio::spawn(capture(err_pipe, err_buf.clone(), cap.clone())));
+
+    let timeout_ms = tc.timeout_ms.unwrap_or(DEFAULT_CASE_TIMEOUT_MS);
+    let deadline = time::sleep(time::Duration::from_millis(timeout_ms));
+    tokio::pin!(deadline);
+    let mut sampler = time::interval(MEMORY_SAMPLE_INTERVAL);
+    let mut memory = MemorySampler::new(child.id());
+    // On Linux the exit is awaited without reaping the program, so the kernel can still be
+    // asked for its peak memory; `child.wait()` reaps it afterwards
+    #[cfg(target_os = "linux")]
+    let exited = wait_unreaped(child.id());
+    #[cfg(not(target_os = "linux"))]
+    let exited = std::future::pending::<Option<u64>>();
+    tokio::pin!(exited);
+    let mut reap_directly = cfg!(not(target_os = "linux"));
+    let mut exit_peak_kb = None;
+    let mut timed_out = false;
+    let mut memory_exceeded = false;
+    let mut output_truncated = false;
+    let status = loop {
+        tokio::select! {
+            peak = &mut exited, if !reap_directly => match peak {
+                Some(peak_kb) => {
+                    exit_peak_kb = Some(peak_kb);
+                    break child.wait().await?;
+                }
+                None => reap_directly = true,
+            },
+            res = child.wait(), if reap_directly => break res?,
+            _ = &mut deadline => timed_out = true,
+            _ = sampler.tick() => {
+                memory.sample();
+                memory_exceeded = tc.memory_limit_kb.is_some_and(|limit| memory.peak_kb > limit);
+            }
+            // A program printing without end would otherwise grow our buffers without bound
+            _ = cap.exceeded.notified() => output_truncated = true,
+        }
+        if timed_out || memory_exceeded || output_truncated {
+            let _ = child.start_kill(); // Best-effort
+            if !reap_directly {
+                exit_peak_kb = (&mut exited).await;
+            }
+            break child.wait().await?;
+        }
+    };
+    // The kernel's figure also covers a spike too short for any sample to see
+    if let Some(peak_kb) = exit_peak_kb {
+        memory.peak_kb = memory.peak_kb.max(peak_kb);
+        memory_exceeded |= tc.memory_limit_kb.is_some_and(|limit| memory.peak_kb > limit);
+    }
+    // Whatever input is still unwritten can no longer be consumed
+    in_handle.abort();
+
+    let killed = timed_out || memory_exceeded || output_truncated;
+    let out_bytes = match out_handle {
+        Some(handle) => collect_output(handle, &out_buf, killed).await,
+        None => Vec::new(),
+    };
+    let err_bytes = match err_handle {
+        Some(handle) => collect_output(handle, &err_buf, killed).await,
+        None => Vec::new(),
+    };
+    // A program that went over a cap just as it exited may have been reaped before the kill
+    output_truncated |= cap.exceeded.notified().now_or_never().is_some();
+    // Spilled output that fits in the preview is handled like captured output
+    let (out_text, spilled_stdout_bytes) = match spill {
+        Some(spill) => read_spilled(spill).await?,
+        None => (String::from_utf8_lossy(&out_bytes).to_string(), None),
+    };
+    let (stdout, output) = if merge_output {
+        (String::new(), Some(out_text))
+    } else {
+        (out_text, None)
+    };
+    let mut stderr = String::from_utf8_lossy(&err_bytes).to_string();
+    let exit_code = status.code();
+    // Tells a crash (e.g. SIGSEGV) apart from a non-zero exit; our own kills are SIGKILL
+    #[cfg(unix)]
+    let term_signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let term_signal = None;
+    // Output cut off at the cap can't be graded, even if the program got to exit cleanly
+    let success = status.success() && !output_truncated;
+
+    // Under the data segment limit an allocation fails instead of growing the program. What
+    // the program prints about it proves nothing, so only a failure whose measured peak came
+    // close to the limit is blamed on memory
+    let memory_exceeded = memory_exceeded
+        || (cfg!(target_os = "linux")
+            && !success
+            && !timed_out
+            && tc
+                .memory_limit_kb
+                .is_some_and(|limit| peak_near_limit(memory.peak_kb, limit)));
+    if let (true, Some(limit_kb)) = (memory_exceeded, tc.memory_limit_kb) {
+        if !stderr.is_empty() && !stderr.ends_with('\n') {
+            stderr.push('\n');
+        }
+        stderr.push_str(&format!("Memory limit of {} KiB exceeded\n", limit_kb));
+    }
+    if output_truncated {
+        if !stderr.is_empty() && !stderr.ends_with('\n') {
+            stderr.push('\n');
+        }
+        // The spill file is cut off exactly at its own cap
+        let limit = match (spill, spilled_stdout_bytes) {
+            (Some(spill), Some(bytes)) if bytes >= spill.max_bytes as u64 => spill.max_bytes,
+            _ => max_output_bytes,
+        };
+        stderr.push_str(&format!("Output limit of {} bytes exceeded\n", limit));
+    }
+
+    let duration_ms = start.elapsed().as_millis() as u64;
+    let finished_at = epoch_millis();
+    // A timed-out case already failed on time; `slow` is only feedback for the rest
+    let slow = !timed_out && tc.soft_time_limit_ms.is_some_and(|soft| duration_ms > soft);
+
+    let ok = success && !timed_out && !memory_exceeded;
+    let graded = output.as_deref().unwrap_or(&stdout);
+    let output_matched = match (&tc.expected, spill.filter(|_| spilled_stdout_bytes.is_some())) {
+        (Some(exp), Some(spill)) => {
+            let file = std::fs::File::open(&spill.path)?;
+            Some(comparison.matches_reader(exp, std::io::BufReader::new(file))?)
+        }
+        (Some(exp), None) => Some(comparison.matches(exp, graded)),
+        (None, _) => None,
+    };
+    // Only the preview of a spilled output is at hand, so there is no line to point at
+    let first_mismatch = match (&tc.expected, output_matched) {
+        (Some(exp), Some(false)) if spilled_stdout_bytes.is_none() => {
+            comparison.first_mismatch(exp, graded)
+        }
+        _ => None,
+    };
+    // The memory kill shows up as a plain signal death, so it must win over RuntimeError
+    let verdict = if memory_exceeded {
+        Verdict::MemoryLimitExceeded
+    } else {
+        Verdict::classify(timed_out, success, output_matched)
+    };
+
+    Ok(CaseResult {
+        id: tc.id,
+        ok,
+        passed: output_matched,
+        input: tc.input.clone(),
+        expected: tc.expected.clone(),
+        stdout,
+        stderr,
+        timed_out,
+        memory_exceeded,
+        output_truncated,
+        slow,
+        duration_ms,
+        memory_kb: memory.peak_kb,
+        exit_code,
+        term_signal,
+        started_at,
+        finished_at,
+        verdict,
+        stdin_base64: None,
+        output,
+        diff: first_mismatch.as_ref().map(LineMismatch::summary),
+        first_mismatch,
+        spilled_stdout_bytes,
+    })
+}
+
+// Read back a spilled stdout. Output that fits in the preview is returned whole and its
+// file removed; longer output keeps its file and comes back as a preview plus its size.
+async fn read_spilled(spill: &Spill) -> Result<(String, Option<u64>)> {
+    let mut file = tokio::fs::File::open(&spill.path).await?;
+    let len = file.metadata().await?.len();
+    let mut preview = Vec::new();
+    (&mut file)
+        .take(spill.preview_bytes as u64)
+        .read_to_end(&mut preview)
+        .await?;
+    let preview = String::from_utf8_lossy(&preview).into_owned();
+    if len <= spill.preview_bytes as u64 {
+        drop(file);
+        let _ = tokio::fs::remove_file(&spill.path).await;
+        return Ok((preview, None));
+    }
+    Ok((preview, Some(len)))
+}
+
+/// How long to keep reading a killed program's output before settling for what was read
+const OUTPUT_DRAIN_GRACE: time::Duration = time::Duration::from_millis(200);
+
+// Output read from a program's pipe so far, shared with the task reading it
+type OutputBuffer = Arc<std::sync::Mutex<Vec<u8>>>;
+
+/// Limit on how much of a program's output is kept, shared by the tasks reading its pipes.
+#[derive(Clone)]
+struct OutputCap {
+    max_bytes: usize,
+    exceeded: Arc<Notify>, // notified when a program writes past `max_bytes`
+}
+
+impl OutputCap {
+    // Keep what was read, up to the cap. Returns false, after notifying `exceeded`, once
+    // the program has written more than that.
+    fn append(&self, buf: &OutputBuffer, chunk: &[u8]) -> bool {
+        let mut buf = buf.lock().unwrap();
+        let room = self.max_bytes.saturating_sub(buf.len());
+        buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if chunk.len() > room {
+            self.exceeded.notify_one();
+            return false;
+        }
+        true
+    }
+}
+
+async fn capture(mut pipe: impl tokio::io::AsyncRead + Unpin, buf: OutputBuffer, cap: OutputCap) {
+    let mut chunk = [0u8; 8192];
+    while let Ok(n @ 1..) = pipe.read(&mut chunk).await {
+        if !cap.append(&buf, &chunk[..n]) {
+            break;
+        }
+    }
+}
+
+// Copy a spilled stdout to its file, keeping at most `max_bytes`. Once the program has
+// written more than that, `exceeded` is notified and the copy stops.
+async fn spill_capped(
+    mut pipe: impl tokio::io::AsyncRead + Unpin,
+    mut file: tokio::fs::File,
+    max_bytes: usize,
+    exceeded: Arc<Notify>,
+) {
+    let mut written = 0;
+    let mut chunk = [0u8; 8192];
+    while let Ok(n @ 1..) = pipe.read(&mut chunk).await {
+        let keep = n.min(max_bytes - written);
+        if file.write_all(&chunk[..keep]).await.is_err() {
+            break;
+        }
+        written += keep;
+        if keep < n {
+            exceeded.notify_one();
+            break;
+        }
+    }
+    let _ = file.flush().await;
+}
+
+fn capture_blocking(mut pipe: impl std::io::Read, buf: OutputBuffer, cap: OutputCap) {
+    let mut chunk = [0u8; 8192];
+    while let Ok(n @ 1..) = pipe.read(&mut chunk) {
+        if !cap.append(&buf, &chunk[..n]) {
+            break;
+        }
+    }
+}
+
+// Wait in a blocking thread for the program to exit, leaving it unreaped, and return its
+// peak resident memory in KiB (`ru_maxrss`, which covers the processes it waited for too).
+// None if the exit can't be waited for this way.
+#[cfg(target_os = "linux")]
+async fn wait_unreaped(pid: Option<u32>) -> Option<u64> {
+    let pid = pid?;
+    let wait = move || loop {
+        // SAFETY: both structs are plain data that the kernel fills in
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        // The raw system call, as only it takes the rusage argument. `WNOWAIT` leaves the
+        // program for `Child::wait` to reap.
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_waitid,
+                libc::P_PID,
+                pid,
+                &mut info as *mut libc::siginfo_t,
+                libc::WEXITED | libc::WNOWAIT,
+                &mut usage as *mut libc::rusage,
+            )
+        };
+        if rc == 0 {
+            return Some(usage.ru_maxrss as u64);
+        }
+        if std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+            return None;
+        }
+    };
+    tokio::task::spawn_blocking(wait).await.ok().flatten()
+}
+
+// Take a program's captured output once its pipe reaches EOF. After a kill, anything the
+// program spawned may still hold the pipe open, so the reader only gets a short grace
+// period to drain what was written before the kill, and the partial output is kept.
+async fn collect_output(
+    mut handle: tokio::task::JoinHandle<()>,
+    buf: &OutputBuffer,
+    killed: bool,
+) -> Vec<u8> {
+    if !killed {
+        let _ = handle.await;
+    } else if time::timeout(OUTPUT_DRAIN_GRACE, &mut handle).await.is_err() {
+        handle.abort();
+    }
+    std::mem::take(&mut *buf.lock().unwrap())
+}
+
+/// How often a running program's memory is sampled
+const MEMORY_SAMPLE_INTERVAL: time::Duration = time::Duration::from_millis(10);
+
+// Tracks the peak resident memory of a running program's process, to kill it once it goes
+// over its limit. On Linux the kernel's own high-water mark is read, and the final peak
+// comes from the kernel when the program exits (see `wait_unreaped`); sampling is only the
+// fallback there. Elsewhere sampling can miss a spike shorter than `MEMORY_SAMPLE_INTERVAL`,
+// and where the process's memory can't be read at all the peak stays 0.
+struct MemorySampler {
+    sys: System,
+    pid: Option<Pid>,
+    peak_kb: u64,
+}
+
+impl MemorySampler {
+    fn new(pid: Option<u32>) -> Self {
+        Self {
+            sys: System::new(),
+            pid: pid.map(Pid::from_u32),
+            peak_kb: 0,
+        }
+    }
+
+    // Update the peak from the process's current memory (nothing once the process is gone)
+    fn sample(&mut self) {
+        let Some(pid) = self.pid else {
+            return;
+        };
+        let refresh = ProcessRefreshKind::new().with_memory();
+        if !self.sys.refresh_process_specifics(pid, refresh) {
+            return;
+        }
+        let current_kb = self.sys.process(pid).map_or(0, |p| p.memory() / 1024);
+        self.peak_kb = self.peak_kb.max(current_kb);
+        // The kernel's high-water mark also covers spikes between two samples
+        #[cfg(target_os = "linux")]
+        if let Some(hwm_kb) = peak_rss_kb(pid.as_u32()) {
+            self.peak_kb = self.peak_kb.max(hwm_kb);
+        }
+    }
+}
+
+// Peak resident memory the kernel recorded for a live process (`VmHWM`), in KiB
+#[cfg(target_os = "linux")]
+fn peak_rss_kb(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let value = status.lines().find_map(|line| line.strip_prefix("VmHWM:"))?;
+    value.trim().strip_suffix("kB")?.trim().parse().ok()
+}
+
+// Wrap a program and its arguments the way they are spawned on this platform: through
+// `cmd /C` on Windows, so built-in commands and `.cmd` shims resolve, and directly elsewhere
+fn platform_argv(program: OsString, args: &[String]) -> Vec<OsString> {
+    let mut argv = if cfg!(windows) {
+        vec!["cmd".into(), "/C".into(), program]
+    } else {
+        vec![program]
+    };
+    argv.extend(args.iter().map(OsString::from));
+    argv
+}
+
+fn compile_argv(compile_command: &str, compile_args: &[String]) -> Vec<OsString> {
+    platform_argv(compile_command.into(), compile_args)
+}
+
+fn run_argv(cfg: &LanguageConfig, work_dir: &std::path::Path) -> Vec<OsString> {
+    let program = resolve_program(&cfg.run_command, work_dir);
+    platform_argv(program.into_os_string(), &cfg.run_args)
+}
+
+fn spawnable(argv: &[OsString]) -> Command {
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..]);
+    cmd
+}
+
+// The concrete commands a run of `req` spawns in `work_dir`, for `BUILDIT_REPORT_COMMANDS`
+fn resolved_commands(
+    req: &ExecuteRequest,
+    cfg: &LanguageConfig,
+    work_dir: &std::path::Path,
+) -> ResolvedCommands {
+    let lossy = |argv: Vec<OsString>| {
+        argv.iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    };
+    let compile = cfg.compile_command.as_ref().map(|command| {
+        // An unknown preset never gets this far; validation rejects it
+        let args = cfg.compile_args_for(req.optimization.as_deref()).unwrap_or_default();
+        lossy(compile_argv(command, &args))
+    });
+    ResolvedCommands {
+        compile,
+        run: lossy(run_argv(cfg, work_dir)),
+    }
+}
+
+// Programs given as a relative path (e.g. "./main" produced by the compile step) are resolved
+// against the work dir explicitly; how `Command` resolves them alongside `current_dir` is
+// platform-specific. Bare names like "java" are left for the PATH lookup.
+fn resolve_program(command: &str, work_dir: &std::path::Path) -> std::path::PathBuf {
+    let path = std::path::Path::new(command);
+    if path.is_relative() && path.components().count() > 1 {
+        work_dir.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn epoch_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusq::Priority;
+    use crate::types::SourceFile;
+
+    fn request(priority: Priority) -> ExecuteRequest {
+        ExecuteRequest {
+            language: "python3".to_string(),
+            code: "print('hi')".to_string(),
+            testcases: vec![],
+            priority,
+            comparison: Comparison::default(),
+            optimization: None,
+            group_weights: HashMap::new(),
+            seed: None,
+            echo_stdin: false,
+            merge_output: false,
+            reference: None,
+            spill_output_bytes: None,
+            env: HashMap::new(),
+            compile_only: false,
+            files: vec![],
+            total_timeout_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_job_runs_first() {
+        let queue = JobQueue::new(10);
+        queue.push(1, request(Priority::Low)).unwrap();
+        queue.push(2, request(Priority::High)).unwrap();
+
+        assert_eq!(queue.pop().await.unwrap().payload.0, 2);
+        assert_eq!(queue.pop().await.unwrap().payload.0, 1);
+    }
+
+    #[tokio::test]
+    async fn test_popped_job_is_in_flight_until_done() {
+        let queue = JobQueue::new(10);
+        queue.push(1, request(Priority::Normal)).unwrap();
+
+        let job = queue.pop().await.unwrap();
+        assert_eq!(queue.queue.metrics().in_flight, 1);
+        queue.done(job);
+        let metrics = queue.queue.metrics();
+        assert_eq!((metrics.in_flight, metrics.messages_acked), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_pop_waits_for_push() {
+        let queue = Arc::new(JobQueue::new(10));
+        let waiter = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.pop().await.map(|job| job.payload.0) }
+        });
+
+        tokio::task::yield_now().await;
+        queue.push(7, request(Priority::Normal)).unwrap();
+        assert_eq!(waiter.await.unwrap(), Some(7));
+    }
+
+    fn completed() -> JobState {
+        JobState::Completed(Box::new(ExecuteResponse {
+            compiled: false,
+            language: "python3".to_string(),
+            status: Some(ExecutionStatus::Success),
+            message: None,
+            results: vec![],
+            total_duration_ms: 0,
+            work_dir: None,
+            compile_exit_code: None,
+            platform: current_platform().to_string(),
+            agent_version: AGENT_VERSION.to_string(),
+            subtasks: None,
+            scoreboard: Scoreboard::default(),
+            commands: None,
+        }))
+    }
+
+    #[test]
+    fn test_job_store_evicts_oldest_finished_jobs() {
+        let mut store = JobStore::new(2);
+        store.insert(1, JobState::Queued);
+        store.insert(2, JobState::Running(Arc::default()));
+        store.insert(3, completed());
+        store.insert(4, JobState::Error(JobError::new("boom")));
+        store.insert(5, completed());
+
+        assert!(store.get(&3).is_none(), "oldest finished job should be evicted");
+        assert!(store.get(&4).is_some());
+        assert!(store.get(&5).is_some());
+        assert!(matches!(store.get(&1), Some(JobState::Queued)));
+        assert!(matches!(store.get(&2), Some(JobState::Running(_))));
+        assert_eq!(store.retained_finished(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_finished_jobs_are_purged_after_ttl() {
+        let ttl = time::Duration::from_secs(60);
+        let store = JobStore::new(10)
+            .with_spill_root(tempfile::tempdir().unwrap())
+            .with_ttl(ttl);
+        let state = AppState {
+            jobs: Arc::new(RwLock::new(store)),
+            ..test_state(HashMap::new())
+        };
+        let dir = {
+            let mut jobs = state.jobs.write().await;
+            jobs.insert(1, completed());
+            jobs.insert(2, JobState::Queued);
+            jobs.spill_dir(1).unwrap()
+        };
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let now = Instant::now();
+        assert_eq!(state.jobs.write().await.purge_expired(now), 0);
+        let resp = status_handler(State(state.clone()), Path(1)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Queued and running jobs are never purged, however old
+        let later = now + ttl + time::Duration::from_secs(1);
+        assert_eq!(state.jobs.write().await.purge_expired(later), 1);
+        let resp = status_handler(State(state.clone()), Path(1)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!dir.exists());
+        let resp = status_handler(State(state.clone()), Path(2)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(state.jobs.read().await.retained_finished(), 0);
+    }
+
+    #[test]
+    fn test_job_store_counts_job_once_when_it_finishes() {
+        let mut store = JobStore::new(2);
+        store.insert(1, JobState::Queued);
+        store.insert(1, JobState::Running(Arc::default()));
+        store.insert(1, completed());
+        store.insert(2, completed());
+        store.insert(3, completed());
+
+        assert!(store.get(&1).is_none());
+        assert_eq!(store.retained_finished(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_spilled_stdout_is_served_until_job_is_evicted() {
+        let mut store = JobStore::new(1).with_spill_root(tempfile::tempdir().unwrap());
+        store.insert(1, completed());
+        let dir = store.spill_dir(1).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(spill_path(&dir, 7), "big output\n").unwrap();
+        let state = AppState {
+            jobs: Arc::new(RwLock::new(store)),
+            ..test_state(HashMap::new())
+        };
+
+        let fetch = |id, case_id| spilled_stdout_handler(State(state.clone()), Path((id, case_id)));
+        let resp = fetch(1, 7).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"big output\n");
+        assert_eq!(fetch(1, 8).await.into_response().status(), StatusCode::NOT_FOUND);
+        assert_eq!(fetch(2, 7).await.into_response().status(), StatusCode::NOT_FOUND);
+
+        // Evicting the job removes its files
+        state.jobs.write().await.insert(2, completed());
+        assert!(!dir.exists());
+        assert_eq!(fetch(1, 7).await.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_executor_bind_defaults_to_loopback() {
+        assert_eq!(
+            executor_bind_ip(None, false).unwrap(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert!(executor_bind_ip(Some("::1"), false).unwrap().is_loopback());
+        assert!(executor_bind_ip(Some("127.0.0.2"), false).unwrap().is_loopback());
+    }
+
+    #[tokio::test]
+    async fn test_executor_accepts_connections_once_ready() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let executor = tokio::spawn(run(port, Some(ready_tx), std::future::pending()));
+        ready_rx.await.unwrap();
+
+        // No retries: the socket is bound before readiness is reported
+        let resp = reqwest::get(format!("http://127.0.0.1:{}/health", port))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        executor.abort();
+    }
+
+    #[test]
+    fn test_executor_refuses_non_loopback_without_opt_in() {
+        assert!(executor_bind_ip(Some("0.0.0.0"), false).is_err());
+        assert!(executor_bind_ip(Some("192.168.1.10"), false).is_err());
+        assert!(executor_bind_ip(Some("::"), false).is_err());
+
+        let ip = executor_bind_ip(Some("0.0.0.0"), true).unwrap();
+        assert_eq!(ip, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    }
+
+    #[test]
+    fn test_executor_rejects_unparseable_host() {
+        assert!(executor_bind_ip(Some("localhost"), true).is_err());
+    }
+
+    const CLIENT: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 40_000);
+
+    fn validation_state() -> AppState {
+        let configs = crate::language::generate_language_configs();
+        let mut subset = HashMap::new();
+        for name in ["python3", "gcc"] {
+            subset.insert(name.to_string(), configs[name].clone());
+        }
+        test_state(subset)
+    }
+
+    fn error_fields(req: &ExecuteRequest, state: &AppState) -> Vec<String> {
+        validate_request(req, &state.languages(), state.allow_unsafe_env)
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    async fn response_json(resp: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    // Headers carrying the admin token of states built with `admin_token: Some("secret")`
+    fn admin_headers() -> header::HeaderMap {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_all_languages_flags_missing_toolchains() {
+        let configs = HashMap::from([
+            ("sh".to_string(), shell_config("true")),
+            (
+                "cobol".to_string(),
+                LanguageConfig {
+                    display_name: "COBOL".to_string(),
+                    ..shell_config("true")
+                },
+            ),
+        ]);
+        let registry = LanguageRegistry {
+            configs,
+            available: HashSet::from(["sh".to_string()]),
+            langs_list: vec![],
+        };
+        let state = AppState {
+            languages: Arc::new(std::sync::RwLock::new(Arc::new(registry))),
+            ..test_state(HashMap::new())
+        };
+
+        let resp = all_languages_handler(State(state))
+            .await
+            .into_response();
+        assert_eq!(
+            response_json(resp).await,
+            serde_json::json!([
+                {"display_name": "COBOL", "language": "cobol", "available": false},
+                {"display_name": "Shell", "language": "sh", "available": true},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_echoes_normalized_request_without_enqueuing() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            testcases: vec![TestCase {
+                id: 1,
+                input: String::new(),
+                expected: Some("hi\n".to_string()),
+                timeout_ms: None,
+                input_from_previous: false,
+                group: None,
+                input_encoding: InputEncoding::Text,
+                memory_limit_kb: None,
+                soft_time_limit_ms: None,
+                args: vec![],
+            }],
+            ..request(Priority::High)
+        };
+        assert!(error_fields(&req, &state).is_empty());
+
+        let resp = validate_handler(State(state.clone()), JsonBody(req)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["valid"], true);
+        assert_eq!(body["request"]["priority"], "High");
+        assert_eq!(
+            body["request"]["testcases"][0]["timeout_ms"],
+            DEFAULT_CASE_TIMEOUT_MS
+        );
+
+        assert_eq!(state.queue.queue.depths(), QueueDepths::default());
+        assert!(state.jobs.read().await.get(&1).is_none());
+    }
+
+    #[test]
+    fn test_cases_inherit_their_languages_limits() {
+        let languages = r#"{
+            "java": {"display_name": "Java", "file_name": "Main.java",
+                     "version_command": "java -version", "run_command": "java",
+                     "default_timeout_ms": 8000, "default_memory_limit_kb": 524288},
+            "gcc": {"display_name": "C", "file_name": "main.c",
+                    "version_command": "gcc --version", "run_command": "./main",
+                    "default_timeout_ms": 1000, "default_memory_limit_kb": 65536},
+            "lua": {"display_name": "Lua", "file_name": "main.lua",
+                    "version_command": "lua -v", "run_command": "lua"}
+        }"#;
+        let state = test_state(serde_json::from_str(languages).unwrap());
+        let case = |timeout_ms, memory_limit_kb| TestCase {
+            id: 1,
+            input: String::new(),
+            expected: None,
+            timeout_ms,
+            input_from_previous: false,
+            group: None,
+            input_encoding: InputEncoding::Text,
+            memory_limit_kb,
+            soft_time_limit_ms: None,
+            args: vec![],
+        };
+        let limits = |language: &str, tc: TestCase| {
+            let req = ExecuteRequest {
+                language: language.to_string(),
+                testcases: vec![tc],
+                ..request(Priority::Normal)
+            };
+            let tc = &normalize_request(req, &state.languages()).testcases[0];
+            (tc.timeout_ms, tc.memory_limit_kb)
+        };
+
+        assert_eq!(limits("java", case(None, None)), (Some(8000), Some(524288)));
+        assert_eq!(limits("gcc", case(None, None)), (Some(1000), Some(65536)));
+        // A case's own limits win, and languages without defaults use the global ones
+        assert_eq!(limits("gcc", case(Some(300), None)), (Some(300), Some(65536)));
+        assert_eq!(
+            limits("lua", case(None, None)),
+            (Some(DEFAULT_CASE_TIMEOUT_MS), None)
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_language_default_timeout_applies_when_running() {
+        let cfg = LanguageConfig {
+            default_timeout_ms: Some(200),
+            ..shell_config("sleep 1")
+        };
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            code: "unused".to_string(),
+            testcases: vec![TestCase {
+                timeout_ms: None,
+                ..testcase("", None)
+            }],
+            ..request(Priority::Normal)
+        };
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(resp.results[0].timed_out);
+        assert!(resp.results[0].duration_ms < 1000);
+    }
+
+    #[test]
+    fn test_validate_rejects_unavailable_language() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            language: "cobol".to_string(),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&req, &state), vec!["language"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_and_oversized_code() {
+        let state = validation_state();
+        let empty = ExecuteRequest {
+            code: "  \n".to_string(),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&empty, &state), vec!["code"]);
+
+        let huge = ExecuteRequest {
+            code: "#".repeat(MAX_CODE_BYTES + 1),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&huge, &state), vec!["code"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_too_many_testcases() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            testcases: (0..=MAX_TESTCASES as i32)
+                .map(|id| TestCase {
+                    id,
+                    input: String::new(),
+                    expected: None,
+                    timeout_ms: None,
+                    input_from_previous: false,
+                    group: None,
+                    input_encoding: InputEncoding::Text,
+                    memory_limit_kb: None,
+                    soft_time_limit_ms: None,
+                    args: vec![],
+                })
+                .collect(),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&req, &state), vec!["testcases"]);
+    }
+
+    #[test]
+    fn test_validate_reports_each_bad_testcase() {
+        let state = validation_state();
+        let case = |id, timeout_ms| TestCase {
+            id,
+            input: String::new(),
+            expected: None,
+            timeout_ms,
+            input_from_previous: false,
+            group: None,
+            input_encoding: InputEncoding::Text,
+            memory_limit_kb: None,
+            soft_time_limit_ms: None,
+            args: vec![],
+        };
+        let req = ExecuteRequest {
+            testcases: vec![case(1, None), case(1, Some(100)), case(2, Some(0))],
+            ..request(Priority::Normal)
+        };
+        assert_eq!(
+            error_fields(&req, &state),
+            vec!["testcases[1].id", "testcases[2].timeout_ms"]
+        );
+
+        let req = ExecuteRequest {
+            total_timeout_ms: Some(0),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&req, &state), vec!["total_timeout_ms"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_pipeline_misuse() {
+        let state = validation_state();
+        let case = |id, input: &str| TestCase {
+            id,
+            input: input.to_string(),
+            expected: None,
+            timeout_ms: None,
+            input_from_previous: true,
+            group: None,
+            input_encoding: InputEncoding::Text,
+            memory_limit_kb: None,
+            soft_time_limit_ms: None,
+            args: vec![],
+        };
+        let req = ExecuteRequest {
+            testcases: vec![case(1, ""), case(2, "ignored")],
+            ..request(Priority::Normal)
+        };
+        assert_eq!(
+            error_fields(&req, &state),
+            vec!["testcases[0].input_from_previous", "testcases[1].input"]
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_unsafe_env() {
+        let mut state = validation_state();
+        let req = ExecuteRequest {
+            env: HashMap::from([
+                ("GOMAXPROCS".to_string(), "1".to_string()),
+                ("LD_PRELOAD".to_string(), "/tmp/hook.so".to_string()),
+                ("Path".to_string(), "/tmp".to_string()),
+                ("A=B".to_string(), "c".to_string()),
+            ]),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(
+            error_fields(&req, &state),
+            vec!["env.A=B", "env.LD_PRELOAD", "env.Path"]
+        );
+
+        // Malformed names stay rejected when the agent allows unsafe variables
+        state.allow_unsafe_env = true;
+        assert_eq!(error_fields(&req, &state), vec!["env.A=B"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_reference_in_compile_only_mode() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            compile_only: true,
+            reference: Some(ReferenceSolution {
+                language: None,
+                code: "print('hi')".to_string(),
+            }),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&req, &state), vec!["reference"]);
+    }
+
+    #[test]
+    fn test_validate_checks_source_files() {
+        let state = validation_state();
+        let file = |name: &str| SourceFile {
+            name: name.to_string(),
+            content: "int x;".to_string(),
+        };
+        let req = ExecuteRequest {
+            language: "gcc".to_string(),
+            code: String::new(),
+            files: vec![file("main.c"), file("lib/helper.h")],
+            ..request(Priority::Normal)
+        };
+        assert!(error_fields(&req, &state).is_empty());
+
+        let req = ExecuteRequest {
+            code: "int main() {}".to_string(),
+            files: vec![
+                file("main.c"),
+                file("../escape.c"),
+                file("/etc/passwd"),
+                file(""),
+                file("main.c"),
+            ],
+            ..req
+        };
+        assert_eq!(
+            error_fields(&req, &state),
+            vec![
+                "code",
+                "files[1].name",
+                "files[2].name",
+                "files[3].name",
+                "files[4].name"
+            ]
+        );
+
+        // Without the entry file among `files`, `code` is still required
+        let req = ExecuteRequest {
+            code: String::new(),
+            files: vec![file("helper.h")],
+            ..req
+        };
+        assert_eq!(error_fields(&req, &state), vec!["code"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_spill_misuse() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            spill_output_bytes: Some(0),
+            merge_output: true,
+            ..request(Priority::Normal)
+        };
+        assert_eq!(
+            error_fields(&req, &state),
+            vec!["spill_output_bytes", "spill_output_bytes"]
+        );
+
+        let req = ExecuteRequest {
+            spill_output_bytes: Some(1024),
+            ..request(Priority::Normal)
+        };
+        assert!(error_fields(&req, &state).is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_optimization_preset() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            language: "gcc".to_string(),
+            optimization: Some("fastest".to_string()),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&req, &state), vec!["optimization"]);
+
+        let known = ExecuteRequest {
+            optimization: Some("release".to_string()),
+            ..req
+        };
+        assert!(error_fields(&known, &state).is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_weight_for_unknown_group() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            group_weights: HashMap::from([("missing".to_string(), 10)]),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(error_fields(&req, &state), vec!["group_weights.missing"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_expected_output_alongside_reference() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            testcases: vec![TestCase {
+                id: 1,
+                input: String::new(),
+                expected: Some("1\n".to_string()),
+                timeout_ms: None,
+                input_from_previous: false,
+                group: None,
+                input_encoding: InputEncoding::Text,
+                memory_limit_kb: None,
+                soft_time_limit_ms: None,
+                args: vec![],
+            }],
+            reference: Some(ReferenceSolution {
+                language: Some("cobol".to_string()),
+                code: "print(1)".to_string(),
+            }),
+            ..request(Priority::Normal)
+        };
+        assert_eq!(
+            error_fields(&req, &state),
+            vec!["reference.language", "testcases[0].expected"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_rejects_invalid_request_with_structured_errors() {
+        let state = validation_state();
+        let req = ExecuteRequest {
+            language: "cobol".to_string(),
+            code: String::new(),
+            ..request(Priority::Normal)
+        };
+
+        let resp = enqueue_handler(State(state.clone()), JsonBody(req)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = response_json(resp).await;
+        assert_eq!(body["errors"].as_array().unwrap().len(), 2);
+        assert_eq!(body["errors"][0]["field"], "language");
+        assert!(body["error"].as_str().unwrap().contains("cobol"));
+        assert_eq!(state.queue.queue.depths(), QueueDepths::default());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_drain_fails_queued_jobs_without_running_them() {
+        let scratch = tempfile::tempdir().unwrap();
+        let log = scratch.path().join("runs.log");
+        let script = format!("echo ran >> {}", log.display());
+        let state = AppState {
+            admin_token: Some("secret".to_string()),
+            ..test_state(HashMap::from([("sh".to_string(), shell_config(&script))]))
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("", None)],
+            ..request(Priority::Normal)
+        };
+        for _ in 0..3 {
+            let resp = enqueue_handler(State(state.clone()), JsonBody(req.clone())).await;
+            assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        }
+
+        let mut headers = header::HeaderMap::new();
+        let resp = drain_handler(State(state.clone()), headers.clone()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let resp = drain_handler(State(state.clone()), headers).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(response_json(resp).await["drained"], 3);
+        assert_eq!(state.queue.queue.depths(), QueueDepths::default());
+
+        // A worker started afterwards finds nothing left to run
+        tokio::spawn(worker_loop(state.clone()));
+        time::sleep(time::Duration::from_millis(200)).await;
+        assert!(!log.exists());
+        let jobs = state.jobs.read().await;
+        for id in 1..=3 {
+            match jobs.get(&id) {
+                Some(JobState::Error(err)) => assert_eq!(err.message, "drained"),
+                other => panic!("job {} should be drained, got {:?}", id, other),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_queued_and_running_jobs() {
+        let scratch = tempfile::tempdir().unwrap();
+        let log = scratch.path().join("runs.log");
+        let script = format!("echo ran >> {}; exec sleep 5", log.display());
+        let state = test_state(HashMap::from([("sh".to_string(), shell_config(&script))]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![TestCase {
+                timeout_ms: Some(10_000),
+                ..testcase("", None)
+            }],
+            ..request(Priority::Normal)
+        };
+        for _ in 0..2 {
+            let resp = enqueue_handler(State(state.clone()), JsonBody(req.clone())).await;
+            assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        }
+        tokio::spawn(worker_loop(state.clone()));
+        time::sleep(time::Duration::from_millis(300)).await;
+
+        // Job 2 is still waiting behind job 1
+        let resp = cancel_handler(State(state.clone()), Path(2)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(response_json(resp).await["error"], "cancelled");
+
+        let start = Instant::now();
+        let resp = cancel_handler(State(state.clone()), Path(1)).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        time::sleep(time::Duration::from_millis(300)).await;
+        {
+            let jobs = state.jobs.read().await;
+            for id in 1..=2 {
+                match jobs.get(&id) {
+                    Some(JobState::Error(err)) => assert_eq!(err.message, "cancelled"),
+                    other => panic!("job {} should be cancelled, got {:?}", id, other),
+                }
+            }
+        }
+        assert!(start.elapsed() < time::Duration::from_secs(2));
+        // Only the running job's program ever started
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "ran\n");
+
+        let resp = cancel_handler(State(state.clone()), Path(1)).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = cancel_handler(State(state), Path(99)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancelled_job_evicted_while_queued_never_runs() {
+        let scratch = tempfile::tempdir().unwrap();
+        let log = scratch.path().join("runs.log");
+        let script = format!("echo ran >> {}", log.display());
+        let state = AppState {
+            jobs: Arc::new(RwLock::new(JobStore::new(1))),
+            ..test_state(HashMap::from([("sh".to_string(), shell_config(&script))]))
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("", None)],
+            ..request(Priority::Normal)
+        };
+        for _ in 0..2 {
+            let resp = enqueue_handler(State(state.clone()), JsonBody(req.clone())).await;
+            assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        }
+        let resp = cancel_handler(State(state.clone()), Path(1)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        // Another finished job pushes the cancelled one out of the store
+        state.jobs.write().await.insert(99, completed());
+        assert!(state.jobs.read().await.get(&1).is_none());
+
+        tokio::spawn(worker_loop(state.clone()));
+        for _ in 0..50 {
+            if state.jobs.read().await.get(&2).is_some_and(JobState::is_finished) {
+                break;
+            }
+            time::sleep(time::Duration::from_millis(50)).await;
+        }
+        assert!(matches!(state.jobs.read().await.get(&2), Some(JobState::Completed(_))));
+        assert!(state.jobs.read().await.get(&1).is_none());
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "ran\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_idle_shutdown_waits_for_queued_and_running_jobs() {
+        let state = test_state(HashMap::from([(
+            "sh".to_string(),
+            shell_config("sleep 0.5"),
+        )]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("", None)],
+            ..request(Priority::Normal)
+        };
+        let idle = tokio::spawn({
+            let state = state.clone();
+            async move { wait_until_idle(&state, time::Duration::from_millis(200)).await }
+        });
+
+        // Queued, but no worker picks it up yet
+        let resp = enqueue_handler(State(state.clone()), JsonBody(req)).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        time::sleep(time::Duration::from_millis(400)).await;
+        assert!(!idle.is_finished());
+
+        // Running
+        tokio::spawn(worker_loop(state.clone()));
+        time::sleep(time::Duration::from_millis(300)).await;
+        assert!(!idle.is_finished());
+
+        time::timeout(time::Duration::from_secs(2), idle)
+            .await
+            .unwrap()
+            .unwrap();
+        let jobs = state.jobs.read().await;
+        assert!(matches!(jobs.get(&1), Some(JobState::Completed(_))));
+        assert!(state.activity.idle_for().unwrap() >= time::Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_drain_disabled_without_admin_token() {
+        let state = test_state(HashMap::new());
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer anything".parse().unwrap());
+        let resp = drain_handler(State(state), headers).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_queue_metrics_reflect_job_queue() {
+        let state = test_state(HashMap::new());
+        state.queue.push(1, request(Priority::Low)).unwrap();
+        state.queue.push(2, request(Priority::High)).unwrap();
+        state.queue.push(3, request(Priority::Low)).unwrap();
+        state.queue.pop().await.unwrap();
+
+        let Json(resp) = queue_metrics_handler(State(state)).await;
+        assert_eq!(resp.metrics.messages_sent, 3);
+        assert_eq!(resp.metrics.messages_received, 1);
+        assert_eq!(resp.depths.high, 0);
+        assert_eq!(resp.depths.low, 2);
+
+        let json = serde_json::to_value(&resp).unwrap();
+        assert_eq!(json["messages_sent"], 3);
+        assert_eq!(json["depths"]["low"], 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_metrics_count_jobs_by_state() {
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), shell_config("cat"));
+        let state = test_state(configs);
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("1\n", Some("1\n"))],
+            ..request(Priority::Normal)
+        };
+        for _ in 0..2 {
+            let resp = enqueue_handler(State(state.clone()), JsonBody(req.clone())).await;
+            assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        }
+        state.jobs.write().await.insert(3, JobState::Error(JobError::new("boom")));
+
+        let json = serde_json::to_value(metrics_handler(State(state.clone())).await.0).unwrap();
+        assert_eq!(
+            json["jobs"],
+            serde_json::json!({"queued": 2, "running": 0, "completed": 0, "error": 1})
+        );
+        assert_eq!(json["queue_depth"], 2);
+        assert_eq!(json["jobs_processed"], 0);
+        assert!(json.get("average_total_duration_ms").is_none());
+
+        tokio::spawn(worker_loop(state.clone()));
+        for _ in 0..100 {
+            if state.pipeline.jobs_processed.load(Ordering::Relaxed) == 2 {
+                break;
+            }
+            time::sleep(time::Duration::from_millis(20)).await;
+        }
+        let Json(resp) = metrics_handler(State(state.clone())).await;
+        let expected = JobCounts {
+            completed: 2,
+            error: 1,
+            ..JobCounts::default()
+        };
+        assert_eq!(resp.jobs, expected);
+        assert_eq!(resp.queue_depth, 0);
+        assert_eq!(resp.jobs_processed, 2);
+        assert!(resp.average_total_duration_ms.is_some());
+    }
+
+    #[cfg(unix)]
+    fn shell_config(script: &str) -> LanguageConfig {
+        LanguageConfig {
+            display_name: "Shell".to_string(),
+            file_name: "main.sh".to_string(),
+            version_command: "sh -c 'echo sh'".to_string(),
+            compile_command: None,
+            compile_args: vec![],
+            run_command: "sh".to_string(),
+            run_args: vec!["-c".to_string(), script.to_string()],
+            file_extension: "sh".to_string(),
+            optimization_presets: HashMap::new(),
+            default_timeout_ms: None,
+            default_memory_limit_kb: None,
+            canary: None,
+        }
+    }
+
+    fn test_state(configs: HashMap<String, LanguageConfig>) -> AppState {
+        let registry = LanguageRegistry {
+            available: configs.keys().cloned().collect(),
+            configs,
+            langs_list: vec![],
+        };
+        AppState {
+            languages: Arc::new(std::sync::RwLock::new(Arc::new(registry))),
+            retain_work_dir: false,
+            jobs: Arc::new(RwLock::new(JobStore::new(10))),
+            queue: Arc::new(JobQueue::new(10)),
+            next_id: Arc::new(AtomicU64::new(1)),
+            compile_cache: None,
+            work_root: None,
+            admin_token: None,
+            report_commands: false,
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            rate_limiter: None,
+            activity: Arc::new(Activity::new()),
+            limits: RunLimits::default(),
+            allow_unsafe_env: false,
+            pipeline: Arc::default(),
+            run_slots: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_RUNS)),
+            languages_path: None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn testcase(input: &str, expected: Option<&str>) -> TestCase {
+        TestCase {
+            id: 1,
+            input: input.to_string(),
+            expected: expected.map(str::to_string),
+            timeout_ms: Some(2000),
+            input_from_previous: false,
+            group: None,
+            input_encoding: InputEncoding::Text,
+            memory_limit_kb: None,
+            soft_time_limit_ms: None,
+            args: vec![],
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_case_timestamps_bracket_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("sleep 0.1; cat");
+        let tc = testcase("hi\n", Some("hi\n"));
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.passed, Some(true));
+        assert!(result.finished_at >= result.started_at);
+        let wall_ms = result.finished_at - result.started_at;
+        assert!(wall_ms >= result.duration_ms);
+        assert!(wall_ms - result.duration_ms < 250);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_crash_is_runtime_error_not_wrong_answer() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("echo partial; exit 3");
+        let tc = testcase("", Some("right\n"));
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.verdict, Verdict::RuntimeError);
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.passed, Some(false));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_wrong_output_is_wrong_answer() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("echo wrong");
+        let tc = testcase("", Some("right\n"));
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.verdict, Verdict::WrongAnswer);
+        assert!(result.ok);
+        assert_eq!(result.passed, Some(false));
+        assert_eq!(
+            result.diff.as_deref(),
+            Some("line 1: expected `right` got `wrong`")
+        );
+        let mismatch = result.first_mismatch.unwrap();
+        assert_eq!(mismatch.line, 1);
+        assert_eq!(mismatch.expected.as_deref(), Some("right"));
+        assert_eq!(mismatch.actual.as_deref(), Some("wrong"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_merged_output_preserves_interleaving() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("echo out1; echo err1 >&2; echo out2; echo err2 >&2");
+        let merged = "out1\nerr1\nout2\nerr2\n";
+        let tc = testcase("", Some(merged));
+
+        let opts = CaseOptions {
+            merge_output: true,
+            ..CaseOptions::default()
+        };
+        let result = run_testcase(&cfg, dir.path(), &tc, opts)
+            .await
+            .unwrap();
+        assert_eq!(result.output.as_deref(), Some(merged));
+        assert_eq!(result.verdict, Verdict::Accepted);
+        assert!(result.stdout.is_empty() && result.stderr.is_empty());
+
+        // Separate capture is unchanged when the option is off
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(result.output, None);
+        assert_eq!(result.stdout, "out1\nout2\n");
+        assert_eq!(result.stderr, "err1\nerr2\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_large_output_is_graded_from_spilled_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("seq 1 200000");
+        let expected: String = (1..=200000).map(|n| format!("{}\n", n)).collect();
+        let spill = Spill {
+            path: dir.path().join("1.stdout"),
+            preview_bytes: 16,
+            max_bytes: DEFAULT_MAX_SPILL_BYTES,
+        };
+        let opts = CaseOptions {
+            spill: Some(&spill),
+            ..CaseOptions::default()
+        };
+
+        let tc = testcase("", Some(&expected));
+        let result = run_testcase(&cfg, dir.path(), &tc, opts)
+            .await
+        .unwrap();
+        assert_eq!(result.verdict, Verdict::Accepted);
+        assert_eq!(result.stdout, expected[..16]);
+        assert_eq!(result.spilled_stdout_bytes, Some(expected.len() as u64));
+        assert_eq!(std::fs::read_to_string(&spill.path).unwrap(), expected);
+
+        // The last line differs, well past the preview
+        let wrong = expected.replace("200000\n", "200001\n");
+        let tc = testcase("", Some(&wrong));
+        let result = run_testcase(&cfg, dir.path(), &tc, opts)
+            .await
+        .unwrap();
+        assert_eq!(result.verdict, Verdict::WrongAnswer);
+        assert_eq!(result.first_mismatch, None);
+        assert_eq!(result.diff, None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_endless_spilled_output_is_cut_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill = Spill {
+            path: dir.path().join("1.stdout"),
+            preview_bytes: 16,
+            max_bytes: 64 * 1024,
+        };
+        let opts = CaseOptions {
+            spill: Some(&spill),
+            ..CaseOptions::default()
+        };
+        let tc = TestCase {
+            timeout_ms: Some(10_000),
+            ..testcase("", Some("x\n"))
+        };
+
+        let start = Instant::now();
+        let cfg = shell_config("while :; do echo x; done");
+        let result = run_testcase(&cfg, dir.path(), &tc, opts).await.unwrap();
+        assert!(start.elapsed() < time::Duration::from_secs(5));
+        assert!(result.output_truncated);
+        assert!(!result.ok);
+        assert_eq!(result.spilled_stdout_bytes, Some(64 * 1024));
+        assert_eq!(std::fs::metadata(&spill.path).unwrap().len(), 64 * 1024);
+        assert!(result.stderr.ends_with("Output limit of 65536 bytes exceeded\n"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_within_preview_is_not_spilled() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("echo wrong");
+        let spill = Spill {
+            path: dir.path().join("1.stdout"),
+            preview_bytes: 1024,
+            max_bytes: DEFAULT_MAX_SPILL_BYTES,
+        };
+        let opts = CaseOptions {
+            spill: Some(&spill),
+            ..CaseOptions::default()
+        };
+        let tc = testcase("", Some("right\n"));
+        let result = run_testcase(&cfg, dir.path(), &tc, opts)
+            .await
+        .unwrap();
+
+        assert_eq!(result.stdout, "wrong\n");
+        assert_eq!(result.spilled_stdout_bytes, None);
+        assert_eq!(result.first_mismatch.unwrap().line, 1);
+        assert!(!spill.path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_endless_output_is_cut_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let tc = TestCase {
+            timeout_ms: Some(10_000),
+            ..testcase("", Some("x\n"))
+        };
+        let opts = CaseOptions {
+            max_output_bytes: 64 * 1024,
+            ..CaseOptions::default()
+        };
+
+        let start = Instant::now();
+        let cfg = shell_config("while :; do echo x; done");
+        let result = run_testcase(&cfg, dir.path(), &tc, opts).await.unwrap();
+        assert!(start.elapsed() < time::Duration::from_secs(5));
+        assert!(result.output_truncated);
+        assert!(!result.timed_out);
+        assert!(!result.ok);
+        assert_eq!(result.verdict, Verdict::RuntimeError);
+        assert_eq!(result.stdout.len(), 64 * 1024);
+        assert!(result.stderr.ends_with("Output limit of 65536 bytes exceeded\n"));
+
+        // The cap applies to each stream, and to both together when they are merged
+        let cfg = shell_config("while :; do echo x >&2; done");
+        let result = run_testcase(&cfg, dir.path(), &tc, opts).await.unwrap();
+        assert!(result.output_truncated);
+        assert!(result.stdout.is_empty());
+        let merged = CaseOptions {
+            merge_output: true,
+            ..opts
+        };
+        let result = run_testcase(&cfg, dir.path(), &tc, merged).await.unwrap();
+        assert!(result.output_truncated);
+        assert_eq!(result.output.map(|o| o.len()), Some(64 * 1024));
+
+        let cfg = shell_config("echo x");
+        let result = run_testcase(&cfg, dir.path(), &tc, opts).await.unwrap();
+        assert!(!result.output_truncated);
+        assert_eq!(result.verdict, Verdict::Accepted);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_program_that_stops_reading_early_is_graded() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("read line; echo \"$line\"");
+        // Far more than a pipe buffer, so writing it fails once the program has exited
+        let input = "first\n".to_string() + &"more\n".repeat(200_000);
+        let tc = testcase(&input, Some("first\n"));
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.verdict, Verdict::Accepted);
+        assert_eq!(result.stdout, "first\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ignore_case_accepts_differently_cased_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("echo YES");
+        let tc = testcase("", Some("yes\n"));
+        let comparison = Comparison {
+            ignore_case: true,
+            ..Comparison::default()
+        };
+
+        let strict = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(strict.verdict, Verdict::WrongAnswer);
+
+        let opts = CaseOptions {
+            comparison,
+            ..CaseOptions::default()
+        };
+        let relaxed = run_testcase(&cfg, dir.path(), &tc, opts)
+            .await
+            .unwrap();
+        assert_eq!(relaxed.verdict, Verdict::Accepted);
+        assert_eq!(relaxed.passed, Some(true));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_large_input_echoed_back_does_not_deadlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("exec cat");
+        // Far larger than an OS pipe buffer in both directions
+        let input = "0123456789abcdef\n".repeat(64 * 1024);
+        let mut tc = testcase(&input, Some(&input));
+        tc.timeout_ms = Some(10_000);
+        // Over the default output limit, which is not what this is about
+        let opts = CaseOptions {
+            max_output_bytes: 2 * input.len(),
+            ..CaseOptions::default()
+        };
+        let result = run_testcase(&cfg, dir.path(), &tc, opts).await.unwrap();
+
+        assert!(!result.timed_out);
+        assert_eq!(result.stdout.len(), input.len());
+        assert_eq!(result.verdict, Verdict::Accepted);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_case_without_expected_output_is_only_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("echo out; echo err >&2");
+        let tc = testcase("", None);
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.passed, None);
+        assert_eq!(result.verdict, Verdict::NotJudged);
+        assert_eq!(result.stdout, "out\n");
+        assert_eq!(result.stderr, "err\n");
+        assert_eq!(result.exit_code, Some(0));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pipeline_feeds_previous_stdout_forward() {
+        let mut configs = HashMap::new();
+        // Each stage upper-cases its input and appends a marker line
+        configs.insert("sh".to_string(), shell_config("tr a-z A-Z; echo stage"));
+        let state = test_state(configs);
+        let mut second = testcase("", Some("HELLO\nSTAGE\nstage\n"));
+        second.id = 2;
+        second.input_from_previous = true;
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("hello\n", Some("HELLO\nstage\n")), second],
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].verdict, Verdict::Accepted);
+        assert_eq!(resp.results[1].input, "HELLO\nstage\n");
+        assert_eq!(resp.results[1].verdict, Verdict::Accepted);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_case_zeroes_its_subtask() {
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), shell_config("cat"));
+        let state = test_state(configs);
+        let case = |id, expected: &str, group: &str| TestCase {
+            id,
+            group: Some(group.to_string()),
+            ..testcase("x\n", Some(expected))
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![case(1, "x\n", "a"), case(2, "y\n", "a"), case(3, "x\n", "b")],
+            group_weights: HashMap::from([("a".to_string(), 40), ("b".to_string(), 60)]),
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        let subtasks = resp.subtasks.expect("grouped cases should be scored");
+        assert_eq!(subtasks.groups[0].score, 0);
+        assert_eq!(subtasks.groups[1].score, 60);
+        assert_eq!((subtasks.score, subtasks.max_score), (60, 100));
+        assert_eq!(resp.results.len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_scoreboard_tallies_mixed_verdicts() {
+        let script = r#"read line; [ "$line" = crash ] && exit 3; echo "$line""#;
+        let state = test_state(HashMap::from([("sh".to_string(), shell_config(script))]));
+        let case = |id, input: &str, expected: &str| TestCase {
+            id,
+            ..testcase(input, Some(expected))
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![
+                case(1, "a\n", "a\n"),
+                case(2, "b\n", "b\n"),
+                case(3, "c\n", "x\n"),
+                case(4, "crash\n", "crash\n"),
+            ],
+            ..request(Priority::Normal)
+        };
+
+        let board = execute_request(&req, &state, None).await.unwrap().scoreboard;
+        assert_eq!(board.accepted, 2);
+        assert_eq!(board.wrong_answer, 1);
+        assert_eq!(board.runtime_error, 1);
+        let categories = board.accepted
+            + board.wrong_answer
+            + board.runtime_error
+            + board.timed_out
+            + board.memory_limit_exceeded
+            + board.compile_error
+            + board.not_judged;
+        assert_eq!(categories, board.total);
+        assert_eq!(board.total, 4);
+        assert_eq!((board.score, board.max_score), (2, 4));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_skips_remaining_cases() {
+        let state = test_state(HashMap::from([("sh".to_string(), shell_config("sleep 0.3; cat"))]));
+        let case = |id| TestCase {
+            id,
+            ..testcase("x\n", Some("x\n"))
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: (1..=4).map(case).collect(),
+            ..request(Priority::Normal)
+        };
+
+        // Signal shutdown while the first case is still running
+        let flag = state.shutting_down.clone();
+        tokio::spawn(async move {
+            time::sleep(time::Duration::from_millis(100)).await;
+            flag.store(true, Ordering::SeqCst);
+        });
+        let resp = execute_request(&req, &state, None).await.unwrap();
+
+        assert!(matches!(resp.status, Some(ExecutionStatus::Cancelled)));
+        let verdicts: Vec<Verdict> = resp.results.iter().map(|r| r.verdict).collect();
+        assert_eq!(
+            verdicts,
+            vec![Verdict::Accepted, Verdict::NotRun, Verdict::NotRun, Verdict::NotRun]
+        );
+        assert_eq!(resp.scoreboard.not_run, 3);
+        assert_eq!(resp.total_duration_ms, resp.results[0].duration_ms);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cases_run_concurrently_in_order() {
+        let script = "sleep 0.5; tr a-z A-Z";
+        let mut state = test_state(HashMap::from([("sh".to_string(), shell_config(script))]));
+        state.limits.max_parallel_cases = 4;
+        let case = |id, input: &str, expected: &str| TestCase {
+            id,
+            ..testcase(input, Some(expected))
+        };
+        // Case 3 reads case 2's output, so those two still run one after the other
+        let mut piped = case(3, "", "B\n");
+        piped.input_from_previous = true;
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![
+                case(1, "a\n", "A\n"),
+                case(2, "b\n", "B\n"),
+                piped,
+                case(4, "d\n", "D\n"),
+                case(5, "e\n", "E\n"),
+            ],
+            ..request(Priority::Normal)
+        };
+
+        let start = Instant::now();
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        let elapsed = start.elapsed();
+
+        let ids: Vec<i32> = resp.results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        assert!(resp.results.iter().all(|r| r.ok), "{:?}", resp.results);
+        assert_eq!(resp.results[2].input, "B\n");
+        // Five half-second cases in two rounds, not five
+        assert!(elapsed < time::Duration::from_millis(2000), "{:?}", elapsed);
+        assert!(elapsed >= time::Duration::from_millis(1000), "{:?}", elapsed);
+    }
+
+    #[cfg(unix)]
+    fn reference_request(student: &str, reference: &str) -> ExecuteRequest {
+        let case = |id, input: &str| TestCase {
+            id,
+            ..testcase(input, None)
+        };
+        ExecuteRequest {
+            language: "sh".to_string(),
+            code: student.to_string(),
+            testcases: vec![case(1, "abc\n"), case(2, "xyz\n")],
+            reference: Some(ReferenceSolution {
+                language: None,
+                code: reference.to_string(),
+            }),
+            ..request(Priority::Normal)
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reference_output_becomes_expected() {
+        let state = test_state(HashMap::from([("sh".to_string(), shell_config("sh main.sh"))]));
+        let req = reference_request("while read l; do echo \"$l\"; done", "cat");
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].expected.as_deref(), Some("abc\n"));
+        assert_eq!(resp.results[1].expected.as_deref(), Some("xyz\n"));
+        assert!(resp.results.iter().all(|r| r.verdict == Verdict::Accepted));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_diverging_from_reference_is_wrong_answer() {
+        let state = test_state(HashMap::from([("sh".to_string(), shell_config("sh main.sh"))]));
+        let req = reference_request("tr a-z A-Z", "cat");
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].stdout, "ABC\n");
+        assert_eq!(resp.results[0].expected.as_deref(), Some("abc\n"));
+        assert!(resp.results.iter().all(|r| r.verdict == Verdict::WrongAnswer));
+
+        // A reference that fails can't produce an answer key
+        let err = execute_request(&reference_request("cat", "exit 1"), &state, None)
+            .await
+            .unwrap_err();
+        assert!(err.message.starts_with("Reference solution failed on test case 1"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_total_timeout_includes_the_reference_run() {
+        let state = test_state(HashMap::from([("sh".to_string(), shell_config("sh main.sh"))]));
+        let mut req = reference_request("cat", "sleep 5; cat");
+        for tc in &mut req.testcases {
+            tc.timeout_ms = Some(10_000);
+        }
+        req.total_timeout_ms = Some(500);
+
+        let start = Instant::now();
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(start.elapsed() < time::Duration::from_secs(3));
+        assert!(matches!(resp.status, Some(ExecutionStatus::Timeout)));
+        assert!(resp.results.is_empty());
+        assert!(resp.message.unwrap().contains("total timeout of 500 ms"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bulk_streams_results_as_submissions_arrive() {
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), shell_config("cat"));
+        let state = test_state(configs);
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Result<Bytes, Infallible>>();
+        let resp = bulk_handler(
+            State(state.clone()),
+            ConnectInfo(CLIENT),
+            header::HeaderMap::new(),
+            Body::from_stream(rx),
+        )
+        .await;
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/x-ndjson");
+        let mut output = Box::pin(ndjson_lines(resp.into_body(), usize::MAX));
+
+        let submission = |id: &str, input: &str| {
+            let req = ExecuteRequest {
+                language: "sh".to_string(),
+                code: "cat".to_string(),
+                testcases: vec![testcase(input, Some(input))],
+                ..request(Priority::Normal)
+            };
+            let mut line = serde_json::to_value(req).unwrap();
+            line["id"] = serde_json::json!(id);
+            format!("{}\n", line)
+        };
+        // Each result arrives while the request body is still open
+        for (id, input) in [("alice", "1\n"), ("bob", "2\n")] {
+            let line = submission(id, input);
+            // Split mid-line to exercise reassembly across chunks
+            let (head, tail) = line.split_at(10);
+            tx.unbounded_send(Ok(Bytes::from(head.to_string()))).unwrap();
+            tx.unbounded_send(Ok(Bytes::from(tail.to_string()))).unwrap();
+
+            let result = output.next().await.unwrap().unwrap();
+            let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(result["id"], id);
+            assert_eq!(result["results"][0]["stdout"], input);
+            assert_eq!(result["results"][0]["verdict"], "accepted");
+        }
+
+        tx.unbounded_send(Ok(Bytes::from("\n{not json\n"))).unwrap();
+        let result = output.next().await.unwrap().unwrap();
+        let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert!(result["error"].as_str().unwrap().starts_with("Line 4:"));
+
+        drop(tx);
+        assert!(output.next().await.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sync_execute_answers_inline_or_times_out() {
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), shell_config("cat"));
+        configs.insert("slow".to_string(), shell_config("sleep 5"));
+        let mut state = test_state(configs);
+        state.limits.sync_timeout = time::Duration::from_millis(300);
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("1\n", Some("1\n"))],
+            ..request(Priority::Normal)
+        };
+
+        let resp = sync_handler(State(state.clone()), JsonBody(req.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["results"][0]["verdict"], "accepted");
+        // Nothing went through the job store
+        assert!(state.jobs.read().await.jobs.is_empty());
+
+        let req = ExecuteRequest {
+            language: "slow".to_string(),
+            testcases: vec![TestCase {
+                timeout_ms: Some(10_000),
+                ..testcase("", Some(""))
+            }],
+            ..req
+        };
+        let start = Instant::now();
+        let resp = sync_handler(State(state), JsonBody(req)).await;
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(start.elapsed() < time::Duration::from_secs(3));
+        let body = response_json(resp).await;
+        assert_eq!(body["error"], "Execution exceeded 300 ms");
+    }
+
+    #[tokio::test]
+    async fn test_bulk_reports_invalid_submission_with_its_id() {
+        let state = validation_state();
+        let line = r#"{"id": 7, "language": "cobol", "code": "x", "testcases": []}"#;
+        let resp = bulk_handler(
+            State(state),
+            ConnectInfo(CLIENT),
+            header::HeaderMap::new(),
+            Body::from(line),
+        )
+        .await;
+        let mut output = Box::pin(ndjson_lines(resp.into_body(), usize::MAX));
+
+        let result = output.next().await.unwrap().unwrap();
+        let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(result["id"], 7);
+        assert_eq!(result["errors"][0]["field"], "language");
+        assert!(output.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_charges_each_submission_to_the_rate_limit() {
+        let state = AppState {
+            rate_limiter: Some(Arc::new(RateLimiter::new(1, 2, [], []))),
+            ..validation_state()
+        };
+        let line = |id| {
+            format!(r#"{{"id": {}, "language": "x", "code": "", "testcases": []}}"#, id)
+        };
+        let lines: String = (1..=3).map(|id| line(id) + "\n").collect();
+        let resp = bulk_handler(
+            State(state.clone()),
+            ConnectInfo(CLIENT),
+            header::HeaderMap::new(),
+            Body::from(lines),
+        )
+        .await;
+        let results: Vec<serde_json::Value> = ndjson_lines(resp.into_body(), usize::MAX)
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect()
+            .await;
+        let throttled = results
+            .iter()
+            .filter(|r| r["error"].as_str().unwrap().starts_with("Rate limit exceeded"))
+            .count();
+        assert_eq!((results.len(), throttled), (3, 1));
+
+        // A made-up key is turned away before anything runs
+        let mut headers = header::HeaderMap::new();
+        headers.insert(API_KEY_HEADER, "made-up".parse().unwrap());
+        let resp = bulk_handler(State(state), ConnectInfo(CLIENT), headers, Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_submissions_wait_for_a_shared_run_slot() {
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), shell_config("cat"));
+        let state = AppState {
+            run_slots: Arc::new(Semaphore::new(1)),
+            ..test_state(configs)
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            code: "cat".to_string(),
+            testcases: vec![testcase("1\n", Some("1\n"))],
+            ..request(Priority::Normal)
+        };
+
+        // Whoever holds the only slot, a sync submission waits for it
+        let slot = state.run_slots.clone().acquire_owned().await.unwrap();
+        let sync = tokio::spawn(sync_handler(State(state.clone()), JsonBody(req)));
+        time::sleep(time::Duration::from_millis(300)).await;
+        assert!(!sync.is_finished());
+        drop(slot);
+        let resp = sync.await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(state.run_slots.available_permits(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_echoed_stdin_is_decoded_base64_input() {
+        let mut configs = HashMap::new();
+        // Report how many bytes arrived so binary stdin needn't survive stdout decoding
+        configs.insert("sh".to_string(), shell_config("wc -c | tr -d ' '"));
+        let state = test_state(configs);
+        let bytes = b"\x00\xff\r\nbinary";
+        let case = TestCase {
+            input: BASE64_STANDARD.encode(bytes),
+            input_encoding: InputEncoding::Base64,
+            ..testcase("", Some("10\n"))
+        };
+        let mut req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![case],
+            echo_stdin: true,
+            merge_output: false,
+            reference: None,
+            spill_output_bytes: None,
+            env: HashMap::new(),
+            compile_only: false,
+            files: vec![],
+            total_timeout_ms: None,
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].verdict, Verdict::Accepted);
+        let echoed = resp.results[0].stdin_base64.as_deref().unwrap();
+        assert_eq!(BASE64_STANDARD.decode(echoed).unwrap(), bytes);
+
+        req.echo_stdin = false;
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(resp.results[0].stdin_base64.is_none());
+    }
+
+    // Grows a megabyte at a time until the limit stops it, and holds on to what it got for a
+    // moment. `exec` so the sampled process is the one allocating; the bytes are written, so
+    // they count toward resident memory.
+    #[cfg(unix)]
+    const MEMORY_HOG: &str = "exec python3 -c '
+import time
+held = []
+try:
+    while True:
+        held.append(b\"x\" * (1 << 20))
+except MemoryError:
+    time.sleep(0.2)
+    raise
+'";
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_memory_hog_is_killed_for_memory_not_time() {
+        if which::which("python3").is_err() {
+            eprintln!("skipping memory limit test: python3 not installed");
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config(MEMORY_HOG);
+        let mut tc = testcase("", Some(""));
+        tc.memory_limit_kb = Some(64 * 1024);
+        tc.timeout_ms = Some(4000);
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert!(result.memory_exceeded);
+        assert!(!result.timed_out);
+        assert!(!result.ok);
+        assert_eq!(result.verdict, Verdict::MemoryLimitExceeded);
+        assert!(result.stderr.ends_with("Memory limit of 65536 KiB exceeded\n"));
+        // On Linux the data segment limit refuses the allocation close to the limit;
+        // elsewhere the program is killed once it has grown past it
+        if cfg!(target_os = "linux") {
+            assert!(peak_near_limit(result.memory_kb, 64 * 1024));
+        } else {
+            assert!(result.memory_kb > 64 * 1024);
+        }
+        assert!(result.duration_ms < 4000);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_out_of_memory_message_alone_is_a_runtime_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("echo 'MemoryError: out of memory' >&2; exit 1");
+        let mut tc = testcase("", Some(""));
+        tc.memory_limit_kb = Some(64 * 1024);
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert!(!result.memory_exceeded);
+        assert_eq!(result.verdict, Verdict::RuntimeError);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_memory_limit_sets_the_overall_status() {
+        if which::which("python3").is_err() {
+            eprintln!("skipping memory status test: python3 not installed");
+            return;
+        }
+        let cfg = shell_config(MEMORY_HOG);
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![TestCase {
+                memory_limit_kb: Some(64 * 1024),
+                timeout_ms: Some(4000),
+                ..testcase("", Some(""))
+            }],
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(resp.results[0].memory_exceeded);
+        assert!(matches!(
+            resp.status,
+            Some(ExecutionStatus::MemoryLimitExceeded)
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_memory_limit_does_not_apply_to_compiling() {
+        let mut cfg = shell_config("echo ok");
+        // The "compiler" holds ~30 MB, well over the cases' limit
+        cfg.compile_command = Some("sh".to_string());
+        cfg.compile_args = vec![
+            "-c".to_string(),
+            "x=$(head -c 30000000 /dev/zero | tr '\\0' a)".to_string(),
+        ];
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            code: "unused".to_string(),
+            testcases: vec![TestCase {
+                memory_limit_kb: Some(16 * 1024),
+                ..testcase("", Some("ok\n"))
+            }],
+            ..request(Priority::Normal)
+        };
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(resp.compiled);
+        assert_eq!(resp.results[0].verdict, Verdict::Accepted);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_peak_rss_is_read_from_proc() {
+        let peak_kb = peak_rss_kb(std::process::id()).unwrap();
+        assert!(peak_kb > 0);
+        assert_eq!(peak_rss_kb(u32::MAX), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_peak_memory_outlasts_a_freed_allocation() {
+        let dir = tempfile::tempdir().unwrap();
+        // The shell holds ~30 MB for a moment, frees it, and keeps running small
+        let cfg = shell_config("x=$(head -c 30000000 /dev/zero | tr '\\0' a); x=; sleep 0.2");
+        let tc = testcase("", None);
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert!(result.ok);
+        assert!(result.memory_kb > 25_000, "peak was {} KiB", result.memory_kb);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_exit_peak_is_read_before_the_program_is_reaped() {
+        // Holds ~30 MB and exits right away, between memory samples or not
+        let mut child = Command::new("sh")
+            .args(["-c", "x=$(head -c 30000000 /dev/zero | tr '\\0' a)"])
+            .spawn()
+            .unwrap();
+        let peak_kb = wait_unreaped(child.id()).await.unwrap();
+        assert!(peak_kb > 25_000, "peak was {} KiB", peak_kb);
+        // Still there for tokio to reap
+        assert!(child.wait().await.unwrap().success());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_soft_time_limit_flags_slow_case_without_failing_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("sleep 0.2; cat");
+        let mut tc = testcase("hi\n", Some("hi\n"));
+        tc.soft_time_limit_ms = Some(50);
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+        assert!(result.slow);
+        assert_eq!(result.passed, Some(true));
+        assert_eq!(result.verdict, Verdict::Accepted);
+
+        tc.soft_time_limit_ms = Some(1500);
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+        assert!(!result.slow);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_slow_program_is_timed_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = shell_config("exec sleep 5");
+        let mut tc = testcase("", Some(""));
+        tc.timeout_ms = Some(100);
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.verdict, Verdict::TimedOut);
+        assert!(result.timed_out);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timed_out_case_keeps_partial_output() {
+        let dir = tempfile::tempdir().unwrap();
+        // `sleep` is a child of the shell and keeps the output pipe open after the kill
+        let cfg = shell_config("echo started; echo working >&2; sleep 2");
+        let mut tc = testcase("", Some("done\n"));
+        tc.timeout_ms = Some(300);
+        let start = Instant::now();
+        let result = run_testcase(&cfg, dir.path(), &tc, CaseOptions::default())
+            .await
+            .unwrap();
+
+        assert!(start.elapsed() < time::Duration::from_millis(1500));
+        assert_eq!(result.verdict, Verdict::TimedOut);
+        assert_eq!(result.stdout, "started\n");
+        assert_eq!(result.stderr, "working\n");
+
+        let opts = CaseOptions {
+            merge_output: true,
+            ..CaseOptions::default()
+        };
+        let result = run_testcase(&cfg, dir.path(), &tc, opts)
+            .await
+            .unwrap();
+        assert!(result.timed_out);
+        assert_eq!(result.output.as_deref(), Some("started\nworking\n"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_empty_testcases_reports_no_tests_run() {
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), shell_config("cat"));
+        let state = test_state(configs);
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(matches!(resp.status, Some(ExecutionStatus::NoTestsRun)));
+        assert!(resp.results.is_empty());
+
+        let json = serde_json::to_value(&resp).unwrap();
+        assert_eq!(json["status"], "no_tests_run");
+        assert!(json.get("results").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reload_languages_swaps_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("languages.json");
+        std::fs::write(
+            &path,
+            r#"{"shell": {
+                "display_name": "Shell",
+                "file_name": "main.sh",
+                "version_command": "echo sh 1.0",
+                "run_command": "sh",
+                "run_args": ["main.sh"]
+            }}"#,
+        )
+        .unwrap();
+
+        let state = test_state(HashMap::new());
+        reload_languages(&state, Some(&path)).await.unwrap();
+
+        let languages = state.languages();
+        assert!(languages.available.contains("shell"));
+        assert_eq!(languages.langs_list[0].display_name, "Shell");
+    }
+
+    #[tokio::test]
+    async fn test_reload_languages_handler_requires_admin_and_keeps_registry_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("languages.json");
+        std::fs::write(
+            &path,
+            r#"{"shell": {
+                "display_name": "Shell",
+                "file_name": "main.sh",
+                "version_command": "echo sh 1.0",
+                "run_command": "sh",
+                "run_args": ["main.sh"]
+            }}"#,
+        )
+        .unwrap();
+        let state = AppState {
+            admin_token: Some("secret".to_string()),
+            languages_path: Some(path.clone()),
+            ..test_state(HashMap::new())
+        };
+
+        let mut headers = header::HeaderMap::new();
+        let resp = reload_languages_handler(State(state.clone()), headers.clone()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(state.languages().available.is_empty());
+
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let resp = reload_languages_handler(State(state.clone()), headers.clone()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(response_json(resp).await["available"], serde_json::json!(["shell"]));
+
+        // A broken file is reported and the languages loaded before stay available
+        std::fs::write(&path, "{ not json").unwrap();
+        let resp = reload_languages_handler(State(state.clone()), headers).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(state.languages().available.contains("shell"));
+    }
+
+    #[test]
+    fn test_resolve_program_relative_to_work_dir() {
+        let work_dir = std::path::Path::new("/tmp/job");
+        assert_eq!(resolve_program("./main", work_dir), work_dir.join("./main"));
+        assert_eq!(resolve_program("java", work_dir), std::path::PathBuf::from("java"));
+    }
+
+    // Compile then run via the real language config, skipping when the toolchain is missing
+    async fn run_two_phase(language: &str, tool: &str, code: &str) -> Option<ExecuteResponse> {
+        run_two_phase_with(language, tool, code, None).await
+    }
+
+    async fn run_two_phase_with(
+        language: &str,
+        tool: &str,
+        code: &str,
+        optimization: Option<&str>,
+    ) -> Option<ExecuteResponse> {
+        if which::which(tool).is_err() {
+            eprintln!("skipping {} test: {} not installed", language, tool);
+            return None;
+        }
+        let configs = crate::language::generate_language_configs();
+        let mut single = HashMap::new();
+        single.insert(language.to_string(), configs[language].clone());
+        let state = test_state(single);
+        let req = ExecuteRequest {
+            language: language.to_string(),
+            code: code.to_string(),
+            testcases: vec![TestCase {
+                id: 1,
+                input: String::new(),
+                expected: Some("hi\n".to_string()),
+                timeout_ms: Some(30_000),
+                input_from_previous: false,
+                group: None,
+                input_encoding: InputEncoding::Text,
+                memory_limit_kb: None,
+                soft_time_limit_ms: None,
+                args: vec![],
+            }],
+            optimization: optimization.map(str::to_string),
+            ..request(Priority::Normal)
+        };
+        Some(execute_request(&req, &state, None).await.unwrap())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_seed_is_exposed_to_python_run() {
+        if which::which("python3").is_err() {
+            eprintln!("skipping seed test: python3 not installed");
+            return;
+        }
+        let configs = crate::language::generate_language_configs();
+        let mut single = HashMap::new();
+        single.insert("python3".to_string(), configs["python3"].clone());
+        let state = test_state(single);
+        let code = "import os\nprint(os.environ['SEED'], os.environ['PYTHONHASHSEED'])\n";
+        let req = ExecuteRequest {
+            language: "python3".to_string(),
+            code: code.to_string(),
+            testcases: vec![testcase("", Some("4294967301 5\n"))],
+            // Larger than 32 bits, so PYTHONHASHSEED wraps
+            seed: Some((1 << 32) + 5),
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].verdict, Verdict::Accepted, "{:?}", resp.results[0]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_args_are_passed_to_python_program() {
+        if which::which("python3").is_err() {
+            eprintln!("skipping args test: python3 not installed");
+            return;
+        }
+        let configs = crate::language::generate_language_configs();
+        let state = test_state(HashMap::from([(
+            "python3".to_string(),
+            configs["python3"].clone(),
+        )]));
+        let req = ExecuteRequest {
+            language: "python3".to_string(),
+            code: "import sys\nprint(sys.argv[1])\nprint(len(sys.argv))\n".to_string(),
+            testcases: vec![TestCase {
+                args: vec!["hello world".to_string(), "--flag".to_string()],
+                ..testcase("", Some("hello world\n3\n"))
+            }],
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        let result = &resp.results[0];
+        assert_eq!(result.verdict, Verdict::Accepted, "stderr: {}", result.stderr);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_request_env_reaches_compile_and_run() {
+        let mut cfg = shell_config("cat built.txt; echo \"$GREETING $SEED\"");
+        cfg.compile_command = Some("sh".to_string());
+        cfg.compile_args = vec!["-c".to_string(), "echo \"$GREETING\" > built.txt".to_string()];
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("", Some("hi\nhi 7\n"))],
+            seed: Some(3),
+            // Set explicitly, it wins over the value derived from `seed`
+            env: HashMap::from([
+                ("GREETING".to_string(), "hi".to_string()),
+                ("SEED".to_string(), "7".to_string()),
+            ]),
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(resp.compiled);
+        assert_eq!(resp.results[0].stdout, "hi\nhi 7\n");
+        assert_eq!(resp.results[0].verdict, Verdict::Accepted);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_args_follow_the_languages_run_args() {
+        // `sh -c script name arg...`: the case's args become $1, $2, ...
+        let mut cfg = shell_config("echo \"$0|$1|$2\"");
+        cfg.run_args.push("prog".to_string());
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![TestCase {
+                args: vec!["a b".to_string(), "*".to_string()],
+                ..testcase("", Some("prog|a b|*\n"))
+            }],
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].stdout, "prog|a b|*\n");
+    }
+
+    #[tokio::test]
+    async fn test_kotlin_compiles_jar_and_runs_it() {
+        let code = "fun main() {\n    println(\"hi\")\n}\n";
+        let Some(resp) = run_two_phase("kotlin", "kotlinc", code).await else {
+            return;
+        };
+        assert!(resp.compiled, "compile failed: {:?}", resp.message);
+        assert_eq!(resp.results[0].passed, Some(true), "stderr: {}", resp.results[0].stderr);
+    }
+
+    #[tokio::test]
+    async fn test_c_compiles_binary_and_runs_it() {
+        let code = "#include <stdio.h>\nint main(void) { puts(\"hi\"); return 0; }\n";
+        let Some(resp) = run_two_phase("gcc", "gcc", code).await else {
+            return;
+        };
+        assert!(resp.compiled, "compile failed: {:?}", resp.message);
+        assert_eq!(resp.results[0].passed, Some(true), "stderr: {}", resp.results[0].stderr);
+        assert_eq!(resp.compile_exit_code, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_c_release_preset_compiles() {
+        let code = "#include <stdio.h>\nint main(void) { puts(\"hi\"); return 0; }\n";
+        let Some(resp) = run_two_phase_with("gcc", "gcc", code, Some("release")).await else {
+            return;
+        };
+        assert!(resp.compiled, "compile failed: {:?}", resp.message);
+        assert_eq!(resp.results[0].passed, Some(true), "stderr: {}", resp.results[0].stderr);
+    }
+
+    #[tokio::test]
+    async fn test_compile_failure_reports_compiler_exit_code() {
+        let code = "int main(void) { return undeclared; }\n";
+        let Some(resp) = run_two_phase("gcc", "gcc", code).await else {
+            return;
+        };
+        assert!(matches!(resp.status, Some(ExecutionStatus::CompileError)));
+        let exit_code = resp.compile_exit_code.expect("compiler exit code should be captured");
+        assert_ne!(exit_code, 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_segfault_reports_signal_and_runtime_error() {
+        let code = "#include <stdio.h>\nint main(void) { puts(\"hi\"); fflush(stdout); \
+                    volatile int *p = 0; return *p; }\n";
+        let Some(resp) = run_two_phase("gcc", "gcc", code).await else {
+            return;
+        };
+        assert!(resp.compiled, "compile failed: {:?}", resp.message);
+        assert!(matches!(resp.status, Some(ExecutionStatus::RuntimeError)));
+        let result = &resp.results[0];
+        assert_eq!(result.term_signal, Some(libc::SIGSEGV));
+        assert_eq!(result.exit_code, None);
+        assert_eq!(result.verdict, Verdict::RuntimeError);
+        assert_eq!(result.stdout, "hi\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_limit_kills_and_plain_failures_are_not_crashes() {
+        let state = test_state(HashMap::from([
+            ("sleep".to_string(), shell_config("sleep 5")),
+            ("fail".to_string(), shell_config("exit 3")),
+        ]));
+        let req = ExecuteRequest {
+            language: "sleep".to_string(),
+            testcases: vec![TestCase {
+                timeout_ms: Some(100),
+                ..testcase("", Some(""))
+            }],
+            ..request(Priority::Normal)
+        };
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].term_signal, Some(libc::SIGKILL));
+        assert!(matches!(resp.status, Some(ExecutionStatus::Success)));
+
+        let req = ExecuteRequest {
+            language: "fail".to_string(),
+            ..req
+        };
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].term_signal, None);
+        assert_eq!(resp.results[0].exit_code, Some(3));
+        assert!(matches!(resp.status, Some(ExecutionStatus::Success)));
+    }
+
+    #[tokio::test]
+    async fn test_typescript_compiles_and_runs() {
+        let code = "const greeting: string = \"hi\";\nconsole.log(greeting);\n";
+        let Some(resp) = run_two_phase("typescript", "tsc", code).await else {
+            return;
+        };
+        assert!(resp.compiled, "compile failed: {:?}", resp.message);
+        assert_eq!(resp.results[0].passed, Some(true), "stderr: {}", resp.results[0].stderr);
+    }
+
+    #[tokio::test]
+    async fn test_typescript_type_error_is_compile_error() {
+        let code = "const greeting: number = \"hi\";\nconsole.log(greeting);\n";
+        let Some(resp) = run_two_phase("typescript", "tsc", code).await else {
+            return;
+        };
+        assert!(matches!(resp.status, Some(ExecutionStatus::CompileError)));
+        assert!(resp.results.is_empty());
+        assert_ne!(resp.compile_exit_code, Some(0));
+        assert!(resp.message.unwrap().contains("TS2322"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_compile_errors_printed_on_stdout_are_reported() {
+        let mut cfg = shell_config("cat");
+        cfg.compile_command = Some("sh".to_string());
+        let script = "echo \"main.ts(1,7): error TS2322: Type 'string' is not assignable\"; exit 2";
+        cfg.compile_args = vec!["-c".to_string(), script.to_string()];
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(matches!(resp.status, Some(ExecutionStatus::CompileError)));
+        assert_eq!(resp.compile_exit_code, Some(2));
+        assert!(resp.message.unwrap().contains("error TS2322"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_compile_only_reports_diagnostics_without_running() {
+        let scratch = tempfile::tempdir().unwrap();
+        let log = scratch.path().join("runs.log");
+        let mut compiled = shell_config(&format!("echo ran >> {}", log.display()));
+        compiled.compile_command = Some("sh".to_string());
+        compiled.compile_args = vec![
+            "-c".to_string(),
+            "echo 'main.sh:1: warning: unused variable' >&2".to_string(),
+        ];
+        let interpreted = shell_config(&format!("echo ran >> {}", log.display()));
+        let state = test_state(HashMap::from([
+            ("compiled".to_string(), compiled),
+            ("interpreted".to_string(), interpreted),
+        ]));
+        let req = ExecuteRequest {
+            language: "compiled".to_string(),
+            testcases: vec![testcase("", Some("ran\n"))],
+            compile_only: true,
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(matches!(resp.status, Some(ExecutionStatus::Success)));
+        assert!(resp.compiled);
+        assert_eq!(resp.compile_exit_code, Some(0));
+        assert_eq!(
+            resp.message.as_deref(),
+            Some("main.sh:1: warning: unused variable\n")
+        );
+        assert!(resp.results.is_empty());
+
+        let req = ExecuteRequest {
+            language: "interpreted".to_string(),
+            ..req
+        };
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(matches!(resp.status, Some(ExecutionStatus::Success)));
+        assert!(!resp.compiled);
+        assert_eq!(resp.message, None);
+        assert!(resp.results.is_empty());
+        assert!(!log.exists(), "nothing should have run");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_compile_only_reports_compile_errors() {
+        let state = failing_compile_state(false);
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            compile_only: true,
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(matches!(resp.status, Some(ExecutionStatus::CompileError)));
+        assert!(!resp.compiled);
+        assert_eq!(resp.message.as_deref(), Some("broken\n"));
+    }
+
+    #[tokio::test]
+    async fn test_c_program_split_across_files() {
+        if which::which("gcc").is_err() {
+            eprintln!("skipping multi-file test: gcc not installed");
+            return;
+        }
+        let configs = crate::language::generate_language_configs();
+        let mut single = HashMap::new();
+        single.insert("gcc".to_string(), configs["gcc"].clone());
+        let state = test_state(single);
+        let main = "#include <stdio.h>\n#include \"helper.c\"\n\
+                    int main() { int n; scanf(\"%d\", &n); printf(\"%d\\n\", twice(n)); }\n";
+        let req = ExecuteRequest {
+            language: "gcc".to_string(),
+            code: String::new(),
+            testcases: vec![testcase("21\n", Some("42\n"))],
+            files: vec![
+                SourceFile {
+                    name: "main.c".to_string(),
+                    content: main.to_string(),
+                },
+                SourceFile {
+                    name: "helper.c".to_string(),
+                    content: "int twice(int n) { return 2 * n; }\n".to_string(),
+                },
+            ],
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(resp.compiled, "{:?}", resp.message);
+        assert!(matches!(resp.status, Some(ExecutionStatus::Success)));
+        assert_eq!(resp.results[0].verdict, Verdict::Accepted);
+
+        // Plain `code` is still the entry file, next to the extra files
+        let req = ExecuteRequest {
+            code: main.to_string(),
+            files: req.files[1..].to_vec(),
+            ..req
+        };
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert_eq!(resp.results[0].verdict, Verdict::Accepted);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_total_timeout_stops_a_slow_compile() {
+        let mut cfg = shell_config("cat");
+        cfg.compile_command = Some("sh".to_string());
+        cfg.compile_args = vec!["-c".to_string(), "sleep 5".to_string()];
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("1\n", Some("1\n"))],
+            total_timeout_ms: Some(300),
+            ..request(Priority::Normal)
+        };
+
+        let start = Instant::now();
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(start.elapsed() < time::Duration::from_secs(3));
+        assert!(matches!(resp.status, Some(ExecutionStatus::Timeout)));
+        assert!(!resp.compiled);
+        assert!(resp.results.is_empty());
+        assert!(resp.message.unwrap().contains("total timeout of 300 ms"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_total_timeout_keeps_finished_cases() {
+        // Each case sleeps for as many seconds as its input says
+        let cfg = shell_config("read secs; sleep $secs; echo done");
+        let state = test_state(HashMap::from([("sh".to_string(), cfg)]));
+        let case = |id, input: &str| TestCase {
+            id,
+            timeout_ms: Some(10_000),
+            ..testcase(input, Some("done\n"))
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![case(1, "0\n"), case(2, "5\n"), case(3, "0\n")],
+            total_timeout_ms: Some(1000),
+            ..request(Priority::Normal)
+        };
+
+        let start = Instant::now();
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(start.elapsed() < time::Duration::from_secs(4));
+        assert!(matches!(resp.status, Some(ExecutionStatus::Timeout)));
+        let verdicts: Vec<Verdict> = resp.results.iter().map(|r| r.verdict).collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                Verdict::Accepted,
+                Verdict::TimedOut,
+                Verdict::NotRun
+            ]
+        );
+
+        // A deadline that is never reached changes nothing
+        let req = ExecuteRequest {
+            testcases: vec![case(1, "0\n")],
+            total_timeout_ms: Some(10_000),
+            ..req
+        };
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(matches!(resp.status, Some(ExecutionStatus::Success)));
+        assert_eq!(resp.message, None);
+    }
+
+    #[cfg(unix)]
+    fn failing_compile_state(retain_work_dir: bool) -> AppState {
+        let mut cfg = shell_config("cat");
+        cfg.compile_command = Some("sh".to_string());
+        cfg.compile_args = vec!["-c".to_string(), "echo broken >&2; exit 1".to_string()];
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), cfg);
+        AppState {
+            retain_work_dir,
+            ..test_state(configs)
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_work_dir_hidden_without_retain_flag() {
+        let state = failing_compile_state(false);
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        assert!(matches!(resp.status, Some(ExecutionStatus::CompileError)));
+        assert_eq!(resp.compile_exit_code, Some(1));
+        assert!(resp.work_dir.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_work_dir_exposed_with_retain_flag() {
+        let state = failing_compile_state(true);
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            ..request(Priority::Normal)
+        };
+
+        let resp = execute_request(&req, &state, None).await.unwrap();
+        let work_dir = resp.work_dir.expect("work dir should be exposed");
+        let work_dir = std::path::PathBuf::from(work_dir);
+        assert!(work_dir.join("main.sh").exists());
+        std::fs::remove_dir_all(work_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unwritable_work_root_reports_actionable_error() {
+        // A regular file can't hold directories, even for root
+        let scratch = tempfile::tempdir().unwrap();
+        let not_a_dir = scratch.path().join("file");
+        std::fs::write(&not_a_dir, "").unwrap();
+        let state = AppState {
+            work_root: Some(not_a_dir.clone()),
+            ..test_state(HashMap::from([("sh".to_string(), shell_config("cat"))]))
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            ..request(Priority::Normal)
+        };
+
+        let err = execute_request(&req, &state, None).await.unwrap_err();
+        assert!(err.message.starts_with("Cannot create a work directory in"));
+        assert!(err.message.contains(&not_a_dir.display().to_string()));
+        assert!(err.message.contains("not a directory"));
+        assert!(err.message.contains("BUILDIT_WORK_ROOT"));
+    }
+
+    #[test]
+    fn test_work_dir_error_names_the_cause() {
+        let root = std::path::Path::new("/tmp");
+        let full = std::io::Error::from(std::io::ErrorKind::StorageFull);
+        assert!(work_dir_error(root, &full).contains("the disk is full"));
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(work_dir_error(root, &denied).contains("permission denied"));
+    }
+
+    #[test]
+    fn test_resolved_commands_reflect_platform() {
+        let cfg = LanguageConfig {
+            compile_command: Some("cc".to_string()),
+            compile_args: vec!["main.c".to_string(), "-o".to_string(), "main".to_string()],
+            run_command: "./main".to_string(),
+            run_args: vec!["--fast".to_string()],
+            ..crate::language::generate_language_configs()["python3"].clone()
+        };
+        let work_dir = std::path::Path::new("work");
+        let commands = resolved_commands(&request(Priority::Normal), &cfg, work_dir);
+
+        let program = work_dir.join("./main").display().to_string();
+        let wrapper: Vec<String> = if cfg!(windows) {
+            vec!["cmd".to_string(), "/C".to_string()]
+        } else {
+            vec![]
+        };
+        let expected = |argv: &[&str]| -> Vec<String> {
+            wrapper.iter().cloned().chain(argv.iter().map(|a| a.to_string())).collect()
+        };
+        assert_eq!(commands.compile, Some(expected(&["cc", "main.c", "-o", "main"])));
+        assert_eq!(commands.run, expected(&[&program, "--fast"]));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_commands_reported_only_when_enabled() {
+        let state = test_state(HashMap::from([("sh".to_string(), shell_config("cat"))]));
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("x\n", Some("x\n"))],
+            ..request(Priority::Normal)
+        };
+        assert!(execute_request(&req, &state, None).await.unwrap().commands.is_none());
+
+        let state = AppState {
+            report_commands: true,
+            ..state
+        };
+        let commands = execute_request(&req, &state, None).await.unwrap().commands.unwrap();
+        assert_eq!(commands.compile, None);
+        assert_eq!(commands.run, vec!["sh", "-c", "cat"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_identical_submission_reuses_cached_build() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let counter = cache_dir.path().join("compiles.log");
+        let mut cfg = shell_config("cat built.txt");
+        cfg.compile_command = Some("sh".to_string());
+        cfg.compile_args = vec![
+            "-c".to_string(),
+            format!("echo x >> {}; echo built > built.txt", counter.display()),
+        ];
+        let mut configs = HashMap::new();
+        configs.insert("sh".to_string(), cfg);
+        let cache = CompileCache::new(cache_dir.path().join("cache"), u64::MAX).unwrap();
+        let state = AppState {
+            compile_cache: Some(Arc::new(cache)),
+            ..test_state(configs)
+        };
+        let req = ExecuteRequest {
+            language: "sh".to_string(),
+            testcases: vec![testcase("", Some("built\n"))],
+            ..request(Priority::Normal)
+        };
+
+        for _ in 0..2 {
+            let resp = execute_request(&req, &state, None).await.unwrap();
+            assert!(resp.compiled);
+            assert_eq!(resp.results[0].verdict, Verdict::Accepted);
+        }
+        assert_eq!(std::fs::read_to_string(&counter).unwrap().lines().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_selftest_reports_each_language() {
+        let canary = |code: Option<&str>| LanguageConfig {
+            canary: code.map(str::to_string),
+            ..shell_config("sh main.sh")
+        };
+        let state = AppState {
+            admin_token: Some("secret".to_string()),
+            ..test_state(HashMap::from([
+                ("good".to_string(), canary(Some("echo hello"))),
+                ("broken".to_string(), canary(Some("echo oops >&2; exit 3"))),
+                ("none".to_string(), canary(None)),
+            ]))
+        };
+
+        let resp = selftest_handler(State(state.clone()), header::HeaderMap::new()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = response_json(selftest_handler(State(state), admin_headers()).await).await;
+        assert_eq!(resp["all_ok"], false);
+        assert_eq!(resp["skipped"], serde_json::json!(["none"]));
+        let languages = resp["languages"].as_array().unwrap();
+        let names: Vec<&str> = languages.iter().map(|r| r["language"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["broken", "good"]);
+
+        let (broken, good) = (&languages[0], &languages[1]);
+        assert_eq!(good["ok"], true);
+        assert!(good["error"].is_null());
+        assert_eq!(broken["ok"], false);
+        assert!(broken["error"].as_str().unwrap().contains("oops"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_selftest_waits_for_a_run_slot() {
+        let cfg = LanguageConfig {
+            canary: Some("echo hello".to_string()),
+            ..shell_config("sh main.sh")
+        };
+        let state = AppState {
+            admin_token: Some("secret".to_string()),
+            run_slots: Arc::new(Semaphore::new(1)),
+            ..test_state(HashMap::from([("sh".to_string(), cfg)]))
+        };
+
+        // A submission holds the only slot, so the canary can't start
+        let busy = state.run_slots.clone().acquire_owned().await.unwrap();
+        let selftest = tokio::spawn(selftest_handler(State(state.clone()), admin_headers()));
+        time::sleep(time::Duration::from_millis(300)).await;
+        assert!(!selftest.is_finished());
+
+        drop(busy);
+        let resp = time::timeout(time::Duration::from_secs(5), selftest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response_json(resp).await["all_ok"], true);
+    }
+
+    #[tokio::test]
+    async fn test_builtin_canaries_pass_for_installed_toolchains() {
+        let builtin = crate::language::generate_language_configs();
+        let tools = [
+            ("python3", "python3"),
+            ("gcc", "gcc"),
+            ("gpp", "g++"),
+            ("rust", "rustc"),
+            ("javascript", "node"),
+            ("typescript", "tsc"),
+            ("ruby", "ruby"),
+            ("php", "php"),
+            ("swift", "swiftc"),
+            ("java", "javac"),
+        ];
+        let configs: HashMap<String, LanguageConfig> = tools
+            .iter()
+            .filter(|(_, tool)| which::which(tool).is_ok())
+            .map(|(language, _)| (language.to_string(), builtin[*language].clone()))
+            .collect();
+        if configs.is_empty() {
+            eprintln!("skipping canary test: no toolchains installed");
+            return;
+        }
+
+        let state = AppState {
+            admin_token: Some("secret".to_string()),
+            ..test_state(configs)
+        };
+        let resp = response_json(selftest_handler(State(state), admin_headers()).await).await;
+        for result in resp["languages"].as_array().unwrap() {
+            let (language, error) = (&result["language"], &result["error"]);
+            assert_eq!(result["ok"], true, "{} canary failed: {}", language, error);
+        }
+        assert_eq!(resp["all_ok"], true);
+        assert_eq!(resp["skipped"], serde_json::json!([]));
+    }
+}
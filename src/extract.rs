@@ -0,0 +1,347 @@
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_path_to_error::Path;
+
+/// A single problem with a submitted request, keyed by the offending field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// A rejected request, answered with `{ "error": <first message>, "errors": [...] }`.
+#[derive(Debug)]
+pub struct InvalidRequest {
+    status: StatusCode,
+    errors: Vec<FieldError>,
+}
+
+impl InvalidRequest {
+    pub fn new(errors: Vec<FieldError>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            errors,
+        }
+    }
+
+    fn with_status(status: StatusCode, error: FieldError) -> Self {
+        Self {
+            status,
+            errors: vec![error],
+        }
+    }
+
+    #[cfg(test)]
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+}
+
+impl IntoResponse for InvalidRequest {
+    fn into_response(self) -> Response {
+        let error = self
+            .errors
+            .first()
+            .map(|e| e.message.clone())
+            .unwrap_or_default();
+        (
+            self.status,
+            Json(serde_json::json!({
+                "error": error,
+                "errors": self.errors,
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Like axum's `Json`, but a body that doesn't deserialize is rejected with the path of
+/// the offending field (e.g. `testcases[0].id`) instead of a plain-text 422.
+pub struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = InvalidRequest;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json(req.headers()) {
+            return Err(InvalidRequest::with_status(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                FieldError::new("body", "Expected a `Content-Type: application/json` body"),
+            ));
+        }
+        let bytes = Bytes::from_request(req, state).await.map_err(|e| {
+            InvalidRequest::with_status(e.status(), FieldError::new("body", e.body_text()))
+        })?;
+
+        let mut de = serde_json::Deserializer::from_slice(&bytes);
+        let value = serde_path_to_error::deserialize(&mut de).map_err(|e| {
+            let inner = e.inner();
+            let error = match inner.classify() {
+                serde_json::error::Category::Data => field_error(e.path(), &json_message(inner)),
+                _ => FieldError::new("body", format!("Malformed JSON: {}", inner)),
+            };
+            InvalidRequest::new(vec![error])
+        })?;
+        de.end().map_err(|e| {
+            InvalidRequest::new(vec![FieldError::new(
+                "body",
+                format!("Malformed JSON: {}", e),
+            )])
+        })?;
+        Ok(JsonBody(value))
+    }
+}
+
+/// Like axum's `Query`, but names the parameter that failed to parse.
+pub struct QueryParams<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for QueryParams<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = InvalidRequest;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let de = serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+        serde_path_to_error::deserialize(de)
+            .map(QueryParams)
+            .map_err(|e| InvalidRequest::new(vec![field_error(e.path(), &e.inner().to_string())]))
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence == "application/json"
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+// serde_json appends the position to data errors; the field path says more
+fn json_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    message
+        .strip_suffix(&position)
+        .unwrap_or(&message)
+        .to_string()
+}
+
+// Turn a serde error at `path` into a field error phrased for API clients
+fn field_error(path: &Path, message: &str) -> FieldError {
+    let path = path.to_string();
+    let at = |name: &str| {
+        if path == "." {
+            name.to_string()
+        } else {
+            format!("{}.{}", path, name)
+        }
+    };
+
+    if let Some(name) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.strip_suffix('`'))
+    {
+        let field = at(name);
+        return FieldError::new(&field, format!("missing field `{}`", field));
+    }
+    let mismatch = message
+        .strip_prefix("invalid type: ")
+        .or_else(|| message.strip_prefix("invalid value: "))
+        .and_then(|rest| rest.split_once(", expected "));
+    if let Some((_, expected)) = mismatch {
+        return FieldError::new(
+            &path,
+            format!("`{}` must be {}", path, describe_expected(expected)),
+        );
+    }
+    FieldError::new(&path, format!("`{}`: {}", path, message))
+}
+
+// Plain-language name for what serde expected, e.g. `u32` -> "an integer"
+fn describe_expected(expected: &str) -> &str {
+    match expected {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" => "an integer",
+        "f32" | "f64" => "a number",
+        "a sequence" => "an array",
+        "a map" => "an object",
+        _ if expected.starts_with("struct ") => "an object",
+        _ => expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ExecuteRequest;
+    use axum::body::Body;
+    use serde::Deserialize;
+
+    async fn post(body: &str) -> Result<ExecuteRequest, Vec<FieldError>> {
+        let req = Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        JsonBody::<ExecuteRequest>::from_request(req, &())
+            .await
+            .map(|JsonBody(req)| req)
+            .map_err(|e| e.errors().to_vec())
+    }
+
+    fn only(errors: Vec<FieldError>) -> (String, String) {
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        let error = errors.into_iter().next().unwrap();
+        (error.field, error.message)
+    }
+
+    #[tokio::test]
+    async fn test_valid_body_is_accepted() {
+        let req = post(r#"{"language": "python", "code": "print(1)", "testcases": []}"#)
+            .await
+            .unwrap();
+        assert_eq!(req.language, "python");
+    }
+
+    #[tokio::test]
+    async fn test_missing_field_is_named() {
+        let errors = post(r#"{"code": "print(1)", "testcases": []}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            only(errors),
+            (
+                "language".to_string(),
+                "missing field `language`".to_string()
+            )
+        );
+
+        let errors = post(r#"{"language": "python", "code": "", "testcases": [{"input": ""}]}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            only(errors),
+            (
+                "testcases[0].id".to_string(),
+                "missing field `testcases[0].id`".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wrong_type_is_named() {
+        let body = r#"{"language": "python", "code": "", "testcases": [
+            {"id": 1, "input": "", "expected": ""},
+            {"id": "two", "input": "", "expected": ""}
+        ]}"#;
+        assert_eq!(
+            only(post(body).await.unwrap_err()),
+            (
+                "testcases[1].id".to_string(),
+                "`testcases[1].id` must be an integer".to_string()
+            )
+        );
+
+        let body = r#"{"language": "python", "code": "", "testcases": {}}"#;
+        assert_eq!(
+            only(post(body).await.unwrap_err()),
+            (
+                "testcases".to_string(),
+                "`testcases` must be an array".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_is_a_body_error() {
+        let (field, message) = only(post(r#"{"language": "python","#).await.unwrap_err());
+        assert_eq!(field, "body");
+        assert!(message.starts_with("Malformed JSON"), "{}", message);
+
+        let body = r#"{"language": "python", "code": "", "testcases": []} []"#;
+        let (field, message) = only(post(body).await.unwrap_err());
+        assert_eq!(field, "body");
+        assert!(message.contains("trailing characters"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_non_json_content_type_is_rejected() {
+        let req = Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from("{}"))
+            .unwrap();
+        let rejection = JsonBody::<ExecuteRequest>::from_request(req, &())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            rejection.into_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Params {
+        #[serde(default)]
+        verbose: bool,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+
+    async fn query(uri: &str) -> Result<Params, Vec<FieldError>> {
+        let (mut parts, _) = Request::builder().uri(uri).body(()).unwrap().into_parts();
+        QueryParams::<Params>::from_request_parts(&mut parts, &())
+            .await
+            .map(|QueryParams(params)| params)
+            .map_err(|e| e.errors().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_query_errors_name_the_parameter() {
+        let params = query("/status?verbose=true&limit=3").await.unwrap();
+        assert!(params.verbose);
+        assert_eq!(params.limit, Some(3));
+
+        let (field, message) = only(query("/status?limit=lots").await.unwrap_err());
+        assert_eq!(field, "limit");
+        assert!(message.starts_with("`limit`: "), "{}", message);
+
+        let (field, _) = only(query("/status?verbose=maybe").await.unwrap_err());
+        assert_eq!(field, "verbose");
+    }
+}
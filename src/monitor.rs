@@ -1,25 +1,40 @@
-use anyhow::Result;
+use crate::extract::{FieldError, InvalidRequest, JsonBody, QueryParams};
+use crate::net;
+use crate::types::current_platform;
+use crate::webhook::{WebhookAlerter, WebhookConfig};
+use anyhow::{Context, Result};
 use axum::{
-    extract::Query,
-    response::IntoResponse,
-    routing::{delete, get},
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, net::SocketAddr, process::Command, sync::Arc};
-use sysinfo::System;
+use std::time::{Duration, Instant};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Write,
+    future::Future,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+use sysinfo::{ProcessStatus, System};
 use tokio::sync::oneshot;
-use tower_http::cors;
 
-#[cfg(windows)]
-use std::sync::Mutex;
 #[cfg(windows)]
 use winapi::{
     shared::windef::HWND,
     um::winuser::{
-        EnumWindows, GetWindowLongW, GetWindowThreadProcessId, IsWindowVisible, GWL_EXSTYLE,
-        WS_EX_TOPMOST,
+        EnumWindows, GetWindowLongW, GetWindowTextLengthW, GetWindowTextW,
+        GetWindowThreadProcessId, IsWindowVisible, GWL_EXSTYLE, WS_EX_TOPMOST,
     },
 };
 
@@ -44,6 +59,16 @@ use objc::{class, msg_send, sel, sel_impl};
 pub struct StatusResponse {
     pub timestamp: String,
     pub forbidden_processes: Vec<String>,
+    /// Each forbidden process with its pid and executable path; left out with `names_only`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<Vec<ForbiddenMatch>>,
+    /// Whether `forbidden_processes` comes from a full scan; an empty list only means
+    /// "all clear" when this is `complete`
+    pub enumeration: EnumerationStatus,
+    /// Visible window titles matching a forbidden title pattern (Windows-only, requested
+    /// with `include_window_titles`)
+    #[serde(default)]
+    pub forbidden_windows: Vec<String>,
     pub platform: String,
     #[cfg(target_os = "macos")]
     pub is_siri_active: bool,
@@ -53,151 +78,476 @@ pub struct StatusResponse {
 pub struct StatusQuery {
     #[serde(default)]
     pub include_topmost: bool,
+    /// Also match visible window titles against the forbidden title patterns
+    #[serde(default)]
+    pub include_window_titles: bool,
+    /// Only report detections belonging to this category
+    #[serde(default)]
+    pub category: Option<ProcessCategory>,
+    /// Match process names only, ignoring path entries, and leave out `matches`
+    #[serde(default)]
+    pub names_only: bool,
+}
+
+/// A running process that matched the forbidden list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForbiddenMatch {
+    pub name: String,
+    pub pid: u32,
+    /// Full path of the executable, when the OS lets us read it
+    pub exe_path: Option<String>,
 }
 
-pub fn get_default_forbidden_list() -> Vec<String> {
+#[derive(Deserialize)]
+pub struct ProcessesQuery {
+    #[serde(default)]
+    pub include_topmost: bool,
+    /// Comma-separated forbidden entry names to terminate; all entries when omitted
+    #[serde(default)]
+    pub names: Option<String>,
+    /// Raise (or lower) the agent's cap on processes killed by this call
+    #[serde(default)]
+    pub max_terminations: Option<usize>,
+}
+
+/// Broad grouping for forbidden processes so dashboards can focus on one kind of tool.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessCategory {
+    Ide,
+    Editor,
+    Automation,
+    Recording,
+    Ai,
+    Vm,
+    Network,
+    Debugger,
+    SystemTool,
+    RemoteAccess,
+}
+
+/// A forbidden process name together with its category.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForbiddenEntry {
+    pub name: String,
+    pub category: ProcessCategory,
+}
+
+impl ForbiddenEntry {
+    pub fn new(name: impl Into<String>, category: ProcessCategory) -> Self {
+        Self {
+            name: name.into(),
+            category,
+        }
+    }
+
+    /// Entries containing a path separator (e.g. `/opt/cursor/`) are matched against the
+    /// executable path, singling out apps that run under a generic name like `electron`.
+    pub fn is_path_pattern(&self) -> bool {
+        self.name.contains(['/', '\\'])
+    }
+
+    /// Case-insensitive substring match against the process name, or against `exe_path`
+    /// for path entries. Without a path, path entries match nothing.
+    pub fn matches(&self, name: &str, exe_path: Option<&str>) -> bool {
+        let pattern = self.name.to_lowercase();
+        if self.is_path_pattern() {
+            exe_path.is_some_and(|path| path.to_lowercase().contains(&pattern))
+        } else {
+            name.to_lowercase().contains(&pattern)
+        }
+    }
+}
+
+fn entries(category: ProcessCategory, names: &[&str]) -> Vec<ForbiddenEntry> {
+    names
+        .iter()
+        .map(|name| ForbiddenEntry::new(*name, category))
+        .collect()
+}
+
+pub fn get_default_forbidden_list() -> Vec<ForbiddenEntry> {
+    use ProcessCategory::*;
+
     let mut forbidden = Vec::new();
 
     // Cross-platform applications
-    forbidden.extend([
-        "Code.exe".to_string(),
-        "code".to_string(), // VS Code
-        "devenv.exe".to_string(),
-        "devenv".to_string(), // Visual Studio
-        "idea64.exe".to_string(),
-        "idea".to_string(),
-        "IntelliJ IDEA".to_string(), // IntelliJ IDEA
-        "PyCharm".to_string(),
-        "pycharm".to_string(), // PyCharm
-        "eclipse".to_string(),
-        "Eclipse".to_string(), // Eclipse
-        "atom".to_string(),
-        "Atom".to_string(), // Atom
-        "sublime_text".to_string(),
-        "Sublime Text".to_string(), // Sublime Text
-        "notepad++.exe".to_string(),
-        "Notepad++".to_string(), // Notepad++
-        "vim".to_string(),
-        "nvim".to_string(),
-        "emacs".to_string(), // Terminal editors
-        "AutoHotkey.exe".to_string(),
-        "autohotkey".to_string(), // AutoHotkey
-        "obs64.exe".to_string(),
-        "obs".to_string(),
-        "OBS Studio".to_string(), // OBS Studio
-        "PowerToys.exe".to_string(),
-        "PowerToys".to_string(), // PowerToys
-        "ollama".to_string(),
-        "Ollama".to_string(), // Ollama
-        "docker".to_string(),
-        "Docker Desktop".to_string(), // Docker
-        "virtualbox".to_string(),
-        "VirtualBox".to_string(), // VirtualBox
-        "vmware".to_string(),
-        "VMware".to_string(), // VMware
-        "wireshark".to_string(),
-        "Wireshark".to_string(), // Wireshark
-        "fiddler".to_string(),
-        "Fiddler".to_string(), // Fiddler
-        "burp".to_string(),
-        "Burp Suite".to_string(), // Burp Suite
-        "ida".to_string(),
-        "IDA Pro".to_string(), // IDA Pro
-        "ghidra".to_string(),
-        "Ghidra".to_string(), // Ghidra
-        "x64dbg".to_string(),
-        "x32dbg".to_string(), // x64dbg/x32dbg
-        "ollydbg".to_string(),
-        "OllyDbg".to_string(), // OllyDbg
-        "cheat engine".to_string(),
-        "Cheat Engine".to_string(), // Cheat Engine
-        "process hacker".to_string(),
-        "Process Hacker".to_string(), // Process Hacker
-        "process monitor".to_string(),
-        "Process Monitor".to_string(), // Process Monitor
-        "autoruns".to_string(),
-        "Autoruns".to_string(), // Autoruns
-        "regshot".to_string(),
-        "Regshot".to_string(), // Regshot
-    ]);
+    forbidden.extend(entries(
+        Ide,
+        &[
+            "Code.exe",
+            "code", // VS Code
+            "devenv.exe",
+            "devenv", // Visual Studio
+            "idea64.exe",
+            "idea",
+            "IntelliJ IDEA", // IntelliJ IDEA
+            "PyCharm",
+            "pycharm", // PyCharm
+            "eclipse",
+            "Eclipse", // Eclipse
+        ],
+    ));
+    forbidden.extend(entries(
+        Editor,
+        &[
+            "atom",
+            "Atom", // Atom
+            "sublime_text",
+            "Sublime Text", // Sublime Text
+            "notepad++.exe",
+            "Notepad++", // Notepad++
+            "vim",
+            "nvim",
+            "emacs", // Terminal editors
+        ],
+    ));
+    forbidden.extend(entries(
+        Automation,
+        &[
+            "AutoHotkey.exe",
+            "autohotkey", // AutoHotkey
+            "PowerToys.exe",
+            "PowerToys", // PowerToys
+        ],
+    ));
+    forbidden.extend(entries(
+        Recording,
+        &[
+            "obs64.exe",
+            "obs",
+            "OBS Studio", // OBS Studio
+        ],
+    ));
+    forbidden.extend(entries(
+        Ai,
+        &[
+            "ollama", "Ollama", // Ollama
+        ],
+    ));
+    forbidden.extend(entries(
+        Vm,
+        &[
+            "docker",
+            "Docker Desktop", // Docker
+            "virtualbox",
+            "VirtualBox", // VirtualBox
+            "vmware",
+            "VMware", // VMware
+        ],
+    ));
+    forbidden.extend(entries(
+        Network,
+        &[
+            "wireshark",
+            "Wireshark", // Wireshark
+            "fiddler",
+            "Fiddler", // Fiddler
+            "burp",
+            "Burp Suite", // Burp Suite
+        ],
+    ));
+    forbidden.extend(entries(
+        Debugger,
+        &[
+            "ida",
+            "IDA Pro", // IDA Pro
+            "ghidra",
+            "Ghidra", // Ghidra
+            "x64dbg",
+            "x32dbg", // x64dbg/x32dbg
+            "ollydbg",
+            "OllyDbg", // OllyDbg
+            "cheat engine",
+            "Cheat Engine", // Cheat Engine
+        ],
+    ));
+    forbidden.extend(entries(
+        SystemTool,
+        &[
+            "process hacker",
+            "Process Hacker", // Process Hacker
+            "process monitor",
+            "Process Monitor", // Process Monitor
+            "autoruns",
+            "Autoruns", // Autoruns
+            "regshot",
+            "Regshot", // Regshot
+        ],
+    ));
 
     // Windows-specific
     if cfg!(windows) {
-        forbidden.extend([
-            "copilot.exe".to_string(), // Copilot
-            "Copilot".to_string(),
-            "mstsc.exe".to_string(),                      // Remote Desktop
-            "TeamViewer.exe".to_string(),                 // TeamViewer
-            "anydesk.exe".to_string(),                    // AnyDesk
-            "chrome_remote_desktop_host.exe".to_string(), // Chrome Remote Desktop
-            "LogMeIn.exe".to_string(),                    // LogMeIn
-            "ammyy.exe".to_string(),                      // Ammyy Admin
-            "radmin.exe".to_string(),                     // Radmin
-            "dwservice.exe".to_string(),                  // DWService
-            "supremo.exe".to_string(),                    // SupRemo
-            "ultraviewer.exe".to_string(),                // UltraViewer
-            "wsl.exe".to_string(),
-            "Windows Subsystem for Linux".to_string(), // WSL
-        ]);
+        forbidden.extend(entries(
+            Ai,
+            &[
+                "copilot.exe", // Copilot
+                "Copilot",
+            ],
+        ));
+        forbidden.extend(entries(
+            RemoteAccess,
+            &[
+                "mstsc.exe",                      // Remote Desktop
+                "TeamViewer.exe",                 // TeamViewer
+                "anydesk.exe",                    // AnyDesk
+                "chrome_remote_desktop_host.exe", // Chrome Remote Desktop
+                "LogMeIn.exe",                    // LogMeIn
+                "ammyy.exe",                      // Ammyy Admin
+                "radmin.exe",                     // Radmin
+                "dwservice.exe",                  // DWService
+                "supremo.exe",                    // SupRemo
+                "ultraviewer.exe",                // UltraViewer
+            ],
+        ));
+        forbidden.extend(entries(
+            Vm,
+            &[
+                "wsl.exe",
+                "Windows Subsystem for Linux", // WSL
+            ],
+        ));
     }
 
     // macOS-specific
     if cfg!(target_os = "macos") {
-        forbidden.extend([
-            "Screen Sharing".to_string(),
-            "Remote Desktop Scanner".to_string(),
-            "Apple Remote Desktop".to_string(),
-            "TeamViewer".to_string(),
-            "AnyDesk".to_string(),
-            "LogMeIn".to_string(),
-            "Splashtop Business".to_string(),
-            "Chrome Remote Desktop".to_string(),
-            "VNC Viewer".to_string(),
-            "Jump Desktop".to_string(),
-            "Microsoft Remote Desktop".to_string(),
-            "Parallels Desktop".to_string(),
-            "VMware Fusion".to_string(),
-            "UTM".to_string(),
-        ]);
+        forbidden.extend(entries(
+            RemoteAccess,
+            &[
+                "Screen Sharing",
+                "Remote Desktop Scanner",
+                "Apple Remote Desktop",
+                "TeamViewer",
+                "AnyDesk",
+                "LogMeIn",
+                "Splashtop Business",
+                "Chrome Remote Desktop",
+                "VNC Viewer",
+                "Jump Desktop",
+                "Microsoft Remote Desktop",
+            ],
+        ));
+        forbidden.extend(entries(Vm, &["Parallels Desktop", "VMware Fusion", "UTM"]));
     }
 
     // Linux-specific
     if cfg!(target_os = "linux") {
-        forbidden.extend([
-            "teamviewer".to_string(),
-            "anydesk".to_string(),
-            "remmina".to_string(),
-            "vinagre".to_string(),
-            "krdc".to_string(),
-            "xfreerdp".to_string(),
-            "rdesktop".to_string(),
-            "vnc".to_string(),
-            "x11vnc".to_string(),
-            "tightvnc".to_string(),
-            "tigervnc".to_string(),
-            "chrome-remote-desktop".to_string(),
-            "nomachine".to_string(),
-            "realvnc".to_string(),
-            "ultravnc".to_string(),
-            "qemu".to_string(),
-            "virtualbox".to_string(),
-            "vmware".to_string(),
-            "kvm".to_string(),
-            "gnome-boxes".to_string(),
-        ]);
+        forbidden.extend(entries(
+            RemoteAccess,
+            &[
+                "teamviewer",
+                "anydesk",
+                "remmina",
+                "vinagre",
+                "krdc",
+                "xfreerdp",
+                "rdesktop",
+                "vnc",
+                "x11vnc",
+                "tightvnc",
+                "tigervnc",
+                "chrome-remote-desktop",
+                "nomachine",
+                "realvnc",
+                "ultravnc",
+            ],
+        ));
+        forbidden.extend(entries(
+            Vm,
+            &["qemu", "virtualbox", "vmware", "kvm", "gnome-boxes"],
+        ));
     }
 
     forbidden
 }
 
+/// Bind address of the monitor (`BUILDIT_MONITOR_HOST`), loopback unless configured.
+/// Unlike the executor it may listen on the LAN so a proctoring server can poll it.
+pub fn monitor_bind_ip(host: Option<&str>) -> Result<IpAddr> {
+    match host {
+        Some(host) => host
+            .parse()
+            .with_context(|| format!("invalid BUILDIT_MONITOR_HOST {:?}", host)),
+        None => Ok(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    }
+}
+
+/// Forbidden list shared with the HTTP handlers; swapped wholesale on reload.
+pub type SharedForbiddenList = Arc<RwLock<Arc<Vec<ForbiddenEntry>>>>;
+
+/// Path of the optional forbidden-list file (`BUILDIT_FORBIDDEN_LIST_FILE`).
+pub fn forbidden_list_path() -> Option<PathBuf> {
+    std::env::var_os("BUILDIT_FORBIDDEN_LIST_FILE").map(PathBuf::from)
+}
+
+/// Load the forbidden list from a JSON array of `{ "name", "category" }` entries,
+/// or fall back to the built-in defaults when no file is configured.
+pub fn load_forbidden_list(path: Option<&Path>) -> Result<Vec<ForbiddenEntry>> {
+    match path {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+        }
+        None => Ok(get_default_forbidden_list()),
+    }
+}
+
+/// Re-read the forbidden list and swap it in. On error the current list is kept.
+pub fn reload_forbidden_list(shared: &SharedForbiddenList, path: Option<&Path>) -> Result<()> {
+    let new_list = load_forbidden_list(path)?;
+    replace_forbidden_list(shared, new_list);
+    Ok(())
+}
+
+/// Swap in `new_list`, logging which names were added and removed.
+pub fn replace_forbidden_list(shared: &SharedForbiddenList, new_list: Vec<ForbiddenEntry>) {
+    let old_list = shared.read().unwrap().clone();
+    let old_names: HashSet<&str> = old_list.iter().map(|e| e.name.as_str()).collect();
+    let new_names: HashSet<&str> = new_list.iter().map(|e| e.name.as_str()).collect();
+    let mut added: Vec<&str> = new_names.difference(&old_names).copied().collect();
+    let mut removed: Vec<&str> = old_names.difference(&new_names).copied().collect();
+    added.sort();
+    removed.sort();
+    println!(
+        "Updated forbidden list ({} entries): added [{}], removed [{}]",
+        new_list.len(),
+        added.join(", "),
+        removed.join(", ")
+    );
+
+    *shared.write().unwrap() = Arc::new(new_list);
+}
+
+/// Reload the forbidden list whenever the process receives SIGHUP.
+/// The handler is registered before returning so no signal is missed.
+#[cfg(unix)]
+pub fn reload_on_sighup(shared: SharedForbiddenList, path: Option<PathBuf>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let hangup = signal(SignalKind::hangup())?;
+    let hangups = futures::stream::unfold(hangup, |mut hangup| async move {
+        hangup.recv().await.map(|()| ((), hangup))
+    });
+    reload_on(shared, path, hangups);
+    Ok(())
+}
+
+/// Reload the forbidden list each time `requests` yields, until it ends.
+#[cfg(unix)]
+fn reload_on(
+    shared: SharedForbiddenList,
+    path: Option<PathBuf>,
+    requests: impl futures::Stream<Item = ()> + Send + 'static,
+) {
+    use futures::StreamExt;
+
+    tokio::spawn(async move {
+        let mut requests = std::pin::pin!(requests);
+        while requests.next().await.is_some() {
+            if let Err(e) = reload_forbidden_list(&shared, path.as_deref()) {
+                eprintln!("Failed to reload forbidden list: {:#}", e);
+            }
+        }
+    });
+}
+
+/// Window title patterns (case-insensitive substrings) that reveal browser-based or
+/// overlay cheating tools running under generic process names.
+pub fn get_default_forbidden_titles() -> Vec<String> {
+    [
+        "ChatGPT",
+        "Claude",
+        "Gemini",
+        "Copilot",
+        "Perplexity",
+        "Stack Overflow",
+        "GeeksforGeeks",
+        "Chegg",
+        "Course Hero",
+        "Remote Desktop Connection",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// Forbidden window title patterns: the comma-separated `BUILDIT_FORBIDDEN_TITLES` when
+/// set, otherwise the built-in defaults.
+pub fn forbidden_titles(value: Option<&str>) -> Vec<String> {
+    match value {
+        Some(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(String::from)
+            .collect(),
+        None => get_default_forbidden_titles(),
+    }
+}
+
+/// Process names exempt from forbidden detection, shared with the HTTP handlers.
+pub type SharedWhitelist = Arc<RwLock<Arc<Vec<String>>>>;
+
+/// Whitelisted process names from the comma-separated `BUILDIT_PROCESS_WHITELIST`.
+/// Unlike forbidden entries these match the whole name, so `code` can be banned without
+/// also flagging e.g. `qtwebengine_code_cache`.
+pub fn process_whitelist(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Select the forbidden entries named in `names` (case-insensitive). Names that aren't on
+/// the forbidden list are returned as the error so the API can't kill arbitrary processes.
+pub fn select_forbidden_entries(
+    forbidden_list: &[ForbiddenEntry],
+    names: &[String],
+) -> std::result::Result<Vec<ForbiddenEntry>, Vec<String>> {
+    let unknown: Vec<String> = names
+        .iter()
+        .filter(|n| {
+            !forbidden_list
+                .iter()
+                .any(|e| e.name.eq_ignore_ascii_case(n))
+        })
+        .cloned()
+        .collect();
+    if !unknown.is_empty() {
+        return Err(unknown);
+    }
+
+    Ok(forbidden_list
+        .iter()
+        .filter(|e| names.iter().any(|n| e.name.eq_ignore_ascii_case(n)))
+        .cloned()
+        .collect())
+}
+
+/// Restrict a forbidden list to a single category; `None` keeps every entry.
+pub fn filter_by_category(
+    forbidden_list: &[ForbiddenEntry],
+    category: Option<ProcessCategory>,
+) -> Vec<ForbiddenEntry> {
+    forbidden_list
+        .iter()
+        .filter(|entry| category.is_none_or(|c| entry.category == c))
+        .cloned()
+        .collect()
+}
+
 #[cfg(windows)]
-fn enumerate_topmost_processes() -> Vec<String> {
-    let process_names = Mutex::new(Vec::<String>::new());
+fn enumerate_topmost_processes() -> Vec<(u32, String)> {
+    let process_names = Mutex::new(Vec::<(u32, String)>::new());
 
     extern "system" fn enum_callback(hwnd: HWND, lparam: isize) -> i32 {
         unsafe {
-            let ptr = lparam as *const Mutex<Vec<String>>;
-            let mutex: &Mutex<Vec<String>> = &*ptr;
+            let ptr = lparam as *const Mutex<Vec<(u32, String)>>;
+            let mutex: &Mutex<Vec<(u32, String)>> = &*ptr;
 
             if IsWindowVisible(hwnd) == 0 {
                 return 1; // Continue enumeration
@@ -214,7 +564,7 @@ fn enumerate_topmost_processes() -> Vec<String> {
 
                 if let Some(process) = sys.process(sysinfo::Pid::from_u32(pid)) {
                     let mut names = mutex.lock().unwrap();
-                    names.push(process.name().to_string());
+                    names.push((pid, process.name().to_string()));
                 }
             }
             1 // Continue enumeration
@@ -358,153 +708,733 @@ fn siri_overlay_active() -> bool {
     is_siri_frontmost() || is_siri_visible()
 }
 
+#[cfg(target_os = "linux")]
+fn enumerate_topmost_processes() -> Vec<(u32, String)> {
+    // Headless sessions and Wayland without XWayland have no X display to ask
+    let pids = match x11_topmost_pids(None) {
+        Ok(pids) => pids,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut sys = System::new();
+    sys.refresh_processes();
+    pids.into_iter()
+        .filter_map(|pid| {
+            let process = sys.process(sysinfo::Pid::from_u32(pid))?;
+            Some((pid, process.name().to_string()))
+        })
+        .collect()
+}
+
+// Pids of the managed X11 windows flagged `_NET_WM_STATE_ABOVE` (always on top). Relies on
+// the window manager's EWMH client list, so windows it doesn't manage aren't seen.
+#[cfg(target_os = "linux")]
+fn x11_topmost_pids(display: Option<&str>) -> Result<Vec<u32>> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+    let (conn, screen) = x11rb::connect(display)?;
+    let root = conn.setup().roots[screen].root;
+    let atom = |name: &[u8]| -> Result<u32> { Ok(conn.intern_atom(false, name)?.reply()?.atom) };
+    let client_list = atom(b"_NET_CLIENT_LIST")?;
+    let wm_state = atom(b"_NET_WM_STATE")?;
+    let above = atom(b"_NET_WM_STATE_ABOVE")?;
+    let wm_pid = atom(b"_NET_WM_PID")?;
+
+    let windows: Vec<u32> = conn
+        .get_property(false, root, client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+        .reply()?
+        .value32()
+        .map(Iterator::collect)
+        .unwrap_or_default();
+
+    let mut pids = Vec::new();
+    for window in windows {
+        // A window closed since the client list was read answers with an error; skip it
+        let Ok(state) = conn
+            .get_property(false, window, wm_state, AtomEnum::ATOM, 0, 1024)?
+            .reply()
+        else {
+            continue;
+        };
+        if !state
+            .value32()
+            .is_some_and(|mut atoms| atoms.any(|a| a == above))
+        {
+            continue;
+        }
+        let Ok(pid) = conn
+            .get_property(false, window, wm_pid, AtomEnum::CARDINAL, 0, 1)?
+            .reply()
+        else {
+            continue;
+        };
+        if let Some(pid) = pid.value32().and_then(|mut values| values.next()) {
+            pids.push(pid);
+        }
+    }
+    Ok(pids)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn enumerate_topmost_processes() -> Vec<(u32, String)> {
+    // On other platforms, we can't easily detect topmost windows
+    Vec::new()
+}
+
+#[cfg(windows)]
+fn enumerate_window_titles() -> Vec<String> {
+    let titles = Mutex::new(Vec::<String>::new());
+
+    extern "system" fn enum_callback(hwnd: HWND, lparam: isize) -> i32 {
+        unsafe {
+            let ptr = lparam as *const Mutex<Vec<String>>;
+            let mutex: &Mutex<Vec<String>> = &*ptr;
+
+            if IsWindowVisible(hwnd) == 0 {
+                return 1; // Continue enumeration
+            }
+
+            let len = GetWindowTextLengthW(hwnd);
+            if len <= 0 {
+                return 1;
+            }
+            let mut buf = vec![0u16; len as usize + 1];
+            let copied = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+            if copied > 0 {
+                let title = String::from_utf16_lossy(&buf[..copied as usize]);
+                mutex.lock().unwrap().push(title);
+            }
+            1 // Continue enumeration
+        }
+    }
+
+    let ptr = &titles as *const _ as isize;
+    unsafe {
+        EnumWindows(Some(enum_callback), ptr);
+    }
+
+    titles.into_inner().unwrap()
+}
+
 #[cfg(not(windows))]
-fn enumerate_topmost_processes() -> Vec<String> {
-    // On non-Windows platforms, we can't easily detect topmost windows
+fn enumerate_window_titles() -> Vec<String> {
+    // Window titles are only enumerated on Windows
     Vec::new()
 }
 
-pub fn detect_forbidden_processes(forbidden_list: &[String], include_topmost: bool) -> Vec<String> {
+/// Detect visible windows whose title matches a forbidden pattern.
+pub fn detect_forbidden_windows(patterns: &[String]) -> Vec<String> {
+    match_window_titles(patterns, &enumerate_window_titles())
+}
+
+/// Return the sorted, de-duplicated window titles containing any of `patterns`
+/// (case-insensitive).
+pub fn match_window_titles(patterns: &[String], titles: &[String]) -> Vec<String> {
+    let patterns: Vec<String> = patterns.iter().map(|p| p.to_lowercase()).collect();
+    let mut detected: Vec<String> = titles
+        .iter()
+        .filter(|title| {
+            let title = title.to_lowercase();
+            patterns.iter().any(|p| title.contains(p.as_str()))
+        })
+        .cloned()
+        .collect();
+    detected.sort();
+    detected.dedup();
+    detected
+}
+
+/// How completely the process table could be read during a scan.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnumerationStatus {
+    /// The process table was read normally
+    Complete,
+    /// Some processes were listed, but the listing looks partial
+    Degraded,
+    /// No processes could be listed at all
+    Unavailable,
+}
+
+/// Judge whether a process listing can be trusted. The agent itself is always running,
+/// so a listing that misses it, or whose names are mostly unreadable, is partial.
+pub fn assess_enumeration(process_names: &[String], saw_self: bool) -> EnumerationStatus {
+    let unreadable = process_names.iter().filter(|name| name.is_empty()).count();
+    if process_names.is_empty() {
+        EnumerationStatus::Unavailable
+    } else if !saw_self || unreadable * 2 > process_names.len() {
+        EnumerationStatus::Degraded
+    } else {
+        EnumerationStatus::Complete
+    }
+}
+
+// Refresh the process table and assess how much of it was readable
+fn scan_process_table() -> (System, EnumerationStatus) {
     let mut sys = System::new_all();
     sys.refresh_processes();
 
-    let mut detected = HashSet::new();
+    let names: Vec<String> = sys
+        .processes()
+        .values()
+        .map(|p| p.name().to_string())
+        .collect();
+    let saw_self = sysinfo::get_current_pid().is_ok_and(|pid| sys.process(pid).is_some());
+    let enumeration = assess_enumeration(&names, saw_self);
+    if enumeration != EnumerationStatus::Complete {
+        eprintln!(
+            "⚠️ Process enumeration is {:?} ({} processes listed); results may be incomplete",
+            enumeration,
+            names.len()
+        );
+    }
+    (sys, enumeration)
+}
+
+/// One pass over the process table. Detection and termination both work from a snapshot,
+/// so what is killed is exactly what was detected, with no gap for processes to come and go.
+#[derive(Debug, Clone)]
+pub struct ProcessSnapshot {
+    /// Pid and name of every listed process, in pid order
+    processes: Vec<(u32, String)>,
+    /// Executable paths by pid, for matching path entries
+    exe_paths: HashMap<u32, String>,
+    /// Pid and name of processes owning topmost windows (when requested)
+    topmost: Vec<(u32, String)>,
+    pub enumeration: EnumerationStatus,
+}
+
+impl ProcessSnapshot {
+    /// Scan the process table, and the topmost windows too if `include_topmost` is set.
+    pub fn take(include_topmost: bool) -> Self {
+        let (sys, enumeration) = scan_process_table();
+        let processes = sys
+            .processes()
+            .values()
+            .map(|p| (p.pid().as_u32(), p.name().to_string()))
+            .collect();
+        let topmost = if include_topmost {
+            enumerate_topmost_processes()
+        } else {
+            Vec::new()
+        };
+        let mut snapshot = Self::new(processes, topmost, enumeration);
+        snapshot.exe_paths = sys
+            .processes()
+            .values()
+            .filter_map(|p| Some((p.pid().as_u32(), p.exe()?.to_string_lossy().into_owned())))
+            .collect();
+        snapshot
+    }
 
-    // Get all running process names
-    let mut all_processes = Vec::new();
-    for (_pid, process) in sys.processes() {
-        all_processes.push(process.name().to_string());
+    pub fn new(
+        mut processes: Vec<(u32, String)>,
+        topmost: Vec<(u32, String)>,
+        enumeration: EnumerationStatus,
+    ) -> Self {
+        // Visit processes in pid order so which ones fall over a kill cap is predictable
+        processes.sort();
+        Self {
+            processes,
+            exe_paths: HashMap::new(),
+            topmost,
+            enumeration,
+        }
     }
 
-    // Add topmost processes on Windows if requested
-    if include_topmost {
-        all_processes.extend(enumerate_topmost_processes());
+    /// Forget the executable paths, so only process names are matched as before path
+    /// entries existed.
+    pub fn names_only(mut self) -> Self {
+        self.exe_paths.clear();
+        self
     }
 
-    // Check for forbidden processes (case-insensitive substring match)
-    for forbidden in forbidden_list {
-        let forbidden_lower = forbidden.to_lowercase();
-        for process_name in &all_processes {
-            let process_lower = process_name.to_lowercase();
-            if process_lower.contains(&forbidden_lower) {
-                detected.insert(process_name.clone());
+    fn is_forbidden(&self, forbidden_list: &[ForbiddenEntry], pid: u32, name: &str) -> bool {
+        let exe_path = self.exe_paths.get(&pid).map(String::as_str);
+        forbidden_list.iter().any(|f| f.matches(name, exe_path))
+    }
+
+    /// Leave out processes whose name equals a whitelist entry (case-insensitive), so they
+    /// are neither detected nor terminated.
+    pub fn exempt(mut self, whitelist: &[String]) -> Self {
+        let exempt = |name: &String| whitelist.iter().any(|w| w.eq_ignore_ascii_case(name));
+        self.processes.retain(|(_, name)| !exempt(name));
+        self.topmost.retain(|(_, name)| !exempt(name));
+        self
+    }
+
+    /// Sorted, de-duplicated names of the forbidden processes in this snapshot.
+    pub fn detect(&self, forbidden_list: &[ForbiddenEntry]) -> Vec<String> {
+        let mut detected: HashSet<String> = self
+            .matches(forbidden_list)
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        detected.extend(
+            self.forbidden_topmost(forbidden_list)
+                .map(|(_, name)| name.clone()),
+        );
+        let mut detected: Vec<String> = detected.into_iter().collect();
+        detected.sort();
+        detected
+    }
+
+    /// The forbidden processes in this snapshot, in pid order. Topmost window owners are
+    /// only reported by `detect`.
+    pub fn matches(&self, forbidden_list: &[ForbiddenEntry]) -> Vec<ForbiddenMatch> {
+        self.processes
+            .iter()
+            .filter(|(pid, name)| self.is_forbidden(forbidden_list, *pid, name))
+            .map(|(pid, name)| ForbiddenMatch {
+                name: name.clone(),
+                pid: *pid,
+                exe_path: self.exe_paths.get(pid).cloned(),
+            })
+            .collect()
+    }
+
+    // Topmost window owners that are on the forbidden list
+    fn forbidden_topmost<'a>(
+        &'a self,
+        forbidden_list: &'a [ForbiddenEntry],
+    ) -> impl Iterator<Item = &'a (u32, String)> {
+        self.topmost
+            .iter()
+            .filter(|(pid, name)| self.is_forbidden(forbidden_list, *pid, name))
+    }
+
+    /// Terminate the forbidden processes in this snapshot, killing at most `max_terminations`
+    /// of them so an overly broad forbidden list can't take down the whole session. `kill`
+    /// is called with each pid and reports whether the kill succeeded.
+    pub fn terminate(
+        &self,
+        forbidden_list: &[ForbiddenEntry],
+        max_terminations: usize,
+        mut kill: impl FnMut(u32) -> bool,
+    ) -> TerminationOutcome {
+        let mut failed = HashSet::new();
+        let mut over_limit = HashSet::new();
+        let mut terminations = 0;
+        let mut killed = 0;
+
+        // Attempt to kill by pid unless the cap has been reached
+        let mut try_kill = |pid: u32, pname: &str| {
+            if terminations >= max_terminations {
+                over_limit.insert(pname.to_string());
+                return;
+            }
+            terminations += 1;
+            if kill(pid) {
+                killed += 1;
+            } else {
+                failed.insert(pname.to_string());
             }
+        };
+
+        // Match running processes by forbidden list (case-insensitive substring)
+        let mut attempted = HashSet::new();
+        for (pid, pname) in &self.processes {
+            if self.is_forbidden(forbidden_list, *pid, pname) {
+                attempted.insert(*pid);
+                try_kill(*pid, pname);
+            }
+        }
+
+        // Then the forbidden topmost window owners the table pass didn't already cover
+        for (pid, pname) in self.forbidden_topmost(forbidden_list) {
+            if attempted.insert(*pid) {
+                try_kill(*pid, pname);
+            }
+        }
+
+        let mut failed: Vec<String> = failed.into_iter().collect();
+        failed.sort();
+        let mut over_limit: Vec<String> = over_limit.into_iter().collect();
+        over_limit.sort();
+        TerminationOutcome {
+            failed,
+            over_limit,
+            killed,
+            kill_failures: terminations - killed,
+            enumeration: self.enumeration,
         }
     }
+}
+
+// Forcefully kill one process, returning whether the kill command succeeded
+fn kill_process(pid: u32) -> bool {
+    let pid_str = pid.to_string();
+    #[cfg(windows)]
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid_str, "/F"])
+        .status();
+
+    #[cfg(not(windows))]
+    let status = Command::new("kill").args(["-9", &pid_str]).status();
 
-    let mut result: Vec<String> = detected.into_iter().collect();
-    result.sort();
-    result
+    matches!(status, Ok(s) if s.success())
 }
 
-/// Attempt to terminate forbidden processes. Returns a sorted list of process names
-/// that couldn't be terminated automatically.
-pub fn terminate_forbidden_processes(
-    forbidden_list: &[String],
-    #[cfg(windows)] include_topmost: bool,
-) -> Vec<String> {
-    let mut sys = System::new_all();
-    sys.refresh_processes();
+/// Default cap on processes killed by one `DELETE /processes` call
+pub const DEFAULT_MAX_TERMINATIONS: usize = 20;
+
+/// What a termination pass did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminationOutcome {
+    /// Sorted names of processes whose kill command failed
+    pub failed: Vec<String>,
+    /// Sorted names of matching processes left alone because the cap was reached
+    pub over_limit: Vec<String>,
+    /// Kill commands that succeeded
+    pub killed: usize,
+    /// Kill commands that failed (`failed` lists each name only once)
+    pub kill_failures: usize,
+    pub enumeration: EnumerationStatus,
+}
+
+/// Forbidden processes that are actually running: unlike `ProcessSnapshot::detect`,
+/// killed processes still awaiting reaping (zombies) are not counted.
+pub fn running_forbidden_processes(forbidden_list: &[ForbiddenEntry]) -> Vec<String> {
+    let (sys, _) = scan_process_table();
+    let mut running: Vec<String> = sys
+        .processes()
+        .values()
+        .filter(|p| p.status() != ProcessStatus::Zombie)
+        .filter(|p| {
+            let exe_path = p.exe().map(|path| path.to_string_lossy());
+            forbidden_list
+                .iter()
+                .any(|f| f.matches(p.name(), exe_path.as_deref()))
+        })
+        .map(|p| p.name().to_string())
+        .collect();
+    running.sort();
+    running.dedup();
+    running
+}
+
+/// Split the processes detected before termination into those missing from a later scan
+/// and those still running, e.g. because they respawned after a successful kill.
+pub fn verify_termination(before: &[String], after: &[String]) -> (Vec<String>, Vec<String>) {
+    before
+        .iter()
+        .cloned()
+        .partition(|name| !after.contains(name))
+}
+
+const TERMINATION_CHECKS: u32 = 5;
+const TERMINATION_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// Re-scan until every process detected before termination is gone, giving killed processes
+// a moment to exit. Returns the (terminated, still running) names of the final scan.
+async fn confirm_termination(
+    forbidden_list: &[ForbiddenEntry],
+    before: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let mut outcome = verify_termination(before, &running_forbidden_processes(forbidden_list));
+    for _ in 1..TERMINATION_CHECKS {
+        if outcome.1.is_empty() {
+            break;
+        }
+        tokio::time::sleep(TERMINATION_CHECK_INTERVAL).await;
+        outcome = verify_termination(before, &running_forbidden_processes(forbidden_list));
+    }
+    outcome
+}
 
-    let mut attempted = HashSet::new();
-    let mut failed = HashSet::new();
+/// Default period of the background scan (`BUILDIT_MONITOR_SCAN_INTERVAL_SECS`, 0 disables it)
+pub const DEFAULT_SCAN_INTERVAL: Duration = Duration::from_secs(5);
+/// Events kept by the event log; older ones are dropped
+pub const EVENT_LOG_CAPACITY: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectionEventKind {
+    /// The process started matching the forbidden list
+    Detected,
+    /// A previously detected process is no longer seen
+    Cleared,
+}
 
-    // Helper to attempt killing by pid
-    let try_kill = |pid: u32| -> bool {
-        let pid_str = pid.to_string();
-        #[cfg(windows)]
-        let status = Command::new("taskkill")
-            .args(["/PID", &pid_str, "/F"])
-            .status();
+/// A change seen by the background scan, as listed by `GET /events`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DetectionEvent {
+    pub timestamp: String,
+    pub process: String,
+    pub event: DetectionEventKind,
+    pub platform: String,
+}
+
+/// Bounded history of detection events, oldest first.
+#[derive(Debug)]
+pub struct EventLog {
+    events: VecDeque<DetectionEvent>,
+    capacity: usize,
+}
 
-        #[cfg(not(windows))]
-        let status = Command::new("kill").args(["-9", &pid_str]).status();
+/// Event log shared between the background scan and the HTTP handlers.
+pub type SharedEventLog = Arc<Mutex<EventLog>>;
 
-        match status {
-            Ok(s) if s.success() => true,
-            _ => false,
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
         }
-    };
+    }
 
-    // Match running processes by forbidden list (case-insensitive substring)
-    for (_pid, process) in sys.processes() {
-        let pname = process.name().to_string();
-        let pname_lower = pname.to_lowercase();
-
-        for forbidden in forbidden_list {
-            let forbidden_lower = forbidden.to_lowercase();
-            if pname_lower.contains(&forbidden_lower) {
-                // Attempt to kill
-                let pid_u32 = process.pid().as_u32();
-                attempted.insert(pname.clone());
-                let ok = try_kill(pid_u32);
-                if !ok {
-                    failed.insert(pname.clone());
-                }
-                break; // avoid duplicate attempts for same process name
+    /// Record the processes that appeared in or disappeared from `current` since the
+    /// `previous` scan. Both lists are sorted, as returned by `ProcessSnapshot::detect`.
+    pub fn record_changes(&mut self, previous: &[String], current: &[String], timestamp: &str) {
+        let changes = current
+            .iter()
+            .filter(|name| previous.binary_search(name).is_err())
+            .map(|name| (name, DetectionEventKind::Detected))
+            .chain(
+                previous
+                    .iter()
+                    .filter(|name| current.binary_search(name).is_err())
+                    .map(|name| (name, DetectionEventKind::Cleared)),
+            );
+        for (process, event) in changes {
+            if self.events.len() >= self.capacity {
+                self.events.pop_front();
             }
+            self.events.push_back(DetectionEvent {
+                timestamp: timestamp.to_string(),
+                process: process.clone(),
+                event,
+                platform: current_platform().to_string(),
+            });
         }
     }
 
-    // If requested, also consider topmost window process names (Windows-only detection returns names)
-    #[cfg(windows)]
-    if include_topmost {
-        let top_names = enumerate_topmost_processes();
-        for tname in top_names {
-            let tname_lower = tname.to_lowercase();
-            // try to find matching processes by name and kill them
-            for (_pid, process) in sys.processes() {
-                let pname = process.name().to_string();
-                let pname_lower = pname.to_lowercase();
-                if pname_lower.contains(&tname_lower) {
-                    attempted.insert(pname.clone());
-                    let pid_u32 = process.pid().as_u32();
-                    let ok = try_kill(pid_u32);
-                    if !ok {
-                        failed.insert(pname.clone());
-                    }
-                }
-            }
+    pub fn events(&self) -> Vec<DetectionEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
+
+/// Scan for forbidden processes every `interval`, logging what starts and stops matching
+/// so proctors get a timeline even when nobody polls `/status`. With an `alerter`, each
+/// scan also feeds the detection webhook, forbidden window titles included.
+pub async fn scan_periodically(
+    forbidden_list: SharedForbiddenList,
+    whitelist: SharedWhitelist,
+    events: SharedEventLog,
+    forbidden_titles: Arc<Vec<String>>,
+    alerter: Option<WebhookAlerter>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut previous = Vec::new();
+    loop {
+        ticker.tick().await;
+        let forbidden_list = forbidden_list.read().unwrap().clone();
+        let whitelist = whitelist.read().unwrap().clone();
+        let snapshot = ProcessSnapshot::take(false).exempt(&whitelist);
+        let detected = snapshot.detect(&forbidden_list);
+        let timestamp = Utc::now().to_rfc3339();
+        events
+            .lock()
+            .unwrap()
+            .record_changes(&previous, &detected, &timestamp);
+
+        // Alerts follow this full scan, never a filtered /status view, so the debounce
+        // state isn't flipped by whatever a caller happened to ask for
+        if let Some(alerter) = &alerter {
+            let alert = StatusResponse {
+                timestamp,
+                forbidden_processes: detected.clone(),
+                matches: Some(snapshot.matches(&forbidden_list)),
+                enumeration: snapshot.enumeration,
+                forbidden_windows: detect_forbidden_windows(&forbidden_titles),
+                platform: current_platform().to_string(),
+                #[cfg(target_os = "macos")]
+                is_siri_active: siri_overlay_active(),
+            };
+            let mut alerted = alert.forbidden_processes.clone();
+            alerted.extend(alert.forbidden_windows.iter().cloned());
+            alerter.send_detection(&alerted, &alert);
         }
+        previous = detected;
+    }
+}
+
+/// Activity counters of the monitor, exported in Prometheus text format at `GET /metrics`.
+#[derive(Debug, Default)]
+pub struct MonitorMetrics {
+    scans: AtomicU64,
+    scan_micros: AtomicU64,
+    forbidden_processes: AtomicU64,
+    terminations: AtomicU64,
+    termination_failures: AtomicU64,
+}
+
+impl MonitorMetrics {
+    /// Record one detection scan and how many forbidden processes it found.
+    pub fn record_scan(&self, duration: Duration, detected: usize) {
+        self.scans.fetch_add(1, Ordering::Relaxed);
+        self.scan_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.forbidden_processes
+            .store(detected as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_terminations(&self, outcome: &TerminationOutcome) {
+        self.terminations
+            .fetch_add(outcome.killed as u64, Ordering::Relaxed);
+        self.termination_failures
+            .fetch_add(outcome.kill_failures as u64, Ordering::Relaxed);
     }
 
-    let mut result: Vec<String> = failed.into_iter().collect();
-    result.sort();
-    result
+    /// The metrics in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+        metric(
+            "buildit_monitor_scans_total",
+            "counter",
+            "Process scans performed for detection.",
+            load(&self.scans).to_string(),
+        );
+        metric(
+            "buildit_monitor_scan_duration_seconds_total",
+            "counter",
+            "Time spent in detection scans.",
+            (load(&self.scan_micros) as f64 / 1e6).to_string(),
+        );
+        metric(
+            "buildit_monitor_forbidden_processes",
+            "gauge",
+            "Forbidden processes found by the most recent scan.",
+            load(&self.forbidden_processes).to_string(),
+        );
+        metric(
+            "buildit_monitor_terminations_total",
+            "counter",
+            "Forbidden processes killed.",
+            load(&self.terminations).to_string(),
+        );
+        metric(
+            "buildit_monitor_termination_failures_total",
+            "counter",
+            "Kill attempts that failed.",
+            load(&self.termination_failures).to_string(),
+        );
+        out
+    }
 }
 
-pub fn build_app(forbidden_list: Arc<Vec<String>>) -> Router {
+pub fn build_app(
+    forbidden_list: SharedForbiddenList,
+    whitelist: SharedWhitelist,
+    events: SharedEventLog,
+    forbidden_titles: Arc<Vec<String>>,
+    max_terminations: usize,
+    auth_token: Option<String>,
+) -> Router {
+    let metrics = Arc::new(MonitorMetrics::default());
+    let auth = middleware::from_fn_with_state(auth_token.map(Arc::<str>::from), require_token);
     Router::new()
         .route(
             "/status",
             get({
                 let forbidden = forbidden_list.clone();
-                move |query| status_handler(query, forbidden)
+                let whitelist = whitelist.clone();
+                let metrics = metrics.clone();
+                move |query| status_handler(query, forbidden, whitelist, forbidden_titles, metrics)
             }),
         )
         .route(
             "/processes",
             delete({
                 let forbidden = forbidden_list.clone();
-                move |query| processes_handler(query, forbidden)
-            }),
+                let whitelist = whitelist.clone();
+                let metrics = metrics.clone();
+                move |query| {
+                    processes_handler(query, forbidden, whitelist, max_terminations, metrics)
+                }
+            })
+            .layer(auth.clone()),
         )
-        .route("/version", get(version_handler))
-        .layer(
-            cors::CorsLayer::new()
-                .allow_origin(cors::Any)
-                .allow_methods(cors::Any)
-                .allow_headers(cors::Any),
+        .route(
+            "/enforce",
+            post({
+                let forbidden = forbidden_list.clone();
+                let whitelist = whitelist.clone();
+                let metrics = metrics.clone();
+                move |query| enforce_handler(query, forbidden, whitelist, max_terminations, metrics)
+            })
+            .layer(auth.clone()),
+        )
+        .route(
+            "/forbidden",
+            get({
+                let forbidden = forbidden_list.clone();
+                move || forbidden_list_handler(forbidden)
+            })
+            .merge(
+                put({
+                    let forbidden = forbidden_list.clone();
+                    move |body| replace_forbidden_list_handler(body, forbidden)
+                })
+                .layer(auth.clone()),
+            ),
+        )
+        .route(
+            "/forbidden/reload",
+            post({
+                let forbidden = forbidden_list.clone();
+                let path = forbidden_list_path();
+                move || reload_forbidden_list_handler(forbidden, path.clone())
+            })
+            .layer(auth.clone()),
+        )
+        .route(
+            "/whitelist",
+            get({
+                let whitelist = whitelist.clone();
+                move || whitelist_handler(whitelist)
+            })
+            .merge(put(move |body| replace_whitelist_handler(body, whitelist)).layer(auth)),
         )
+        .route("/events", get(move || events_handler(events)))
+        .route("/version", get(version_handler))
+        .route("/metrics", get(move || metrics_handler(metrics)))
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProcessesResponse {
     pub timestamp: String,
+    /// Processes whose kill command failed
     pub failed_to_terminate: Vec<String>,
+    /// Matching processes not killed because the per-request cap was reached
+    pub not_terminated_due_to_limit: Vec<String>,
+    /// Processes detected before termination and absent from a fresh scan afterwards
+    pub terminated: Vec<String>,
+    /// Processes detected before termination that are still running afterwards, whether
+    /// the kill failed or the process respawned
+    pub still_running: Vec<String>,
+    pub enumeration: EnumerationStatus,
     pub platform: String,
 }
 
+/// Result of `POST /enforce`: the forbidden processes found by a single scan, and what
+/// terminating exactly those did.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EnforceResponse {
+    pub detected: Vec<String>,
+    #[serde(flatten)]
+    pub termination: ProcessesResponse,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VersionResponse {
     pub version: String,
@@ -516,56 +1446,255 @@ async fn version_handler() -> impl IntoResponse {
     Json(response)
 }
 
+async fn events_handler(events: SharedEventLog) -> impl IntoResponse {
+    let events = events.lock().unwrap().events();
+    Json(events)
+}
+
+async fn metrics_handler(metrics: Arc<MonitorMetrics>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
+
+// With `BUILDIT_MONITOR_TOKEN` set, the endpoints that kill processes or change what is
+// detected require `Authorization: Bearer <token>`; without it they stay open.
+async fn require_token(
+    State(token): State<Option<Arc<str>>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if let Some(expected) = &token {
+        let provided = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if !provided.is_some_and(|provided| tokens_match(provided, expected)) {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Missing or invalid monitor token" })),
+            )
+                .into_response();
+        }
+    }
+    next.run(req).await
+}
+
+// Compare a provided token with the expected one in time that depends only on the expected
+// token's length, so response times don't reveal how much of a guess was right
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    let provided = provided.as_bytes();
+    let mut diff = provided.len() ^ expected.len();
+    for (i, byte) in expected.bytes().enumerate() {
+        diff |= usize::from(provided.get(i).copied().unwrap_or(0) ^ byte);
+    }
+    diff == 0
+}
+
+async fn forbidden_list_handler(forbidden_list: SharedForbiddenList) -> impl IntoResponse {
+    let forbidden_list = forbidden_list.read().unwrap().clone();
+    Json(forbidden_list.as_ref().clone())
+}
+
+// Replace the whole list; later scans pick it up, so no restart is needed
+async fn replace_forbidden_list_handler(
+    JsonBody(new_list): JsonBody<Vec<ForbiddenEntry>>,
+    forbidden_list: SharedForbiddenList,
+) -> Response {
+    let errors: Vec<FieldError> = new_list
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.name.trim().is_empty())
+        .map(|(i, _)| FieldError::new(format!("[{}].name", i), "Process names must not be empty"))
+        .collect();
+    if !errors.is_empty() {
+        return InvalidRequest::new(errors).into_response();
+    }
+
+    replace_forbidden_list(&forbidden_list, new_list.clone());
+    Json(new_list).into_response()
+}
+
+// POST /forbidden/reload: re-read BUILDIT_FORBIDDEN_LIST_FILE, the HTTP equivalent of SIGHUP
+async fn reload_forbidden_list_handler(
+    forbidden_list: SharedForbiddenList,
+    path: Option<PathBuf>,
+) -> Response {
+    if let Err(e) = reload_forbidden_list(&forbidden_list, path.as_deref()) {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("{:#}", e) })),
+        )
+            .into_response();
+    }
+    let forbidden_list = forbidden_list.read().unwrap().clone();
+    Json(forbidden_list.as_ref().clone()).into_response()
+}
+
+async fn whitelist_handler(whitelist: SharedWhitelist) -> impl IntoResponse {
+    let whitelist = whitelist.read().unwrap().clone();
+    Json(whitelist.as_ref().clone())
+}
+
+async fn replace_whitelist_handler(
+    JsonBody(names): JsonBody<Vec<String>>,
+    whitelist: SharedWhitelist,
+) -> Response {
+    let errors: Vec<FieldError> = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.trim().is_empty())
+        .map(|(i, _)| FieldError::new(format!("[{}]", i), "Process names must not be empty"))
+        .collect();
+    if !errors.is_empty() {
+        return InvalidRequest::new(errors).into_response();
+    }
+
+    println!("Updated process whitelist ({} entries)", names.len());
+    *whitelist.write().unwrap() = Arc::new(names.clone());
+    Json(names).into_response()
+}
+
 async fn processes_handler(
-    #[cfg(windows)] Query(params): Query<StatusQuery>,
-    #[cfg(not(windows))] Query(_params): Query<StatusQuery>,
-    forbidden_list: Arc<Vec<String>>,
-) -> impl IntoResponse {
-    let platform = if cfg!(windows) {
-        "windows"
-    } else if cfg!(target_os = "macos") {
-        "macos"
-    } else if cfg!(target_os = "linux") {
-        "linux"
-    } else {
-        "unknown"
-    };
+    QueryParams(params): QueryParams<ProcessesQuery>,
+    forbidden_list: SharedForbiddenList,
+    whitelist: SharedWhitelist,
+    max_terminations: usize,
+    metrics: Arc<MonitorMetrics>,
+) -> Response {
+    let forbidden_list = forbidden_list.read().unwrap().clone();
+    let whitelist = whitelist.read().unwrap().clone();
+    match enforce(
+        &params,
+        &forbidden_list,
+        &whitelist,
+        max_terminations,
+        &metrics,
+    )
+    .await
+    {
+        Ok((_, response)) => Json(response).into_response(),
+        Err(rejection) => rejection,
+    }
+}
+
+async fn enforce_handler(
+    QueryParams(params): QueryParams<ProcessesQuery>,
+    forbidden_list: SharedForbiddenList,
+    whitelist: SharedWhitelist,
+    max_terminations: usize,
+    metrics: Arc<MonitorMetrics>,
+) -> Response {
+    let forbidden_list = forbidden_list.read().unwrap().clone();
+    let whitelist = whitelist.read().unwrap().clone();
+    match enforce(
+        &params,
+        &forbidden_list,
+        &whitelist,
+        max_terminations,
+        &metrics,
+    )
+    .await
+    {
+        Ok((detected, termination)) => Json(EnforceResponse {
+            detected,
+            termination,
+        })
+        .into_response(),
+        Err(rejection) => rejection,
+    }
+}
+
+// Detect and terminate the (selected) forbidden processes from one scan, then confirm which
+// of them are gone. Returns what was detected along with the termination report.
+async fn enforce(
+    params: &ProcessesQuery,
+    forbidden_list: &[ForbiddenEntry],
+    whitelist: &[String],
+    max_terminations: usize,
+    metrics: &MonitorMetrics,
+) -> Result<(Vec<String>, ProcessesResponse), Response> {
+    let mut forbidden_list = forbidden_list.to_vec();
+    if let Some(names) = params.names.as_deref() {
+        let names: Vec<String> = names
+            .split(',')
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .collect();
+        match select_forbidden_entries(&forbidden_list, &names) {
+            Ok(subset) => forbidden_list = subset,
+            Err(unknown) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "Requested names are not on the forbidden list",
+                        "unknown": unknown,
+                    })),
+                )
+                    .into_response());
+            }
+        }
+    }
 
-    let failed = terminate_forbidden_processes(
+    let scan_start = Instant::now();
+    let snapshot = ProcessSnapshot::take(params.include_topmost).exempt(whitelist);
+    let detected = snapshot.detect(&forbidden_list);
+    metrics.record_scan(scan_start.elapsed(), detected.len());
+    let outcome = snapshot.terminate(
         &forbidden_list,
-        #[cfg(windows)]
-        params.include_topmost,
+        params.max_terminations.unwrap_or(max_terminations),
+        kill_process,
     );
+    metrics.record_terminations(&outcome);
+    let (terminated, still_running) = confirm_termination(&forbidden_list, &detected).await;
 
     let response = ProcessesResponse {
         timestamp: Utc::now().to_rfc3339(),
-        failed_to_terminate: failed,
-        platform: platform.to_string(),
+        failed_to_terminate: outcome.failed,
+        not_terminated_due_to_limit: outcome.over_limit,
+        terminated,
+        still_running,
+        enumeration: outcome.enumeration,
+        platform: current_platform().to_string(),
     };
-
-    Json(response)
+    Ok((detected, response))
 }
 
 async fn status_handler(
-    Query(params): Query<StatusQuery>,
-    forbidden_list: Arc<Vec<String>>,
+    QueryParams(params): QueryParams<StatusQuery>,
+    forbidden_list: SharedForbiddenList,
+    whitelist: SharedWhitelist,
+    forbidden_titles: Arc<Vec<String>>,
+    metrics: Arc<MonitorMetrics>,
 ) -> impl IntoResponse {
-    let platform = if cfg!(windows) {
-        "windows"
-    } else if cfg!(target_os = "macos") {
-        "macos"
-    } else if cfg!(target_os = "linux") {
-        "linux"
+    let forbidden_list = forbidden_list.read().unwrap().clone();
+    let whitelist = whitelist.read().unwrap().clone();
+
+    let forbidden_list = filter_by_category(&forbidden_list, params.category);
+    let scan_start = Instant::now();
+    let mut snapshot = ProcessSnapshot::take(params.include_topmost).exempt(&whitelist);
+    if params.names_only {
+        snapshot = snapshot.names_only();
+    }
+    let forbidden_processes = snapshot.detect(&forbidden_list);
+    let matches = (!params.names_only).then(|| snapshot.matches(&forbidden_list));
+    metrics.record_scan(scan_start.elapsed(), forbidden_processes.len());
+    // Window enumeration is comparatively costly, so it only runs when asked for
+    let forbidden_windows = if params.include_window_titles {
+        detect_forbidden_windows(&forbidden_titles)
     } else {
-        "unknown"
+        Vec::new()
     };
 
-    let forbidden_processes = detect_forbidden_processes(&forbidden_list, params.include_topmost);
-
     let response = StatusResponse {
         timestamp: Utc::now().to_rfc3339(),
         forbidden_processes,
-        platform: platform.to_string(),
+        matches,
+        enumeration: snapshot.enumeration,
+        forbidden_windows,
+        platform: current_platform().to_string(),
         #[cfg(target_os = "macos")]
         is_siri_active: siri_overlay_active(),
     };
@@ -573,14 +1702,30 @@ async fn status_handler(
     Json(response)
 }
 
-pub async fn run(ready_tx: Option<oneshot::Sender<()>>) -> Result<()> {
+/// Port of the monitor unless `BUILDIT_MONITOR_PORT` overrides it
+pub const DEFAULT_PORT: u16 = 8765;
+
+/// Serve the monitor on `port` until `shutdown` resolves, e.g. on Ctrl+C (see
+/// `net::shutdown_requested`). `ready_tx` is told once connections are accepted.
+pub async fn run(
+    port: u16,
+    ready_tx: Option<oneshot::Sender<()>>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
     println!("Starting process monitor...");
 
-    let forbidden_list = Arc::new(get_default_forbidden_list());
+    let bind_ip = monitor_bind_ip(std::env::var("BUILDIT_MONITOR_HOST").ok().as_deref())?;
+    let cors = net::cors_layer(std::env::var("BUILDIT_CORS_ORIGINS").ok().as_deref())?;
+    if !bind_ip.is_loopback() {
+        println!("⚠️ Process monitor is reachable on {}", bind_ip);
+    }
+
+    let list_path = forbidden_list_path();
+    let initial_list = load_forbidden_list(list_path.as_deref())?;
 
     println!(
         "Checking for {} known forbidden processes",
-        forbidden_list.len()
+        initial_list.len()
     );
     println!(
         "Platform: {}",
@@ -595,10 +1740,62 @@ pub async fn run(ready_tx: Option<oneshot::Sender<()>>) -> Result<()> {
         }
     );
 
-    let app = build_app(forbidden_list.clone());
+    let forbidden_list: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(initial_list)));
+    #[cfg(unix)]
+    reload_on_sighup(forbidden_list.clone(), list_path)?;
+
+    let alerter = WebhookConfig::from_env().map(|config| {
+        println!("Sending detection alerts to {}", config.url);
+        WebhookAlerter::spawn(config)
+    });
+
+    // Guardrail against an overly broad forbidden list killing the whole session
+    let max_terminations = std::env::var("BUILDIT_MAX_TERMINATIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_TERMINATIONS);
+
+    let forbidden_titles = Arc::new(forbidden_titles(
+        std::env::var("BUILDIT_FORBIDDEN_TITLES").ok().as_deref(),
+    ));
+
+    let whitelist = process_whitelist(std::env::var("BUILDIT_PROCESS_WHITELIST").ok().as_deref());
+    if !whitelist.is_empty() {
+        println!("Exempting whitelisted processes: {}", whitelist.join(", "));
+    }
+    let whitelist: SharedWhitelist = Arc::new(RwLock::new(Arc::new(whitelist)));
+
+    let events: SharedEventLog = Arc::new(Mutex::new(EventLog::new(EVENT_LOG_CAPACITY)));
+    let scan_interval = std::env::var("BUILDIT_MONITOR_SCAN_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map_or(DEFAULT_SCAN_INTERVAL, Duration::from_secs);
+    if !scan_interval.is_zero() {
+        tokio::spawn(scan_periodically(
+            forbidden_list.clone(),
+            whitelist.clone(),
+            events.clone(),
+            forbidden_titles.clone(),
+            alerter,
+            scan_interval,
+        ));
+    } else if alerter.is_some() {
+        eprintln!("⚠️ Detection alerts need the background scan; none are sent while it is off");
+    }
 
-    let port = 8765;
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let app = build_app(
+        forbidden_list,
+        whitelist,
+        events,
+        forbidden_titles,
+        max_terminations,
+        std::env::var("BUILDIT_MONITOR_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty()),
+    )
+    .layer(cors);
+
+    let addr = SocketAddr::new(bind_ip, port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     println!("🟢 Process monitor is running...\n");
     if let Some(tx) = ready_tx {
@@ -607,7 +1804,925 @@ pub async fn run(ready_tx: Option<oneshot::Sender<()>>) -> Result<()> {
     // println!("Try: curl http://localhost:{}/status", port);
     // println!("With topmost detection (Windows only): curl 'http://localhost:{}/status?include_topmost=true'", port);
 
-    axum::serve(listener, app).await?;
-
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown.await;
+            println!("Shutting down the process monitor...");
+        })
+        .await?;
+    println!("🔴 Process monitor stopped");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process_names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_assess_enumeration() {
+        assert_eq!(
+            assess_enumeration(&[], false),
+            EnumerationStatus::Unavailable
+        );
+        assert_eq!(
+            assess_enumeration(&process_names(&["init", "agent"]), true),
+            EnumerationStatus::Complete
+        );
+        // Not seeing ourselves means the listing is partial
+        assert_eq!(
+            assess_enumeration(&process_names(&["init", "sshd"]), false),
+            EnumerationStatus::Degraded
+        );
+        // Mostly unreadable names
+        assert_eq!(
+            assess_enumeration(&process_names(&["", "", "agent"]), true),
+            EnumerationStatus::Degraded
+        );
+    }
+
+    #[test]
+    fn test_degraded_scan_is_not_all_clear() {
+        let response = StatusResponse {
+            timestamp: Utc::now().to_rfc3339(),
+            forbidden_processes: vec![],
+            matches: None,
+            enumeration: EnumerationStatus::Unavailable,
+            forbidden_windows: vec![],
+            platform: current_platform().to_string(),
+            #[cfg(target_os = "macos")]
+            is_siri_active: false,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["enumeration"], "unavailable");
+        assert_eq!(json["forbidden_processes"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_match_window_titles() {
+        let patterns = forbidden_titles(Some("chatgpt, Remote Desktop ,"));
+        assert_eq!(patterns, vec!["chatgpt", "Remote Desktop"]);
+        let titles = process_names(&[
+            "ChatGPT - Google Chrome",
+            "main.py - Notepad",
+            "exam.example.com - Remote Desktop Connection",
+            "ChatGPT - Google Chrome",
+        ]);
+        assert_eq!(
+            match_window_titles(&patterns, &titles),
+            vec![
+                "ChatGPT - Google Chrome",
+                "exam.example.com - Remote Desktop Connection",
+            ]
+        );
+        assert!(match_window_titles(&[], &titles).is_empty());
+        assert_eq!(forbidden_titles(None), get_default_forbidden_titles());
+    }
+
+    #[test]
+    fn test_scan_sees_own_process() {
+        let enumeration = ProcessSnapshot::take(false).enumeration;
+        assert_eq!(enumeration, EnumerationStatus::Complete);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_topmost_scan_without_x_display() {
+        assert!(x11_topmost_pids(Some(":4242")).is_err());
+    }
+
+    #[test]
+    fn test_monitor_bind_ip() {
+        assert_eq!(
+            monitor_bind_ip(None).unwrap(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert_eq!(
+            monitor_bind_ip(Some("0.0.0.0")).unwrap(),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        );
+        assert!(monitor_bind_ip(Some("not-an-ip")).is_err());
+    }
+
+    fn names(list: &[ForbiddenEntry]) -> Vec<&str> {
+        list.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_filter_by_category_returns_subset() {
+        let list = get_default_forbidden_list();
+        let debuggers = filter_by_category(&list, Some(ProcessCategory::Debugger));
+
+        assert!(!debuggers.is_empty());
+        assert!(debuggers
+            .iter()
+            .all(|e| e.category == ProcessCategory::Debugger));
+        assert!(names(&debuggers).contains(&"x64dbg"));
+        assert!(!names(&debuggers).contains(&"vim"));
+    }
+
+    #[test]
+    fn test_filter_by_category_none_keeps_all() {
+        let list = get_default_forbidden_list();
+        assert_eq!(filter_by_category(&list, None), list);
+    }
+
+    #[test]
+    fn test_category_filter_limits_detections() {
+        let list = vec![
+            ForbiddenEntry::new("anydesk", ProcessCategory::RemoteAccess),
+            ForbiddenEntry::new("idea", ProcessCategory::Ide),
+        ];
+        let running = ProcessSnapshot::new(
+            vec![(10, "AnyDesk".to_string()), (20, "idea64.exe".to_string())],
+            vec![],
+            EnumerationStatus::Complete,
+        );
+
+        let remote = filter_by_category(&list, Some(ProcessCategory::RemoteAccess));
+        assert_eq!(running.detect(&remote), vec!["AnyDesk"]);
+
+        let ide = filter_by_category(&list, Some(ProcessCategory::Ide));
+        assert_eq!(running.detect(&ide), vec!["idea64.exe"]);
+
+        assert_eq!(running.detect(&list).len(), 2);
+    }
+
+    #[test]
+    fn test_load_forbidden_list_defaults_without_file() {
+        let list = load_forbidden_list(None).unwrap();
+        assert_eq!(list, get_default_forbidden_list());
+    }
+
+    #[test]
+    fn test_load_forbidden_list_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("forbidden.json");
+        std::fs::write(&path, r#"[{"name":"anydesk","category":"remote_access"}]"#).unwrap();
+
+        let list = load_forbidden_list(Some(&path)).unwrap();
+        assert_eq!(
+            list,
+            vec![ForbiddenEntry::new(
+                "anydesk",
+                ProcessCategory::RemoteAccess
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_old_list_on_bad_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("forbidden.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let original = vec![ForbiddenEntry::new("vim", ProcessCategory::Editor)];
+        let shared: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(original.clone())));
+        assert!(reload_forbidden_list(&shared, Some(&path)).is_err());
+        assert_eq!(*shared.read().unwrap().as_ref(), original);
+    }
+
+    #[tokio::test]
+    async fn test_reload_handler_reports_bad_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("forbidden.json");
+        std::fs::write(&path, r#"[{"name":"ghidra","category":"debugger"}]"#).unwrap();
+        let shared: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(vec![])));
+
+        let response = reload_forbidden_list_handler(shared.clone(), Some(path.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let reloaded = vec![ForbiddenEntry::new("ghidra", ProcessCategory::Debugger)];
+        assert_eq!(*shared.read().unwrap().as_ref(), reloaded);
+
+        std::fs::write(&path, "not json").unwrap();
+        let response = reload_forbidden_list_handler(shared.clone(), Some(path)).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(*shared.read().unwrap().as_ref(), reloaded);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reload_request_reloads_edited_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("forbidden.json");
+        std::fs::write(&path, r#"[{"name":"vim","category":"editor"}]"#).unwrap();
+
+        let initial = load_forbidden_list(Some(&path)).unwrap();
+        let shared: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(initial)));
+        // Stands in for SIGHUP, which would reach every test in the process
+        let (reload_tx, reload_rx) = futures::channel::mpsc::unbounded();
+        reload_on(shared.clone(), Some(path.clone()), reload_rx);
+
+        std::fs::write(&path, r#"[{"name":"ghidra","category":"debugger"}]"#).unwrap();
+        reload_tx.unbounded_send(()).unwrap();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        loop {
+            if shared.read().unwrap()[0].name == "ghidra" {
+                break;
+            }
+            assert!(
+                std::time::Instant::now() < deadline,
+                "reload did not happen"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }
+
+    #[test]
+    fn test_select_forbidden_entries_subset() {
+        let list = vec![
+            ForbiddenEntry::new("anydesk", ProcessCategory::RemoteAccess),
+            ForbiddenEntry::new("vim", ProcessCategory::Editor),
+        ];
+        let subset = select_forbidden_entries(&list, &["AnyDesk".to_string()]).unwrap();
+        assert_eq!(subset, vec![list[0].clone()]);
+    }
+
+    #[test]
+    fn test_select_forbidden_entries_rejects_unknown_names() {
+        let list = vec![ForbiddenEntry::new("vim", ProcessCategory::Editor)];
+        let err =
+            select_forbidden_entries(&list, &["vim".to_string(), "sshd".to_string()]).unwrap_err();
+        assert_eq!(err, vec!["sshd".to_string()]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_terminate_only_selected_process() {
+        // Two copies of `sleep` under distinct names so they can be told apart
+        let dir = tempfile::tempdir().unwrap();
+        let spawn = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::copy("/bin/sleep", &path).unwrap();
+            std::process::Command::new(&path).arg("30").spawn().unwrap()
+        };
+        let mut alpha = spawn("bitest-alpha");
+        let mut beta = spawn("bitest-beta");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let list = vec![
+            ForbiddenEntry::new("bitest-alpha", ProcessCategory::SystemTool),
+            ForbiddenEntry::new("bitest-beta", ProcessCategory::SystemTool),
+        ];
+        let subset = select_forbidden_entries(&list, &["bitest-alpha".to_string()]).unwrap();
+        let outcome =
+            ProcessSnapshot::take(false).terminate(&subset, DEFAULT_MAX_TERMINATIONS, kill_process);
+        assert!(outcome.failed.is_empty());
+        assert_eq!(outcome.enumeration, EnumerationStatus::Complete);
+
+        let alpha_status = alpha.wait().unwrap();
+        assert!(!alpha_status.success());
+        assert!(
+            beta.try_wait().unwrap().is_none(),
+            "unselected process must survive"
+        );
+
+        beta.kill().unwrap();
+        beta.wait().unwrap();
+    }
+
+    #[test]
+    fn test_status_query_parses_category() {
+        let query: StatusQuery = serde_json::from_str(r#"{"category":"remote_access"}"#).unwrap();
+        assert_eq!(query.category, Some(ProcessCategory::RemoteAccess));
+        assert!(!query.include_topmost);
+    }
+
+    #[test]
+    fn test_verify_termination_splits_gone_and_persisting() {
+        let before = process_names(&["obs", "teamviewer", "vim"]);
+        let after = process_names(&["teamviewer", "zoom"]);
+        let (terminated, still_running) = verify_termination(&before, &after);
+        assert_eq!(terminated, process_names(&["obs", "vim"]));
+        assert_eq!(still_running, process_names(&["teamviewer"]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_post_scan_confirms_killed_process_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bitest-verify");
+        std::fs::copy("/bin/sleep", &path).unwrap();
+        // Left unreaped on purpose: a killed zombie must not count as still running
+        let mut child = std::process::Command::new(&path).arg("30").spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let list = vec![ForbiddenEntry::new(
+            "bitest-verify",
+            ProcessCategory::SystemTool,
+        )];
+        let before = ProcessSnapshot::take(false).detect(&list);
+        assert_eq!(before, process_names(&["bitest-verify"]));
+
+        let outcome =
+            ProcessSnapshot::take(false).terminate(&list, DEFAULT_MAX_TERMINATIONS, kill_process);
+        assert!(outcome.failed.is_empty());
+        let (terminated, still_running) = confirm_termination(&list, &before).await;
+        assert_eq!(terminated, before);
+        assert!(still_running.is_empty());
+
+        child.wait().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_terminations_stop_at_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut children: Vec<_> = ["bitest-cap-a", "bitest-cap-b", "bitest-cap-c"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                std::fs::copy("/bin/sleep", &path).unwrap();
+                std::process::Command::new(&path).arg("30").spawn().unwrap()
+            })
+            .collect();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let list = vec![ForbiddenEntry::new(
+            "bitest-cap",
+            ProcessCategory::SystemTool,
+        )];
+        let outcome = ProcessSnapshot::take(false).terminate(&list, 2, kill_process);
+        assert!(outcome.failed.is_empty());
+        assert_eq!(outcome.over_limit.len(), 1);
+        assert_eq!((outcome.killed, outcome.kill_failures), (2, 0));
+
+        std::thread::sleep(Duration::from_millis(100));
+        let survivors = children
+            .iter_mut()
+            .map(|child| child.try_wait().unwrap().is_none())
+            .filter(|&alive| alive)
+            .count();
+        assert_eq!(survivors, 1, "only the cap's worth may be killed");
+        for child in &mut children {
+            let _ = child.kill();
+            child.wait().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_termination_kills_exactly_what_was_detected() {
+        let snapshot = ProcessSnapshot::new(
+            vec![
+                (30, "vim".to_string()),
+                (10, "obs".to_string()),
+                (20, "bash".to_string()),
+            ],
+            vec![],
+            EnumerationStatus::Complete,
+        );
+        let list = vec![
+            ForbiddenEntry::new("obs", ProcessCategory::Recording),
+            ForbiddenEntry::new("vim", ProcessCategory::Editor),
+            ForbiddenEntry::new("zoom", ProcessCategory::Network),
+        ];
+        let detected = snapshot.detect(&list);
+        assert_eq!(detected, process_names(&["obs", "vim"]));
+
+        // Whatever starts after the scan is not killed, and nothing detected is skipped
+        let mut killed = vec![];
+        let outcome = snapshot.terminate(&list, DEFAULT_MAX_TERMINATIONS, |pid| {
+            killed.push(pid);
+            pid != 30
+        });
+        assert_eq!(killed, vec![10, 30]);
+        assert_eq!(outcome.failed, process_names(&["vim"]));
+        assert_eq!((outcome.killed, outcome.kill_failures), (1, 1));
+    }
+
+    #[test]
+    fn test_whitelisted_process_is_not_detected_or_killed() {
+        let snapshot = ProcessSnapshot::new(
+            vec![
+                (10, "code".to_string()),
+                (20, "QtWebEngine_Code_Cache".to_string()),
+            ],
+            vec![],
+            EnumerationStatus::Complete,
+        );
+        let list = vec![ForbiddenEntry::new("code", ProcessCategory::Ide)];
+        assert_eq!(
+            snapshot.detect(&list),
+            process_names(&["QtWebEngine_Code_Cache", "code"])
+        );
+
+        let whitelist = process_whitelist(Some(" qtwebengine_code_cache, "));
+        assert_eq!(whitelist, process_names(&["qtwebengine_code_cache"]));
+        let snapshot = snapshot.exempt(&whitelist);
+        assert_eq!(snapshot.detect(&list), process_names(&["code"]));
+
+        let mut killed = vec![];
+        snapshot.terminate(&list, DEFAULT_MAX_TERMINATIONS, |pid| {
+            killed.push(pid);
+            true
+        });
+        assert_eq!(killed, vec![10]);
+    }
+
+    #[test]
+    fn test_only_forbidden_topmost_owners_are_killed() {
+        let snapshot = ProcessSnapshot::new(
+            vec![(10, "obs".to_string()), (20, "gnome-shell".to_string())],
+            vec![
+                (10, "obs".to_string()),
+                (20, "gnome-shell".to_string()),
+                (40, "overlay".to_string()),
+                (50, "zoom".to_string()),
+            ],
+            EnumerationStatus::Complete,
+        );
+        let list = vec![
+            ForbiddenEntry::new("obs", ProcessCategory::Recording),
+            ForbiddenEntry::new("overlay", ProcessCategory::SystemTool),
+        ];
+        assert_eq!(snapshot.detect(&list), process_names(&["obs", "overlay"]));
+
+        // Owners not on the list are left alone, and each detected owner is killed once by pid
+        let mut killed = vec![];
+        snapshot.terminate(&list, DEFAULT_MAX_TERMINATIONS, |pid| {
+            killed.push(pid);
+            true
+        });
+        assert_eq!(killed, vec![10, 40]);
+
+        let whitelist = process_names(&["overlay"]);
+        assert_eq!(
+            snapshot.clone().exempt(&whitelist).detect(&list),
+            process_names(&["obs"])
+        );
+
+        let mut killed = vec![];
+        let outcome = snapshot.terminate(&list, 1, |pid| {
+            killed.push(pid);
+            true
+        });
+        assert_eq!(killed, vec![10]);
+        assert_eq!(outcome.over_limit, process_names(&["overlay"]));
+    }
+
+    #[test]
+    fn test_path_entries_tell_apart_processes_with_generic_names() {
+        let mut snapshot = ProcessSnapshot::new(
+            vec![(10, "electron".to_string()), (20, "electron".to_string())],
+            vec![],
+            EnumerationStatus::Complete,
+        );
+        snapshot
+            .exe_paths
+            .insert(10, "/usr/lib/slack/electron".to_string());
+        snapshot
+            .exe_paths
+            .insert(20, "/opt/Cursor/electron".to_string());
+        let list = vec![ForbiddenEntry::new("/opt/cursor/", ProcessCategory::Ide)];
+
+        assert_eq!(
+            snapshot.matches(&list),
+            vec![ForbiddenMatch {
+                name: "electron".to_string(),
+                pid: 20,
+                exe_path: Some("/opt/Cursor/electron".to_string()),
+            }]
+        );
+        let mut killed = vec![];
+        snapshot.terminate(&list, DEFAULT_MAX_TERMINATIONS, |pid| {
+            killed.push(pid);
+            true
+        });
+        assert_eq!(killed, vec![20]);
+
+        // Name-only matching never looks at paths
+        let snapshot = snapshot.names_only();
+        assert!(snapshot.detect(&list).is_empty());
+        let list = vec![ForbiddenEntry::new("electron", ProcessCategory::Ide)];
+        assert_eq!(snapshot.matches(&list).len(), 2);
+        assert_eq!(snapshot.detect(&list), process_names(&["electron"]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_scan_reports_executable_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bitest-exe");
+        std::fs::copy("/bin/sleep", &path).unwrap();
+        let mut child = std::process::Command::new(&path).arg("30").spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let dir_entry = format!("{}/", dir.path().display());
+        let list = vec![ForbiddenEntry::new(dir_entry, ProcessCategory::SystemTool)];
+        let matches = ProcessSnapshot::take(false).matches(&list);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(matches.len(), 1, "{:?}", matches);
+        assert_eq!(matches[0].pid, child.id());
+        assert_eq!(matches[0].exe_path, Some(path.display().to_string()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_enforce_reports_detection_and_termination_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bitest-enforce");
+        std::fs::copy("/bin/sleep", &path).unwrap();
+        let mut child = std::process::Command::new(&path).arg("30").spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let list = vec![ForbiddenEntry::new(
+            "bitest-enforce",
+            ProcessCategory::SystemTool,
+        )];
+        let params = ProcessesQuery {
+            include_topmost: false,
+            names: None,
+            max_terminations: None,
+        };
+        let metrics = MonitorMetrics::default();
+        let (detected, response) = enforce(&params, &list, &[], DEFAULT_MAX_TERMINATIONS, &metrics)
+            .await
+            .unwrap();
+        assert_eq!(detected, process_names(&["bitest-enforce"]));
+        assert_eq!(response.terminated, detected);
+        assert!(response.failed_to_terminate.is_empty() && response.still_running.is_empty());
+        assert!(metrics
+            .render()
+            .contains("buildit_monitor_terminations_total 1"));
+
+        child.wait().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_put_forbidden_updates_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bitest-put");
+        std::fs::copy("/bin/sleep", &path).unwrap();
+        let mut child = std::process::Command::new(&path).arg("30").spawn().unwrap();
+
+        let list: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(vec![])));
+        let whitelist: SharedWhitelist = Arc::new(RwLock::new(Arc::new(vec![])));
+        let events = Arc::new(Mutex::new(EventLog::new(EVENT_LOG_CAPACITY)));
+        let app = build_app(
+            list,
+            whitelist,
+            events,
+            Arc::new(vec![]),
+            DEFAULT_MAX_TERMINATIONS,
+            None,
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = reqwest::Client::new();
+
+        let status = |client: reqwest::Client| {
+            let url = format!("{}/status", base);
+            async move {
+                let response: StatusResponse =
+                    client.get(url).send().await.unwrap().json().await.unwrap();
+                response.forbidden_processes
+            }
+        };
+        assert!(status(client.clone()).await.is_empty());
+
+        let entries = vec![ForbiddenEntry::new(
+            "bitest-put",
+            ProcessCategory::SystemTool,
+        )];
+        let response = client
+            .put(format!("{}/forbidden", base))
+            .json(&entries)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let current: Vec<ForbiddenEntry> = client
+            .get(format!("{}/forbidden", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(current, entries);
+        assert_eq!(status(client.clone()).await, process_names(&["bitest-put"]));
+
+        // A blank name is rejected and the list is left alone
+        let response = client
+            .put(format!("{}/forbidden", base))
+            .json(&serde_json::json!([{"name": " ", "category": "editor"}]))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(status(client).await, process_names(&["bitest-put"]));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_listens_on_the_given_port() {
+        // Find a free port, then hand it to the monitor
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        assert_ne!(port, DEFAULT_PORT);
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let monitor = tokio::spawn(run(port, Some(ready_tx), std::future::pending()));
+        ready_rx.await.unwrap();
+
+        let version: VersionResponse = reqwest::get(format!("http://127.0.0.1:{}/version", port))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_the_monitor_gracefully() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let (ready_tx, ready_rx) = oneshot::channel();
+        // Stands in for Ctrl+C or SIGTERM, which would reach every test in the process
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        let stop = async {
+            let _ = stop_rx.await;
+        };
+        let monitor = tokio::spawn(run(port, Some(ready_tx), stop));
+        ready_rx.await.unwrap();
+
+        stop_tx.send(()).unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), monitor)
+            .await
+            .expect("monitor did not stop")
+            .unwrap();
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_tokens_match_only_when_identical() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3creT", "s3cret"));
+        assert!(!tokens_match("s3cre", "s3cret"));
+        assert!(!tokens_match("s3cret\0", "s3cret"));
+        assert!(!tokens_match("", "s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_destructive_endpoints_require_token() {
+        let list: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(vec![])));
+        let whitelist: SharedWhitelist = Arc::new(RwLock::new(Arc::new(vec![])));
+        let events = Arc::new(Mutex::new(EventLog::new(EVENT_LOG_CAPACITY)));
+        let app = build_app(
+            list,
+            whitelist,
+            events,
+            Arc::new(vec![]),
+            DEFAULT_MAX_TERMINATIONS,
+            Some("secret".to_string()),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = reqwest::Client::new();
+
+        let unauthorized = [
+            client.delete(format!("{}/processes", base)),
+            client
+                .delete(format!("{}/processes", base))
+                .bearer_auth("wrong"),
+            client.post(format!("{}/enforce", base)),
+            client.post(format!("{}/forbidden/reload", base)),
+            client.put(format!("{}/whitelist", base)).json(&["vim"]),
+        ];
+        for request in unauthorized {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        // Rejected before the list was touched
+        let whitelist: Vec<String> = client
+            .get(format!("{}/whitelist", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(whitelist.is_empty());
+
+        let response = client
+            .delete(format!("{}/processes", base))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let report: ProcessesResponse = response.json().await.unwrap();
+        assert!(report.terminated.is_empty());
+
+        for path in ["/status", "/version"] {
+            let response = client
+                .get(format!("{}{}", base, path))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_event_log_records_changes_and_caps_history() {
+        let mut log = EventLog::new(3);
+        log.record_changes(&[], &process_names(&["obs", "vim"]), "t1");
+        log.record_changes(
+            &process_names(&["obs", "vim"]),
+            &process_names(&["obs", "vim"]),
+            "t2",
+        );
+        log.record_changes(
+            &process_names(&["obs", "vim"]),
+            &process_names(&["vim"]),
+            "t3",
+        );
+        let summary = |log: &EventLog| -> Vec<(String, String, DetectionEventKind)> {
+            log.events()
+                .into_iter()
+                .map(|e| (e.timestamp, e.process, e.event))
+                .collect()
+        };
+        assert_eq!(
+            summary(&log),
+            vec![
+                ("t1".into(), "obs".into(), DetectionEventKind::Detected),
+                ("t1".into(), "vim".into(), DetectionEventKind::Detected),
+                ("t3".into(), "obs".into(), DetectionEventKind::Cleared),
+            ]
+        );
+
+        // The oldest event makes room for the newest
+        log.record_changes(&process_names(&["vim"]), &[], "t4");
+        assert_eq!(log.events().len(), 3);
+        assert_eq!(log.events()[0].process, "vim");
+        assert_eq!(log.events()[2].event, DetectionEventKind::Cleared);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_background_scan_logs_detections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bitest-events");
+        std::fs::copy("/bin/sleep", &path).unwrap();
+        let mut child = std::process::Command::new(&path).arg("30").spawn().unwrap();
+
+        let list = vec![ForbiddenEntry::new(
+            "bitest-events",
+            ProcessCategory::SystemTool,
+        )];
+        let list: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(list)));
+        let whitelist: SharedWhitelist = Arc::new(RwLock::new(Arc::new(vec![])));
+        let events = Arc::new(Mutex::new(EventLog::new(EVENT_LOG_CAPACITY)));
+        let scan = tokio::spawn(scan_periodically(
+            list,
+            whitelist,
+            events.clone(),
+            Arc::new(vec![]),
+            None,
+            Duration::from_millis(50),
+        ));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while events.lock().unwrap().events().is_empty() {
+            assert!(Instant::now() < deadline, "nothing was logged");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        child.kill().unwrap();
+        child.wait().unwrap();
+        while events.lock().unwrap().events().len() < 2 {
+            assert!(Instant::now() < deadline, "the exit was not logged");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        scan.abort();
+
+        let logged = events.lock().unwrap().events();
+        assert_eq!(logged[0].process, "bitest-events");
+        assert_eq!(logged[0].event, DetectionEventKind::Detected);
+        assert_eq!(logged[0].platform, current_platform());
+        assert_eq!(logged[1].event, DetectionEventKind::Cleared);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_category_filtered_polls_do_not_realert() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bitest-alert");
+        std::fs::copy("/bin/sleep", &path).unwrap();
+        let mut child = std::process::Command::new(&path).arg("30").spawn().unwrap();
+
+        let hook = Router::new().route("/hook", post(|| async { StatusCode::OK }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, hook).await });
+        let alerter = WebhookAlerter::spawn(WebhookConfig::new(url));
+
+        let list = vec![
+            ForbiddenEntry::new("bitest-alert", ProcessCategory::SystemTool),
+            ForbiddenEntry::new("bitest-absent", ProcessCategory::Editor),
+        ];
+        let list: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(list)));
+        let whitelist: SharedWhitelist = Arc::new(RwLock::new(Arc::new(vec![])));
+        let events = Arc::new(Mutex::new(EventLog::new(EVENT_LOG_CAPACITY)));
+        let scan = tokio::spawn(scan_periodically(
+            list.clone(),
+            whitelist.clone(),
+            events,
+            Arc::new(vec![]),
+            Some(alerter.clone()),
+            Duration::from_millis(50),
+        ));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while alerter.stats().delivered == 0 {
+            assert!(Instant::now() < deadline, "the detection was not alerted");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        // Dashboards watching different categories see different sets; neither re-alerts
+        let metrics = Arc::new(MonitorMetrics::default());
+        for category in [ProcessCategory::Editor, ProcessCategory::SystemTool].repeat(3) {
+            let query = StatusQuery {
+                include_topmost: false,
+                include_window_titles: false,
+                category: Some(category),
+                names_only: false,
+            };
+            let _ = status_handler(
+                QueryParams(query),
+                list.clone(),
+                whitelist.clone(),
+                Arc::new(vec![]),
+                metrics.clone(),
+            )
+            .await;
+            tokio::time::sleep(Duration::from_millis(60)).await;
+        }
+        scan.abort();
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let stats = alerter.stats();
+        assert_eq!((stats.delivered, stats.pending), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_export_after_scan() {
+        let metrics = Arc::new(MonitorMetrics::default());
+        let list: SharedForbiddenList = Arc::new(RwLock::new(Arc::new(vec![])));
+        let query = StatusQuery {
+            include_topmost: false,
+            include_window_titles: false,
+            category: None,
+            names_only: false,
+        };
+        let titles = Arc::new(vec![]);
+        let whitelist = Arc::new(RwLock::new(Arc::new(vec![])));
+        let _ = status_handler(QueryParams(query), list, whitelist, titles, metrics.clone()).await;
+        metrics.record_terminations(&TerminationOutcome {
+            failed: vec!["vim".to_string()],
+            over_limit: vec![],
+            killed: 2,
+            kill_failures: 1,
+            enumeration: EnumerationStatus::Complete,
+        });
+
+        let text = metrics.render();
+        for name in [
+            "buildit_monitor_scans_total",
+            "buildit_monitor_scan_duration_seconds_total",
+            "buildit_monitor_forbidden_processes",
+            "buildit_monitor_terminations_total",
+            "buildit_monitor_termination_failures_total",
+        ] {
+            assert!(
+                text.contains(&format!("# TYPE {} ", name)),
+                "missing {}",
+                name
+            );
+        }
+        assert!(text.contains("\nbuildit_monitor_scans_total 1\n"));
+        assert!(text.contains("\nbuildit_monitor_forbidden_processes 0\n"));
+        assert!(text.contains("\nbuildit_monitor_terminations_total 2\n"));
+        assert!(text.contains("\nbuildit_monitor_termination_failures_total 1\n"));
+    }
+}
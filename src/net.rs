@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use axum::http::HeaderValue;
+use std::future::Future;
+use tower_http::cors::{self, AllowOrigin, CorsLayer};
+
+/// Port a service listens on: the value of the environment variable `var` when set,
+/// otherwise `default`.
+pub fn port_from_env(var: &str, default: u16) -> Result<u16> {
+    parse_port(var, std::env::var(var).ok().as_deref(), default)
+}
+
+fn parse_port(var: &str, value: Option<&str>, default: u16) -> Result<u16> {
+    match value.map(str::trim) {
+        Some(value) if !value.is_empty() => value
+            .parse()
+            .with_context(|| format!("invalid {} {:?}", var, value)),
+        _ => Ok(default),
+    }
+}
+
+/// Resolves on Ctrl+C, or on SIGTERM under Unix (e.g. `docker stop`), so a service can
+/// shut down gracefully. The handlers are installed before returning, so a signal that
+/// arrives before the future is first polled is not missed.
+pub fn shutdown_requested() -> impl Future<Output = ()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let interrupt = signal(SignalKind::interrupt());
+        let terminate = signal(SignalKind::terminate());
+        async move {
+            tokio::select! {
+                _ = recv_or_pending(interrupt) => {}
+                _ = recv_or_pending(terminate) => {}
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+// Without a handler the signal can't be waited for, so only other signals count
+#[cfg(unix)]
+async fn recv_or_pending(signal: std::io::Result<tokio::signal::unix::Signal>) {
+    match signal {
+        Ok(mut signal) => {
+            signal.recv().await;
+        }
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// CORS policy shared by both services. Any origin may call them unless `origins`
+/// (`BUILDIT_CORS_ORIGINS`) lists the allowed ones, comma-separated.
+pub fn cors_layer(origins: Option<&str>) -> Result<CorsLayer> {
+    let allow_origin = match allowed_origins(origins)? {
+        Some(origins) => AllowOrigin::list(origins),
+        None => AllowOrigin::any(),
+    };
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(cors::Any)
+        .allow_headers(cors::Any))
+}
+
+// `None` means any origin: the variable is unset, empty or `*`
+fn allowed_origins(origins: Option<&str>) -> Result<Option<Vec<HeaderValue>>> {
+    let origins: Vec<&str> = origins
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .collect();
+    if origins.is_empty() || origins == ["*"] {
+        return Ok(None);
+    }
+    origins
+        .into_iter()
+        .map(|origin| {
+            anyhow::ensure!(
+                origin.starts_with("http://") || origin.starts_with("https://"),
+                "invalid BUILDIT_CORS_ORIGINS entry {:?}: expected e.g. http://localhost:3000",
+                origin
+            );
+            HeaderValue::from_str(origin)
+                .with_context(|| format!("invalid BUILDIT_CORS_ORIGINS entry {:?}", origin))
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_defaults_and_overrides() {
+        assert_eq!(parse_port("PORT", None, 8910).unwrap(), 8910);
+        assert_eq!(parse_port("PORT", Some(" "), 8910).unwrap(), 8910);
+        assert_eq!(parse_port("PORT", Some("9100"), 8910).unwrap(), 9100);
+        let error = parse_port("PORT", Some("99999"), 8910).unwrap_err();
+        assert!(error.to_string().contains("invalid PORT"), "{}", error);
+    }
+
+    #[test]
+    fn test_allowed_origins() {
+        assert!(allowed_origins(None).unwrap().is_none());
+        assert!(allowed_origins(Some(" * ")).unwrap().is_none());
+        let origins = allowed_origins(Some("http://localhost:3000, https://exam.example.org"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            origins,
+            vec![
+                HeaderValue::from_static("http://localhost:3000"),
+                HeaderValue::from_static("https://exam.example.org"),
+            ]
+        );
+        assert!(allowed_origins(Some("localhost:3000")).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_BURST: u32 = 10;
+/// Clients tracked before idle ones (with a full bucket) are forgotten, and then the least
+/// recently seen ones, so a flood of distinct addresses can't grow the map without bound
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Per-client token bucket limiting how fast each caller may submit jobs, so one client
+/// can't starve the others. Clients are told apart by their API key when they send one,
+/// and by IP address otherwise. Only configured keys are accepted, so a client can't get a
+/// fresh bucket by making up a new key for each request.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    allowlist: HashSet<String>,
+    api_keys: HashSet<String>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+/// Why a request was turned away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The API key is neither a configured key nor allowlisted
+    UnknownKey,
+    /// The client is over its limit; holds how long until its next request would be accepted
+    Throttled(Duration),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `per_minute` requests per client on average, and bursts of up to `burst`.
+    /// Callers whose API key or IP address is in `allowlist` are never limited, and each key
+    /// in `api_keys` is limited on its own.
+    pub fn new(
+        per_minute: u32,
+        burst: u32,
+        allowlist: impl IntoIterator<Item = String>,
+        api_keys: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            per_second: f64::from(per_minute) / 60.0,
+            burst: f64::from(burst.max(1)),
+            allowlist: allowlist.into_iter().collect(),
+            api_keys: api_keys.into_iter().collect(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Opt-in via `BUILDIT_RATE_LIMIT_PER_MINUTE`; `BUILDIT_RATE_LIMIT_BURST` sets the burst
+    /// (default 10), `BUILDIT_RATE_LIMIT_ALLOWLIST` the comma-separated trusted callers and
+    /// `BUILDIT_RATE_LIMIT_API_KEYS` the comma-separated keys clients may identify with.
+    pub fn from_env() -> Option<Self> {
+        let per_minute: u32 = std::env::var("BUILDIT_RATE_LIMIT_PER_MINUTE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)?;
+        let burst = std::env::var("BUILDIT_RATE_LIMIT_BURST")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BURST);
+        let allowlist = env_list("BUILDIT_RATE_LIMIT_ALLOWLIST");
+        let api_keys = env_list("BUILDIT_RATE_LIMIT_API_KEYS");
+        Some(Self::new(per_minute, burst, allowlist, api_keys))
+    }
+
+    /// Take a token for one request at `now`. Requests with an API key that isn't
+    /// configured are rejected outright.
+    pub fn check(&self, api_key: Option<&str>, ip: IpAddr, now: Instant) -> Result<(), Rejection> {
+        let ip = ip.to_string();
+        if self.allowlist.contains(&ip) || api_key.is_some_and(|k| self.allowlist.contains(k)) {
+            return Ok(());
+        }
+        if !self.knows_key(api_key) {
+            return Err(Rejection::UnknownKey);
+        }
+        // Prefixed so an API key can't share a bucket with an IP address
+        let client = match api_key {
+            Some(key) => format!("key:{}", key),
+            None => format!("ip:{}", ip),
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            self.evict(&mut buckets, now);
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refilled(*bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Rejection::Throttled(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.per_second,
+            )))
+        }
+    }
+
+    /// Whether requests with `api_key` are accepted at all: no key, or a configured or
+    /// allowlisted one. Takes no token.
+    pub fn knows_key(&self, api_key: Option<&str>) -> bool {
+        api_key.is_none_or(|k| self.api_keys.contains(k) || self.allowlist.contains(k))
+    }
+
+    // Make room for one more client: forget those whose bucket is full again, which costs
+    // them nothing, then the least recently seen, a tenth at a time so a flood of new
+    // clients doesn't rescan the map on every request
+    fn evict(&self, buckets: &mut HashMap<String, Bucket>, now: Instant) {
+        buckets.retain(|_, bucket| self.refilled(*bucket, now) < self.burst);
+        if buckets.len() < MAX_TRACKED_CLIENTS {
+            return;
+        }
+        let mut by_age: Vec<(Instant, String)> = buckets
+            .iter()
+            .map(|(client, bucket)| (bucket.updated, client.clone()))
+            .collect();
+        let excess = buckets.len() + 1 - MAX_TRACKED_CLIENTS;
+        let evicted = (excess + MAX_TRACKED_CLIENTS / 10).min(by_age.len());
+        by_age.select_nth_unstable(evicted - 1);
+        for (_, client) in &by_age[..evicted] {
+            buckets.remove(client);
+        }
+    }
+
+    fn refilled(&self, bucket: Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.per_second).min(self.burst)
+    }
+}
+
+// Comma-separated, trimmed entries of an environment variable
+fn env_list(var: &str) -> Vec<String> {
+    std::env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const FLOOD: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const CALM: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn test_burst_is_throttled_while_slow_client_is_not() {
+        // One request per second on average, bursts of three
+        let limiter = RateLimiter::new(60, 3, [], []);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check(None, FLOOD, start).is_ok());
+        }
+        let rejection = limiter.check(None, FLOOD, start).unwrap_err();
+        assert_eq!(rejection, Rejection::Throttled(Duration::from_secs(1)));
+
+        // A client sending one request every two seconds never hits the limit
+        for i in 0..10 {
+            let now = start + Duration::from_secs(2 * i);
+            assert!(limiter.check(None, CALM, now).is_ok());
+        }
+
+        // The flooding client recovers a token per second
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.check(None, FLOOD, later).is_ok());
+        assert!(limiter.check(None, FLOOD, later).is_err());
+    }
+
+    #[test]
+    fn test_api_keys_are_limited_separately_from_ips() {
+        let keys = ["team-a".to_string(), "team-b".to_string()];
+        let limiter = RateLimiter::new(60, 1, [], keys);
+        let now = Instant::now();
+        assert!(limiter.check(None, FLOOD, now).is_ok());
+        assert!(limiter.check(None, FLOOD, now).is_err());
+        // Same address, but identified by key
+        assert!(limiter.check(Some("team-a"), FLOOD, now).is_ok());
+        assert!(limiter.check(Some("team-b"), FLOOD, now).is_ok());
+        assert!(limiter.check(Some("team-a"), FLOOD, now).is_err());
+    }
+
+    #[test]
+    fn test_made_up_keys_do_not_get_a_fresh_bucket() {
+        let limiter = RateLimiter::new(60, 1, [], ["team-a".to_string()]);
+        let now = Instant::now();
+        assert!(limiter.check(None, FLOOD, now).is_ok());
+        for i in 0..5 {
+            let key = format!("random-{}", i);
+            assert_eq!(
+                limiter.check(Some(&key), FLOOD, now),
+                Err(Rejection::UnknownKey)
+            );
+        }
+        assert!(limiter.check(None, FLOOD, now).is_err());
+    }
+
+    #[test]
+    fn test_allowlisted_callers_are_never_limited() {
+        let allowlist = ["10.0.0.1".to_string(), "grader".to_string()];
+        let limiter = RateLimiter::new(60, 1, allowlist, []);
+        let now = Instant::now();
+        for _ in 0..5 {
+            assert!(limiter.check(None, FLOOD, now).is_ok());
+            assert!(limiter.check(Some("grader"), CALM, now).is_ok());
+        }
+        assert!(limiter.check(None, CALM, now).is_ok());
+        assert!(limiter.check(None, CALM, now).is_err());
+    }
+
+    #[test]
+    fn test_flood_of_addresses_evicts_least_recently_seen() {
+        // Buckets refill too slowly to ever be full again during the flood
+        let limiter = RateLimiter::new(1, 2, [], []);
+        let start = Instant::now();
+        let address = |i: usize| IpAddr::V4(Ipv4Addr::from(0x0b00_0000 + i as u32));
+        for i in 0..MAX_TRACKED_CLIENTS * 3 {
+            let now = start + Duration::from_millis(i as u64);
+            assert!(limiter.check(None, address(i), now).is_ok());
+            assert!(limiter.buckets.lock().unwrap().len() <= MAX_TRACKED_CLIENTS);
+        }
+
+        let last = MAX_TRACKED_CLIENTS * 3 - 1;
+        let now = start + Duration::from_millis(last as u64);
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(!buckets.contains_key(&format!("ip:{}", address(0))));
+        assert!(buckets.contains_key(&format!("ip:{}", address(last))));
+        drop(buckets);
+
+        // A client seen recently keeps its partly used bucket
+        assert!(limiter.check(None, address(last), now).is_ok());
+        assert!(limiter.check(None, address(last), now).is_err());
+    }
+}
@@ -1,14 +1,21 @@
-use crossbeam_channel::{bounded, unbounded, select, Receiver, Sender, TryRecvError, TrySendError};
+use crossbeam_channel::{
+    bounded, unbounded, Receiver, RecvTimeoutError, Select, Sender, TryRecvError,
+    TrySendError,
+};
+use futures::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::sync::Notify;
 
 /// Message priority levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Priority {
     Low = 0,
+    #[default]
     Normal = 1,
     High = 2,
     Critical = 3,
@@ -43,11 +50,23 @@ impl<T> Message<T> {
     }
 }
 
+/// Channel capacity of each priority level (None for unbounded)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PriorityCapacities {
+    pub critical: Option<usize>,
+    pub high: Option<usize>,
+    pub normal: Option<usize>,
+    pub low: Option<usize>,
+}
+
 /// Configuration for the MPMC queue
 #[derive(Debug, Clone)]
 pub struct RusqConfig {
     /// Bounded channel capacity (None for unbounded)
     pub capacity: Option<usize>,
+    /// Per-priority capacities overriding `capacity`, so e.g. Low can shed load while
+    /// Critical stays effectively unbounded
+    pub capacities: Option<PriorityCapacities>,
     /// Enable priority queuing
     pub enable_priority: bool,
     /// Maximum retry attempts for failed messages
@@ -56,16 +75,36 @@ pub struct RusqConfig {
     pub consumer_timeout_ms: u64,
     /// Enable metrics collection
     pub enable_metrics: bool,
+    /// Keep messages that exhaust their retries in the dead letter queue; when disabled
+    /// they are dropped and counted in `messages_dropped`
+    pub enable_dead_letter: bool,
+}
+
+impl RusqConfig {
+    /// Capacity of the channel for `priority` (None for unbounded)
+    pub fn capacity_for(&self, priority: Priority) -> Option<usize> {
+        let Some(caps) = &self.capacities else {
+            return self.capacity;
+        };
+        match priority {
+            Priority::Critical => caps.critical,
+            Priority::High => caps.high,
+            Priority::Normal => caps.normal,
+            Priority::Low => caps.low,
+        }
+    }
 }
 
 impl Default for RusqConfig {
     fn default() -> Self {
         Self {
             capacity: Some(10000),
+            capacities: None,
             enable_priority: true,
             max_retries: 3,
             consumer_timeout_ms: 1000,
             enable_metrics: true,
+            enable_dead_letter: true,
         }
     }
 }
@@ -77,6 +116,10 @@ pub struct RusqMetrics {
     pub messages_received: AtomicU64,
     pub messages_failed: AtomicU64,
     pub messages_retried: AtomicU64,
+    pub messages_dropped: AtomicU64,
+    pub messages_acked: AtomicU64,
+    /// Messages handed out by a consumer and not yet acked or nacked
+    pub in_flight: AtomicU64,
     pub active_producers: AtomicU64,
     pub active_consumers: AtomicU64,
 }
@@ -102,20 +145,45 @@ impl RusqMetrics {
         self.messages_retried.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn increment_dropped(&self) {
+        self.messages_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_acked(&self) {
+        self.messages_acked.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn start_processing(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Saturates at zero, so settling a message twice can't wrap the gauge around
+    pub fn finish_processing(&self) {
+        let _ = self
+            .in_flight
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
     pub fn add_producer(&self) {
         self.active_producers.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Saturates at zero, so a stray extra call can't wrap the count around
     pub fn remove_producer(&self) {
-        self.active_producers.fetch_sub(1, Ordering::Relaxed);
+        let _ = self
+            .active_producers
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
     }
 
     pub fn add_consumer(&self) {
         self.active_consumers.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Saturates at zero, so a stray extra call can't wrap the count around
     pub fn remove_consumer(&self) {
-        self.active_consumers.fetch_sub(1, Ordering::Relaxed);
+        let _ = self
+            .active_consumers
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
     }
 
     pub fn snapshot(&self) -> MetricsSnapshot {
@@ -124,22 +192,42 @@ impl RusqMetrics {
             messages_received: self.messages_received.load(Ordering::Relaxed),
             messages_failed: self.messages_failed.load(Ordering::Relaxed),
             messages_retried: self.messages_retried.load(Ordering::Relaxed),
+            messages_dropped: self.messages_dropped.load(Ordering::Relaxed),
+            messages_acked: self.messages_acked.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
             active_producers: self.active_producers.load(Ordering::Relaxed),
             active_consumers: self.active_consumers.load(Ordering::Relaxed),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MetricsSnapshot {
     pub messages_sent: u64,
     pub messages_received: u64,
     pub messages_failed: u64,
     pub messages_retried: u64,
+    /// Messages discarded after exhausting their retries with the dead letter queue disabled
+    pub messages_dropped: u64,
+    pub messages_acked: u64,
+    /// Messages received and not yet acked or nacked, i.e. being processed right now
+    pub in_flight: u64,
     pub active_producers: u64,
     pub active_consumers: u64,
 }
 
+/// Number of messages currently waiting at each priority level
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QueueDepths {
+    pub critical: usize,
+    pub high: usize,
+    pub normal: usize,
+    pub low: usize,
+    pub dead_letter: usize,
+    /// Messages set aside by [`Consumer::recv_topic`] for consumers of other topics
+    pub parked: usize,
+}
+
 /// High-performance MPMC Message Queue
 pub struct MpmcQueue<T> {
     // Priority queues for different priority levels
@@ -159,8 +247,15 @@ pub struct MpmcQueue<T> {
     config: RusqConfig,
     metrics: Arc<RusqMetrics>,
     is_shutdown: Arc<AtomicBool>,
+    // Wakes async producers waiting for room once a message is received or on shutdown
+    space_available: Arc<Notify>,
+    parked: Parked<T>,
 }
 
+/// Messages taken off their channel by a topic-filtering consumer that wanted another
+/// topic, in arrival order. Every consumer of the queue shares them.
+type Parked<T> = Arc<Mutex<VecDeque<Message<T>>>>;
+
 impl<T> MpmcQueue<T>
 where
     T: Clone + Send + 'static,
@@ -175,10 +270,12 @@ where
             }
         };
 
-        let (critical_sender, critical_receiver) = create_channel(config.capacity);
-        let (high_sender, high_receiver) = create_channel(config.capacity);
-        let (normal_sender, normal_receiver) = create_channel(config.capacity);
-        let (low_sender, low_receiver) = create_channel(config.capacity);
+        let (critical_sender, critical_receiver) =
+            create_channel(config.capacity_for(Priority::Critical));
+        let (high_sender, high_receiver) = create_channel(config.capacity_for(Priority::High));
+        let (normal_sender, normal_receiver) =
+            create_channel(config.capacity_for(Priority::Normal));
+        let (low_sender, low_receiver) = create_channel(config.capacity_for(Priority::Low));
         let (dlq_sender, dlq_receiver) = create_channel(None); // DLQ is always unbounded
 
         Self {
@@ -195,14 +292,15 @@ where
             config,
             metrics: Arc::new(RusqMetrics::new()),
             is_shutdown: Arc::new(AtomicBool::new(false)),
+            space_available: Arc::new(Notify::new()),
+            parked: Arc::default(),
         }
     }
 
     /// Create a producer handle for sending messages
     pub fn producer(&self) -> Producer<T> {
-        if self.config.enable_metrics {
-            self.metrics.add_producer();
-        }
+        // Handles are counted even without `enable_metrics`, since every drop uncounts one
+        self.metrics.add_producer();
 
         Producer {
             critical_sender: self.critical_sender.clone(),
@@ -212,31 +310,40 @@ where
             config: self.config.clone(),
             metrics: self.metrics.clone(),
             is_shutdown: self.is_shutdown.clone(),
+            space_available: self.space_available.clone(),
         }
     }
 
     /// Create a consumer handle for receiving messages
     pub fn consumer(&self) -> Consumer<T> {
-        if self.config.enable_metrics {
-            self.metrics.add_consumer();
-        }
+        // Handles are counted even without `enable_metrics`, since every drop uncounts one
+        self.metrics.add_consumer();
 
         Consumer {
             critical_receiver: self.critical_receiver.clone(),
             high_receiver: self.high_receiver.clone(),
             normal_receiver: self.normal_receiver.clone(),
             low_receiver: self.low_receiver.clone(),
+            critical_sender: self.critical_sender.clone(),
+            high_sender: self.high_sender.clone(),
+            normal_sender: self.normal_sender.clone(),
+            low_sender: self.low_sender.clone(),
             dlq_sender: self.dlq_sender.clone(),
             config: self.config.clone(),
             metrics: self.metrics.clone(),
             is_shutdown: self.is_shutdown.clone(),
+            space_available: self.space_available.clone(),
+            parked: self.parked.clone(),
         }
     }
 
-    /// Get a handle to the dead letter queue
+    /// Get a handle to the dead letter queue. With `enable_dead_letter` off, receiving
+    /// from it fails with `RusqError::DeadLetterDisabled`.
     pub fn dead_letter_queue(&self) -> DeadLetterQueue<T> {
         DeadLetterQueue {
+            enabled: self.config.enable_dead_letter,
             dlq_receiver: self.dlq_receiver.clone(),
+            dlq_sender: self.dlq_sender.clone(),
             metrics: self.metrics.clone(),
         }
     }
@@ -246,9 +353,22 @@ where
         self.metrics.snapshot()
     }
 
+    /// Get the number of messages waiting in each priority queue
+    pub fn depths(&self) -> QueueDepths {
+        QueueDepths {
+            critical: self.critical_receiver.len(),
+            high: self.high_receiver.len(),
+            normal: self.normal_receiver.len(),
+            low: self.low_receiver.len(),
+            dead_letter: self.dlq_receiver.len(),
+            parked: self.parked.lock().unwrap().len(),
+        }
+    }
+
     /// Shutdown the queue gracefully
     pub fn shutdown(&self) {
         self.is_shutdown.store(true, Ordering::SeqCst);
+        self.space_available.notify_waiters();
     }
 
     /// Check if the queue is shutdown
@@ -266,6 +386,7 @@ pub struct Producer<T> {
     config: RusqConfig,
     metrics: Arc<RusqMetrics>,
     is_shutdown: Arc<AtomicBool>,
+    space_available: Arc<Notify>,
 }
 
 impl<T> Producer<T>
@@ -286,8 +407,17 @@ where
 
     /// Send a pre-constructed message
     pub fn send_message(&self, message: Message<T>) -> Result<(), RusqError> {
+        self.try_send(message).map_err(RusqError::from)
+    }
+
+    /// Send a pre-constructed message without blocking. On failure the message is handed
+    /// back inside the error so the caller can retry or buffer it.
+    pub fn try_send(&self, message: Message<T>) -> Result<(), SendError<T>> {
         if self.is_shutdown.load(Ordering::SeqCst) {
-            return Err(RusqError::QueueShutdown);
+            return Err(SendError {
+                error: RusqError::QueueShutdown,
+                message,
+            });
         }
 
         let sender = match message.priority {
@@ -297,16 +427,36 @@ where
             Priority::Low => &self.low_sender,
         };
 
-        match sender.try_send(message) {
-            Ok(_) => {
-                if self.config.enable_metrics {
-                    self.metrics.increment_sent();
-                }
-                Ok(())
+        sender.try_send(message)?;
+        if self.config.enable_metrics {
+            self.metrics.increment_sent();
+        }
+        Ok(())
+    }
+
+    /// Send messages without blocking until the first one is rejected.
+    /// Returns how many were accepted; each message is routed by its own priority.
+    pub fn try_send_batch<I>(&self, messages: I) -> usize
+    where
+        I: IntoIterator<Item = Message<T>>,
+    {
+        let mut accepted = 0;
+        for message in messages {
+            if self.send_message(message).is_err() {
+                break;
             }
-            Err(TrySendError::Full(_)) => Err(RusqError::QueueFull),
-            Err(TrySendError::Disconnected(_)) => Err(RusqError::QueueShutdown),
+            accepted += 1;
         }
+        accepted
+    }
+
+    /// Send every item without blocking, going on past rejected ones, e.g. when some
+    /// priorities are full and others aren't. Returns each item's outcome, in order.
+    pub fn send_batch(&self, items: Vec<(T, String, Priority)>) -> Vec<Result<(), RusqError>> {
+        items
+            .into_iter()
+            .map(|(payload, topic, priority)| self.send_with_priority(payload, topic, priority))
+            .collect()
     }
 
     /// Send a message with blocking behavior
@@ -338,6 +488,49 @@ where
             Err(_) => Err(RusqError::QueueShutdown),
         }
     }
+
+    /// Send a message, awaiting capacity without blocking the async runtime
+    pub async fn send_async(&self, payload: T, topic: String) -> Result<(), RusqError> {
+        let message = Message::new(payload, topic);
+        self.send_message_async(message).await
+    }
+
+    /// Send a pre-constructed message, awaiting capacity in its priority's channel without
+    /// blocking the async runtime. Fails once the queue is shut down.
+    pub async fn send_message_async(&self, mut message: Message<T>) -> Result<(), RusqError> {
+        loop {
+            // Register for a wakeup before retrying so a receive in between isn't missed
+            let space_available = self.space_available.notified();
+            tokio::pin!(space_available);
+            space_available.as_mut().enable();
+
+            match self.try_send(message) {
+                Ok(()) => return Ok(()),
+                Err(SendError {
+                    error: RusqError::QueueFull,
+                    message: rejected,
+                }) => message = rejected,
+                Err(err) => return Err(err.error),
+            }
+            space_available.await;
+        }
+    }
+}
+
+impl<T> Clone for Producer<T> {
+    fn clone(&self) -> Self {
+        self.metrics.add_producer();
+        Self {
+            critical_sender: self.critical_sender.clone(),
+            high_sender: self.high_sender.clone(),
+            normal_sender: self.normal_sender.clone(),
+            low_sender: self.low_sender.clone(),
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            is_shutdown: self.is_shutdown.clone(),
+            space_available: self.space_available.clone(),
+        }
+    }
 }
 
 impl<T> Drop for Producer<T> {
@@ -352,10 +545,17 @@ pub struct Consumer<T> {
     high_receiver: Receiver<Message<T>>,
     normal_receiver: Receiver<Message<T>>,
     low_receiver: Receiver<Message<T>>,
+    // Senders for requeueing nacked messages
+    critical_sender: Sender<Message<T>>,
+    high_sender: Sender<Message<T>>,
+    normal_sender: Sender<Message<T>>,
+    low_sender: Sender<Message<T>>,
     dlq_sender: Sender<Message<T>>,
     config: RusqConfig,
     metrics: Arc<RusqMetrics>,
     is_shutdown: Arc<AtomicBool>,
+    space_available: Arc<Notify>,
+    parked: Parked<T>,
 }
 
 impl<T> Consumer<T>
@@ -364,124 +564,172 @@ where
 {
     /// Receive a message with priority ordering (non-blocking)
     pub fn try_recv(&self) -> Result<Message<T>, RusqError> {
-        if self.is_shutdown.load(Ordering::SeqCst) {
-            return Err(RusqError::QueueShutdown);
-        }
+        self.try_recv_matching(|_| true)
+    }
 
-        // Check priority queues in order: Critical -> High -> Normal -> Low
-        match self.critical_receiver.try_recv() {
-            Ok(msg) => {
-                if self.config.enable_metrics {
-                    self.metrics.increment_received();
-                }
-                return Ok(msg);
+    /// Receive the highest-priority message whose topic is `topic`, waiting up to
+    /// `timeout`. Messages of other topics taken off the queue on the way are parked for
+    /// other consumers, which receive them before anything of lower priority.
+    pub fn recv_topic(&self, topic: &str, timeout: Duration) -> Result<Message<T>, RusqError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.try_recv_matching(|message| message.topic == topic) {
+                Err(RusqError::Empty) => {}
+                result => return result,
             }
-            Err(TryRecvError::Disconnected) => return Err(RusqError::QueueShutdown),
-            Err(TryRecvError::Empty) => {}
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RusqError::Timeout);
+            }
+            // Bounded so messages parked by other consumers are noticed too
+            self.wait_ready((deadline - now).min(Duration::from_millis(10)));
         }
+    }
 
-        match self.high_receiver.try_recv() {
-            Ok(msg) => {
-                if self.config.enable_metrics {
-                    self.metrics.increment_received();
-                }
-                return Ok(msg);
-            }
-            Err(TryRecvError::Disconnected) => return Err(RusqError::QueueShutdown),
-            Err(TryRecvError::Empty) => {}
+    // Take the highest-priority message `wanted` accepts, from the parked messages or the
+    // channels, parking the unwanted ones met in the channels along the way
+    fn try_recv_matching(
+        &self,
+        wanted: impl Fn(&Message<T>) -> bool,
+    ) -> Result<Message<T>, RusqError> {
+        if self.is_shutdown.load(Ordering::SeqCst) {
+            return Err(RusqError::QueueShutdown);
         }
 
-        match self.normal_receiver.try_recv() {
-            Ok(msg) => {
-                if self.config.enable_metrics {
-                    self.metrics.increment_received();
+        loop {
+            let parked = self.best_parked(&wanted);
+            match self.take_above(parked) {
+                Ok(message) if wanted(&message) => {
+                    self.on_received();
+                    return Ok(message);
                 }
-                return Ok(msg);
+                Ok(message) => {
+                    // Room was made in its channel all the same
+                    self.space_available.notify_waiters();
+                    self.parked.lock().unwrap().push_back(message);
+                }
+                Err(RusqError::Empty) if parked.is_some() => {
+                    if let Some(message) = self.unpark(&wanted) {
+                        self.on_received();
+                        return Ok(message);
+                    }
+                    // Another consumer took it first; look again
+                }
+                Err(e) => return Err(e),
             }
-            Err(TryRecvError::Disconnected) => return Err(RusqError::QueueShutdown),
-            Err(TryRecvError::Empty) => {}
         }
+    }
 
-        match self.low_receiver.try_recv() {
-            Ok(msg) => {
-                if self.config.enable_metrics {
-                    self.metrics.increment_received();
-                }
-                Ok(msg)
+    // Receive from the channels of priorities above `floor` (all when `None`), in order:
+    // Critical -> High -> Normal -> Low
+    fn take_above(&self, floor: Option<Priority>) -> Result<Message<T>, RusqError> {
+        let channels = [
+            (Priority::Critical, &self.critical_receiver),
+            (Priority::High, &self.high_receiver),
+            (Priority::Normal, &self.normal_receiver),
+            (Priority::Low, &self.low_receiver),
+        ];
+        for (priority, receiver) in channels {
+            if floor.is_some_and(|floor| priority <= floor) {
+                break;
+            }
+            match receiver.try_recv() {
+                Ok(message) => return Ok(message),
+                Err(TryRecvError::Disconnected) => return Err(RusqError::QueueShutdown),
+                Err(TryRecvError::Empty) => {}
             }
-            Err(TryRecvError::Disconnected) => Err(RusqError::QueueShutdown),
-            Err(TryRecvError::Empty) => Err(RusqError::Empty),
         }
+        Err(RusqError::Empty)
     }
 
-    /// Receive a message with priority ordering (blocking with timeout)
-    pub fn recv_timeout(&self, timeout: Duration) -> Result<Message<T>, RusqError> {
-        if self.is_shutdown.load(Ordering::SeqCst) {
-            return Err(RusqError::QueueShutdown);
-        }
+    // Priority of the best parked message `wanted` accepts
+    fn best_parked(&self, wanted: impl Fn(&Message<T>) -> bool) -> Option<Priority> {
+        let parked = self.parked.lock().unwrap();
+        parked
+            .iter()
+            .filter(|message| wanted(message))
+            .map(|message| message.priority)
+            .max()
+    }
 
-        let start_time = Instant::now();
+    // Remove the earliest parked message of the best priority `wanted` accepts
+    fn unpark(&self, wanted: impl Fn(&Message<T>) -> bool) -> Option<Message<T>> {
+        let mut parked = self.parked.lock().unwrap();
+        let best = parked
+            .iter()
+            .filter(|message| wanted(message))
+            .map(|message| message.priority)
+            .max()?;
+        let index = parked
+            .iter()
+            .position(|message| message.priority == best && wanted(message))?;
+        parked.remove(index)
+    }
 
+    /// Receive a message with priority ordering (blocking with timeout). Parked messages
+    /// are merged with the channels, so none is handed out ahead of a higher priority.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Message<T>, RusqError> {
+        let deadline = Instant::now() + timeout;
         loop {
-            if self.is_shutdown.load(Ordering::SeqCst) {
-                return Err(RusqError::QueueShutdown);
+            match self.try_recv() {
+                Err(RusqError::Empty) => {}
+                result => return result,
             }
-
-            if start_time.elapsed() >= timeout {
+            let now = Instant::now();
+            if now >= deadline {
                 return Err(RusqError::Timeout);
             }
+            // Bounded so a shutdown and messages parked by other consumers are noticed too
+            self.wait_ready((deadline - now).min(Duration::from_millis(10)));
+        }
+    }
 
-            // Use select! to efficiently wait on multiple receivers
-            select! {
-                recv(self.critical_receiver) -> msg => {
-                    match msg {
-                        Ok(message) => {
-                            if self.config.enable_metrics {
-                                self.metrics.increment_received();
-                            }
-                            return Ok(message);
-                        }
-                        Err(_) => return Err(RusqError::QueueShutdown),
-                    }
-                }
-                recv(self.high_receiver) -> msg => {
-                    match msg {
-                        Ok(message) => {
-                            if self.config.enable_metrics {
-                                self.metrics.increment_received();
-                            }
-                            return Ok(message);
-                        }
-                        Err(_) => return Err(RusqError::QueueShutdown),
-                    }
-                }
-                recv(self.normal_receiver) -> msg => {
-                    match msg {
-                        Ok(message) => {
-                            if self.config.enable_metrics {
-                                self.metrics.increment_received();
-                            }
-                            return Ok(message);
-                        }
-                        Err(_) => return Err(RusqError::QueueShutdown),
-                    }
-                }
-                recv(self.low_receiver) -> msg => {
-                    match msg {
-                        Ok(message) => {
-                            if self.config.enable_metrics {
-                                self.metrics.increment_received();
-                            }
-                            return Ok(message);
-                        }
-                        Err(_) => return Err(RusqError::QueueShutdown),
-                    }
-                }
-                default(Duration::from_millis(10)) => {
-                    // Continue the loop to check timeout
-                }
-            }
+    // Count a received message and wake producers awaiting capacity
+    fn on_received(&self) {
+        if self.config.enable_metrics {
+            self.metrics.increment_received();
+            self.metrics.start_processing();
         }
+        self.space_available.notify_waiters();
+    }
+
+    /// Receive up to `max` messages without blocking, all of a higher priority before any
+    /// of a lower one; fewer when the queue runs out
+    pub fn try_recv_batch(&self, max: usize) -> Vec<Message<T>> {
+        let mut batch = Vec::with_capacity(max.min(self.len_hint()));
+        batch.extend(self.try_iter().take(max));
+        batch
+    }
+
+    // Messages currently waiting, for sizing buffers
+    fn len_hint(&self) -> usize {
+        self.critical_receiver.len()
+            + self.high_receiver.len()
+            + self.normal_receiver.len()
+            + self.low_receiver.len()
+            + self.parked.lock().unwrap().len()
+    }
+
+    /// Iterate over messages in priority order until the queue is empty
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { consumer: self }
+    }
+
+    /// Iterate over messages in priority order, blocking while the queue is empty, until
+    /// the queue is shut down
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { consumer: self }
+    }
+
+    // Block until any priority channel has a message or `timeout` elapses, without
+    // receiving, so the caller can then take the highest-priority one with `try_recv`
+    fn wait_ready(&self, timeout: Duration) {
+        let mut sel = Select::new();
+        sel.recv(&self.critical_receiver);
+        sel.recv(&self.high_receiver);
+        sel.recv(&self.normal_receiver);
+        sel.recv(&self.low_receiver);
+        let _ = sel.ready_timeout(timeout);
     }
 
     /// Receive a message with priority ordering (blocking)
@@ -489,37 +737,79 @@ where
         self.recv_timeout(Duration::from_millis(self.config.consumer_timeout_ms))
     }
 
-    /// Mark a message as failed and potentially send to DLQ
-    pub fn nack(&self, mut message: Message<T>) -> Result<(), RusqError> {
+    /// Mark a received message as successfully processed. Taking the message keeps it from
+    /// being settled twice.
+    pub fn ack(&self, _message: Message<T>) {
+        if self.config.enable_metrics {
+            self.metrics.increment_acked();
+            self.metrics.finish_processing();
+        }
+    }
+
+    /// Mark a message as failed: it is requeued at its priority until it has been retried
+    /// `max_retries` times, then sent to the DLQ. If the queue it goes to is full or shut
+    /// down, the message is handed back inside the error rather than lost.
+    pub fn nack(&self, mut message: Message<T>) -> Result<(), SendError<T>> {
         message.retry_count += 1;
 
         if self.config.enable_metrics {
             self.metrics.increment_failed();
+            self.metrics.finish_processing();
         }
 
         if message.retry_count > self.config.max_retries {
-            // Send to dead letter queue
-            match self.dlq_sender.try_send(message) {
-                Ok(_) => Ok(()),
-                Err(TrySendError::Full(_)) => Err(RusqError::QueueFull),
-                Err(TrySendError::Disconnected(_)) => Err(RusqError::QueueShutdown),
+            if !self.config.enable_dead_letter {
+                if self.config.enable_metrics {
+                    self.metrics.increment_dropped();
+                }
+                return Ok(());
             }
+            // Send to dead letter queue
+            self.dlq_sender.try_send(message)?;
+            Ok(())
         } else {
             if self.config.enable_metrics {
                 self.metrics.increment_retried();
             }
 
+            if self.is_shutdown.load(Ordering::SeqCst) {
+                return Err(SendError {
+                    error: RusqError::QueueShutdown,
+                    message,
+                });
+            }
+
             // Retry by sending back to the appropriate queue
-            let _sender = match message.priority {
-                Priority::Critical => &self.critical_receiver,
-                Priority::High => &self.high_receiver,
-                Priority::Normal => &self.normal_receiver,
-                Priority::Low => &self.low_receiver,
+            let sender = match message.priority {
+                Priority::Critical => &self.critical_sender,
+                Priority::High => &self.high_sender,
+                Priority::Normal => &self.normal_sender,
+                Priority::Low => &self.low_sender,
             };
+            sender.try_send(message)?;
+            Ok(())
+        }
+    }
+}
 
-            // This is a bit tricky - we need to get the sender from the receiver
-            // In a real implementation, you might want to refactor this
-            Err(RusqError::RetryRequired)
+impl<T> Clone for Consumer<T> {
+    fn clone(&self) -> Self {
+        self.metrics.add_consumer();
+        Self {
+            critical_receiver: self.critical_receiver.clone(),
+            high_receiver: self.high_receiver.clone(),
+            normal_receiver: self.normal_receiver.clone(),
+            low_receiver: self.low_receiver.clone(),
+            critical_sender: self.critical_sender.clone(),
+            high_sender: self.high_sender.clone(),
+            normal_sender: self.normal_sender.clone(),
+            low_sender: self.low_sender.clone(),
+            dlq_sender: self.dlq_sender.clone(),
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            is_shutdown: self.is_shutdown.clone(),
+            space_available: self.space_available.clone(),
+            parked: self.parked.clone(),
         }
     }
 }
@@ -530,9 +820,83 @@ impl<T> Drop for Consumer<T> {
     }
 }
 
+/// Non-blocking iterator returned by [`Consumer::try_iter`]
+pub struct TryIter<'a, T> {
+    consumer: &'a Consumer<T>,
+}
+
+impl<T> Iterator for TryIter<'_, T>
+where
+    T: Clone + Send,
+{
+    type Item = Message<T>;
+
+    fn next(&mut self) -> Option<Message<T>> {
+        self.consumer.try_recv().ok()
+    }
+}
+
+/// Blocking iterator returned by [`Consumer::iter`]
+pub struct Iter<'a, T> {
+    consumer: &'a Consumer<T>,
+}
+
+impl<T> Iterator for Iter<'_, T>
+where
+    T: Clone + Send,
+{
+    type Item = Message<T>;
+
+    fn next(&mut self) -> Option<Message<T>> {
+        loop {
+            match self.consumer.try_recv() {
+                Ok(message) => return Some(message),
+                Err(RusqError::Empty) => {}
+                Err(_) => return None,
+            }
+            // Wake periodically to notice a shutdown, which doesn't close the channels
+            self.consumer.wait_ready(Duration::from_millis(10));
+        }
+    }
+}
+
+/// Longest a stream from [`Consumer::into_stream`] waits on the blocking pool at a time
+const STREAM_WAIT_SLICE: Duration = Duration::from_millis(50);
+
+impl<T> Consumer<T>
+where
+    T: Clone + Send + 'static,
+{
+    /// Turn the consumer into a stream of messages in priority order, for async code. The
+    /// waiting between messages happens on Tokio's blocking thread pool, so the runtime's
+    /// workers stay free; the stream must therefore be polled within a Tokio runtime. It
+    /// ends once the queue is shut down.
+    pub fn into_stream(self) -> impl Stream<Item = Message<T>> {
+        futures::stream::unfold(self, |mut consumer| async move {
+            loop {
+                match consumer.try_recv() {
+                    Ok(message) => return Some((message, consumer)),
+                    Err(RusqError::Empty) => {}
+                    Err(_) => return None,
+                }
+                // In short slices, so a dropped stream doesn't hold a blocking thread for long
+                consumer = tokio::task::spawn_blocking(move || {
+                    consumer.wait_ready(STREAM_WAIT_SLICE);
+                    consumer
+                })
+                .await
+                .ok()?;
+            }
+        })
+    }
+}
+
 /// Handle for accessing the dead letter queue
 pub struct DeadLetterQueue<T> {
+    enabled: bool,
     dlq_receiver: Receiver<Message<T>>,
+    // Takes back messages whose replay failed
+    dlq_sender: Sender<Message<T>>,
     #[allow(dead_code)]
     metrics: Arc<RusqMetrics>,
 }
@@ -540,15 +904,17 @@ pub struct DeadLetterQueue<T> {
 impl<T> DeadLetterQueue<T> {
     /// Get a failed message from the dead letter queue
     pub fn try_recv(&self) -> Result<Message<T>, RusqError> {
-        match self.dlq_receiver.try_recv() {
-            Ok(msg) => Ok(msg),
-            Err(TryRecvError::Empty) => Err(RusqError::Empty),
-            Err(TryRecvError::Disconnected) => Err(RusqError::QueueShutdown),
+        if !self.enabled {
+            return Err(RusqError::DeadLetterDisabled);
         }
+        Ok(self.dlq_receiver.try_recv()?)
     }
 
     /// Get a failed message from the dead letter queue with timeout
     pub fn recv_timeout(&self, timeout: Duration) -> Result<Message<T>, RusqError> {
+        if !self.enabled {
+            return Err(RusqError::DeadLetterDisabled);
+        }
         match self.dlq_receiver.recv_timeout(timeout) {
             Ok(msg) => Ok(msg),
             Err(_) => Err(RusqError::Timeout),
@@ -556,6 +922,54 @@ impl<T> DeadLetterQueue<T> {
     }
 }
 
+impl<T> DeadLetterQueue<T>
+where
+    T: Clone + Send,
+{
+    /// Send the oldest failed message back to the main queue through `producer`, at its
+    /// original priority and with its retry count reset. A message the producer rejects
+    /// stays in the dead letter queue. Fails with `RusqError::Empty` when there is nothing
+    /// to replay.
+    pub fn replay(&self, producer: &Producer<T>) -> Result<(), RusqError> {
+        let mut message = self.try_recv()?;
+        message.retry_count = 0;
+        producer.try_send(message).map_err(|rejected| {
+            // Unbounded, so it always fits
+            let _ = self.dlq_sender.send(rejected.message);
+            rejected.error
+        })
+    }
+
+    /// Replay every message that was in the dead letter queue when called, stopping at the
+    /// first one `producer` rejects
+    pub fn replay_all(&self, producer: &Producer<T>) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        for _ in 0..self.dlq_receiver.len() {
+            match self.replay(producer) {
+                Ok(()) => report.replayed += 1,
+                Err(RusqError::Empty) => break,
+                Err(e) => {
+                    report.error = Some(e);
+                    break;
+                }
+            }
+        }
+        report.remaining = self.dlq_receiver.len();
+        report
+    }
+}
+
+/// Outcome of [`DeadLetterQueue::replay_all`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Messages sent back to the main queue
+    pub replayed: usize,
+    /// Messages still in the dead letter queue afterwards
+    pub remaining: usize,
+    /// Why replaying stopped early, if it did
+    pub error: Option<RusqError>,
+}
+
 /// Error types for the MPMC queue
 #[derive(Debug, Clone, PartialEq)]
 pub enum RusqError {
@@ -564,6 +978,7 @@ pub enum RusqError {
     Empty,
     Timeout,
     RetryRequired,
+    DeadLetterDisabled,
 }
 
 impl std::fmt::Display for RusqError {
@@ -574,12 +989,83 @@ impl std::fmt::Display for RusqError {
             RusqError::Empty => write!(f, "Queue is empty"),
             RusqError::Timeout => write!(f, "Operation timed out"),
             RusqError::RetryRequired => write!(f, "Message retry required"),
+            RusqError::DeadLetterDisabled => write!(f, "Dead letter queue is disabled"),
         }
     }
 }
 
 impl std::error::Error for RusqError {}
 
+impl From<TryRecvError> for RusqError {
+    fn from(err: TryRecvError) -> Self {
+        match err {
+            TryRecvError::Empty => RusqError::Empty,
+            TryRecvError::Disconnected => RusqError::QueueShutdown,
+        }
+    }
+}
+
+impl From<RecvTimeoutError> for RusqError {
+    fn from(err: RecvTimeoutError) -> Self {
+        match err {
+            RecvTimeoutError::Timeout => RusqError::Timeout,
+            RecvTimeoutError::Disconnected => RusqError::QueueShutdown,
+        }
+    }
+}
+
+/// A rejected send that carries the message back to the caller, like crossbeam's
+/// `TrySendError`, so backpressure can be handled without losing it.
+#[derive(Debug, Clone)]
+pub struct SendError<T> {
+    pub error: RusqError,
+    pub message: Message<T>,
+}
+
+impl<T> SendError<T> {
+    /// Take back the message that could not be sent
+    pub fn into_message(self) -> Message<T> {
+        self.message
+    }
+}
+
+impl<T> std::fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (message {} on topic '{}')",
+            self.error, self.message.id, self.message.topic
+        )
+    }
+}
+
+impl<T: Debug> std::error::Error for SendError<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<T> From<SendError<T>> for RusqError {
+    fn from(err: SendError<T>) -> Self {
+        err.error
+    }
+}
+
+impl<T> From<TrySendError<Message<T>>> for SendError<T> {
+    fn from(err: TrySendError<Message<T>>) -> Self {
+        match err {
+            TrySendError::Full(message) => SendError {
+                error: RusqError::QueueFull,
+                message,
+            },
+            TrySendError::Disconnected(message) => SendError {
+                error: RusqError::QueueShutdown,
+                message,
+            },
+        }
+    }
+}
+
 // Utility functions
 fn generate_message_id() -> u64 {
     use std::sync::atomic::{AtomicU64, Ordering};
@@ -638,6 +1124,110 @@ mod tests {
         assert_eq!(consumer.try_recv().unwrap().payload, "Low");
     }
 
+    fn send_mixed_priorities(producer: &Producer<String>) {
+        for (payload, priority) in [
+            ("Low", Priority::Low),
+            ("High", Priority::High),
+            ("Normal", Priority::Normal),
+            ("Critical", Priority::Critical),
+            ("High 2", Priority::High),
+        ] {
+            producer.send_with_priority(payload.to_string(), "test".to_string(), priority).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_try_iter_drains_in_priority_order() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        send_mixed_priorities(&producer);
+
+        let payloads: Vec<String> = consumer.try_iter().map(|m| m.payload).collect();
+        assert_eq!(payloads, ["Critical", "High", "High 2", "Normal", "Low"]);
+        assert!(consumer.try_iter().next().is_none());
+    }
+
+    #[test]
+    fn test_try_recv_batch_drains_in_priority_order() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        let priorities = [Priority::Low, Priority::Critical, Priority::Normal, Priority::High];
+        for i in 0..100 {
+            producer.send_with_priority(i, "test".to_string(), priorities[i % 4]).unwrap();
+        }
+
+        let first = consumer.try_recv_batch(50);
+        let second = consumer.try_recv_batch(50);
+        assert_eq!(first.len(), 50);
+        assert_eq!(second.len(), 50);
+        let received: Vec<Message<usize>> = first.into_iter().chain(second).collect();
+        // Highest priority first, and in sending order within a priority
+        let mut expected: Vec<(Priority, usize)> =
+            (0..100).map(|i| (priorities[i % 4], i)).collect();
+        expected.sort_by_key(|&(priority, i)| (std::cmp::Reverse(priority), i));
+        let received: Vec<(Priority, usize)> =
+            received.iter().map(|m| (m.priority, m.payload)).collect();
+        assert_eq!(received, expected);
+
+        assert!(consumer.try_recv_batch(50).is_empty());
+        assert_eq!(queue.metrics().messages_received, 100);
+    }
+
+    #[test]
+    fn test_iter_blocks_until_shutdown() {
+        let queue = Arc::new(MpmcQueue::new(RusqConfig::default()));
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        send_mixed_priorities(&producer);
+
+        let late = thread::spawn({
+            let queue = queue.clone();
+            move || {
+                thread::sleep(Duration::from_millis(50));
+                producer.send("Late".to_string(), "test".to_string()).unwrap();
+                thread::sleep(Duration::from_millis(50));
+                queue.shutdown();
+            }
+        });
+
+        let payloads: Vec<String> = consumer.iter().map(|m| m.payload).collect();
+        assert_eq!(payloads, ["Critical", "High", "High 2", "Normal", "Low", "Late"]);
+        late.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_messages_until_shutdown() {
+        use futures::StreamExt;
+
+        let queue = Arc::new(MpmcQueue::new(RusqConfig::default()));
+        let producer = queue.producer();
+        send_mixed_priorities(&producer);
+        let mut stream = Box::pin(queue.consumer().into_stream());
+
+        let mut payloads = Vec::new();
+        for _ in 0..5 {
+            payloads.push(stream.next().await.unwrap().payload);
+        }
+        assert_eq!(payloads, ["Critical", "High", "High 2", "Normal", "Low"]);
+
+        // This single-threaded runtime can only run the sender while the stream waits if
+        // the waiting doesn't block it
+        let sender = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                producer.send("Late".to_string(), "test".to_string()).unwrap();
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                queue.shutdown();
+            }
+        });
+        assert_eq!(stream.next().await.unwrap().payload, "Late");
+        assert!(stream.next().await.is_none());
+        sender.await.unwrap();
+    }
+
     #[test]
     fn test_mpmc_concurrency() {
         let config = RusqConfig::default();
@@ -726,6 +1316,238 @@ mod tests {
         assert_eq!(metrics.active_consumers, 1);
     }
 
+    #[test]
+    fn test_active_handles_return_to_zero() {
+        for enable_metrics in [true, false] {
+            let config = RusqConfig {
+                enable_metrics,
+                ..Default::default()
+            };
+            let queue: MpmcQueue<u32> = MpmcQueue::new(config);
+            {
+                let producers: Vec<_> = (0..50).map(|_| queue.producer()).collect();
+                let consumers: Vec<_> = (0..50).map(|_| queue.consumer()).collect();
+                let cloned: Vec<_> = producers.iter().map(Producer::clone).collect();
+                let _consumer_clone = consumers[0].clone();
+
+                let metrics = queue.metrics();
+                assert_eq!(metrics.active_producers, 100);
+                assert_eq!(metrics.active_consumers, 51);
+                drop(cloned);
+                assert_eq!(queue.metrics().active_producers, 50);
+            }
+            let metrics = queue.metrics();
+            assert_eq!(metrics.active_producers, 0);
+            assert_eq!(metrics.active_consumers, 0);
+        }
+
+        let metrics = RusqMetrics::new();
+        metrics.remove_producer();
+        metrics.remove_consumer();
+        assert_eq!(metrics.snapshot().active_producers, 0);
+        assert_eq!(metrics.snapshot().active_consumers, 0);
+    }
+
+    #[test]
+    fn test_try_send_batch_stops_when_full() {
+        let config = RusqConfig {
+            capacity: Some(3),
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+
+        let batch = (0..5).map(|i| Message::new(i, "batch".to_string()));
+        assert_eq!(producer.try_send_batch(batch), 3);
+        assert_eq!(queue.metrics().messages_sent, 3);
+
+        let mut received = Vec::new();
+        while let Ok(msg) = consumer.try_recv() {
+            received.push(msg.payload);
+        }
+        assert_eq!(received, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_send_batch_reports_each_item() {
+        let config = RusqConfig {
+            capacities: Some(PriorityCapacities {
+                critical: Some(2),
+                high: Some(2),
+                normal: Some(2),
+                low: Some(1),
+            }),
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let item = |i, priority| (i, "batch".to_string(), priority);
+
+        let results = producer.send_batch(vec![
+            item(1, Priority::Low),
+            item(2, Priority::Low),
+            item(3, Priority::High),
+            item(4, Priority::Low),
+            item(5, Priority::High),
+        ]);
+        assert_eq!(
+            results,
+            vec![Ok(()), Err(RusqError::QueueFull), Ok(()), Err(RusqError::QueueFull), Ok(())]
+        );
+        assert_eq!(queue.metrics().messages_sent, 3);
+        let received: Vec<i32> = queue.consumer().try_iter().map(|m| m.payload).collect();
+        assert_eq!(received, [3, 5, 1]);
+    }
+
+    #[test]
+    fn test_per_priority_capacities() {
+        let config = RusqConfig {
+            capacities: Some(PriorityCapacities {
+                critical: None,
+                high: Some(10),
+                normal: Some(10),
+                low: Some(2),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(config.capacity_for(Priority::Low), Some(2));
+        assert_eq!(config.capacity_for(Priority::Critical), None);
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+
+        let low = |i| Message::new(i, "flood".to_string()).with_priority(Priority::Low);
+        producer.try_send(low(1)).unwrap();
+        producer.try_send(low(2)).unwrap();
+        assert_eq!(producer.try_send(low(3)).unwrap_err().error, RusqError::QueueFull);
+
+        // Critical is unaffected by the full Low channel
+        for i in 0..100 {
+            let msg = Message::new(i, "urgent".to_string()).with_priority(Priority::Critical);
+            producer.try_send(msg).unwrap();
+        }
+        assert_eq!(queue.depths().low, 2);
+        assert_eq!(queue.depths().critical, 100);
+    }
+
+    #[test]
+    fn test_try_send_batch_routes_by_priority() {
+        let config = RusqConfig {
+            capacity: Some(1),
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+
+        let batch = vec![
+            Message::new("low", "batch".to_string()).with_priority(Priority::Low),
+            Message::new("high", "batch".to_string()).with_priority(Priority::High),
+            Message::new("low again", "batch".to_string()).with_priority(Priority::Low),
+        ];
+        assert_eq!(producer.try_send_batch(batch), 2);
+
+        assert_eq!(consumer.try_recv().unwrap().payload, "high");
+        assert_eq!(consumer.try_recv().unwrap().payload, "low");
+        assert_eq!(consumer.try_recv().unwrap_err(), RusqError::Empty);
+    }
+
+    #[test]
+    fn test_queue_depths() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+
+        producer.send_with_priority(1, "t".to_string(), Priority::High).unwrap();
+        producer.send_with_priority(2, "t".to_string(), Priority::Low).unwrap();
+        producer.send_with_priority(3, "t".to_string(), Priority::Low).unwrap();
+        consumer.try_recv().unwrap();
+
+        let depths = queue.depths();
+        assert_eq!(depths.high, 0);
+        assert_eq!(depths.low, 2);
+        assert_eq!(depths.critical + depths.normal + depths.dead_letter, 0);
+    }
+
+    #[test]
+    fn test_consumers_filter_interleaved_topics() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        for i in 0..10 {
+            let topic = if i % 2 == 0 { "even" } else { "odd" };
+            producer.send(i, topic.to_string()).unwrap();
+        }
+
+        let receivers: Vec<_> = ["even", "odd"]
+            .into_iter()
+            .map(|topic| {
+                let consumer = queue.consumer();
+                thread::spawn(move || {
+                    let mut received = Vec::new();
+                    while let Ok(message) = consumer.recv_topic(topic, Duration::from_millis(200)) {
+                        assert_eq!(message.topic, topic);
+                        received.push(message.payload);
+                        consumer.ack(message);
+                    }
+                    received
+                })
+            })
+            .collect();
+        let received: Vec<Vec<i32>> = receivers.into_iter().map(|h| h.join().unwrap()).collect();
+
+        // Each consumer gets all of its topic, in order, whoever pulled it off the queue
+        assert_eq!(received[0], [0, 2, 4, 6, 8]);
+        assert_eq!(received[1], [1, 3, 5, 7, 9]);
+        assert_eq!(queue.depths(), QueueDepths::default());
+        assert_eq!(queue.metrics().messages_received, 10);
+    }
+
+    #[test]
+    fn test_parked_messages_keep_their_priority() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        producer.send_with_priority("high a", "a".to_string(), Priority::High).unwrap();
+        producer.send_with_priority("normal a", "a".to_string(), Priority::Normal).unwrap();
+        producer.send_with_priority("low b", "b".to_string(), Priority::Low).unwrap();
+        producer.send_with_priority("critical c", "c".to_string(), Priority::Critical).unwrap();
+
+        let message = consumer.recv_topic("b", Duration::from_millis(50)).unwrap();
+        assert_eq!(message.payload, "low b");
+        assert_eq!(queue.depths().parked, 3);
+        assert_eq!(
+            consumer.recv_topic("b", Duration::from_millis(20)).unwrap_err(),
+            RusqError::Timeout
+        );
+
+        // Plain receives see the parked messages too, still in priority order
+        producer.send_with_priority("high d", "d".to_string(), Priority::High).unwrap();
+        let payloads: Vec<&str> = consumer.try_iter().map(|m| m.payload).collect();
+        assert_eq!(payloads, ["critical c", "high a", "high d", "normal a"]);
+    }
+
+    #[test]
+    fn test_recv_timeout_puts_parked_messages_in_priority_order() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        producer.send_with_priority("low a", "a".to_string(), Priority::Low).unwrap();
+        producer.send_with_priority("low b", "b".to_string(), Priority::Low).unwrap();
+        let message = consumer.recv_topic("b", Duration::from_millis(50)).unwrap();
+        assert_eq!(message.payload, "low b");
+        assert_eq!(queue.depths().parked, 1);
+
+        // The Critical message still in its channel beats the parked Low one
+        producer.send_with_priority("critical c", "c".to_string(), Priority::Critical).unwrap();
+        let first = consumer.recv_timeout(Duration::from_millis(50)).unwrap();
+        assert_eq!(first.payload, "critical c");
+        assert_eq!(consumer.recv().unwrap().payload, "low a");
+        assert_eq!(
+            consumer.recv_timeout(Duration::from_millis(20)).unwrap_err(),
+            RusqError::Timeout
+        );
+    }
+
     #[test]
     fn test_message_creation() {
         let msg = Message::new("test payload".to_string(), "test_topic".to_string());
@@ -760,6 +1582,55 @@ mod tests {
         assert_eq!(config.max_retries, 3);
         assert_eq!(config.consumer_timeout_ms, 1000);
         assert!(config.enable_metrics);
+        assert!(config.enable_dead_letter);
+    }
+
+    #[tokio::test]
+    async fn test_send_async_waits_for_capacity() {
+        let config = RusqConfig {
+            capacity: Some(1),
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+
+        producer.send_async(1, "async".to_string()).await.unwrap();
+        let pending = tokio::spawn(async move {
+            producer.send_async(2, "async".to_string()).await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!pending.is_finished());
+
+        assert_eq!(consumer.try_recv().unwrap().payload, 1);
+        tokio::time::timeout(Duration::from_secs(1), pending)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(consumer.try_recv().unwrap().payload, 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_async_fails_on_shutdown() {
+        let config = RusqConfig {
+            capacity: Some(1),
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        producer.send_async(1, "async".to_string()).await.unwrap();
+
+        let pending = tokio::spawn(async move {
+            producer.send_async(2, "async".to_string()).await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        queue.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(1), pending)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result, Err(RusqError::QueueShutdown));
     }
 
     #[test]
@@ -791,6 +1662,228 @@ mod tests {
         assert_eq!(RusqError::Empty.to_string(), "Queue is empty");
         assert_eq!(RusqError::Timeout.to_string(), "Operation timed out");
         assert_eq!(RusqError::RetryRequired.to_string(), "Message retry required");
+        assert_eq!(
+            RusqError::DeadLetterDisabled.to_string(),
+            "Dead letter queue is disabled"
+        );
+    }
+
+    #[test]
+    fn test_exhausted_message_goes_to_dead_letter_queue() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let consumer = queue.consumer();
+        let mut message = Message::new("job", "topic".to_string());
+        message.retry_count = 3;
+
+        consumer.nack(message).unwrap();
+        let dead = queue.dead_letter_queue().try_recv().unwrap();
+        assert_eq!(dead.payload, "job");
+        assert_eq!(queue.metrics().messages_dropped, 0);
+    }
+
+    #[test]
+    fn test_dead_letters_can_be_replayed() {
+        let config = RusqConfig {
+            max_retries: 0,
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        let dlq = queue.dead_letter_queue();
+        producer.send_with_priority("job", "t".to_string(), Priority::High).unwrap();
+        consumer.nack(consumer.try_recv().unwrap()).unwrap();
+        assert_eq!(consumer.try_recv().unwrap_err(), RusqError::Empty);
+
+        // Fixed the problem; now it goes through
+        dlq.replay(&producer).unwrap();
+        let message = consumer.try_recv().unwrap();
+        assert_eq!(message.payload, "job");
+        assert_eq!(message.priority, Priority::High);
+        assert_eq!(message.retry_count, 0);
+        consumer.ack(message);
+        assert_eq!(dlq.replay(&producer).unwrap_err(), RusqError::Empty);
+    }
+
+    #[test]
+    fn test_replay_all_stops_when_the_queue_is_full() {
+        let config = RusqConfig {
+            capacity: Some(2),
+            max_retries: 0,
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        let dlq = queue.dead_letter_queue();
+        for i in 0..3 {
+            consumer.nack(Message::new(i, "t".to_string())).unwrap();
+        }
+
+        let report = dlq.replay_all(&producer);
+        assert_eq!(
+            report,
+            ReplayReport {
+                replayed: 2,
+                remaining: 1,
+                error: Some(RusqError::QueueFull),
+            }
+        );
+        let replayed: Vec<i32> = consumer.try_iter().map(|m| m.payload).collect();
+        assert_eq!(replayed, [0, 1]);
+
+        let report = dlq.replay_all(&producer);
+        assert_eq!((report.replayed, report.remaining, report.error), (1, 0, None));
+        assert_eq!(consumer.try_recv().unwrap().payload, 2);
+    }
+
+    #[test]
+    fn test_nacked_message_is_requeued_until_retries_run_out() {
+        let config = RusqConfig {
+            max_retries: 3,
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        producer.send("job", "topic".to_string()).unwrap();
+
+        let mut message = consumer.try_recv().unwrap();
+        for retry in 1..=3 {
+            consumer.nack(message).unwrap();
+            message = consumer.try_recv().unwrap();
+            assert_eq!(message.retry_count, retry);
+            assert_eq!(message.payload, "job");
+        }
+        assert_eq!(queue.metrics().messages_retried, 3);
+
+        // The fourth failure exhausts the retries
+        consumer.nack(message).unwrap();
+        assert_eq!(consumer.try_recv().unwrap_err(), RusqError::Empty);
+        let dead = queue.dead_letter_queue().try_recv().unwrap();
+        assert_eq!((dead.payload, dead.retry_count), ("job", 4));
+    }
+
+    #[test]
+    fn test_nack_hands_the_message_back_when_it_cannot_be_requeued() {
+        let config = RusqConfig {
+            capacity: Some(1),
+            max_retries: 1,
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        producer.send("first", "topic".to_string()).unwrap();
+        let first = consumer.try_recv().unwrap();
+        producer.send("second", "topic".to_string()).unwrap();
+
+        // No room to requeue it
+        let err = consumer.nack(first).unwrap_err();
+        assert_eq!(err.error, RusqError::QueueFull);
+        let mut first = err.into_message();
+        assert_eq!((first.payload, first.retry_count), ("first", 1));
+
+        // Nor, with a retry left, once the queue has shut down
+        first.retry_count = 0;
+        queue.shutdown();
+        let err = consumer.nack(first).unwrap_err();
+        assert_eq!(err.error, RusqError::QueueShutdown);
+        assert_eq!(err.into_message().payload, "first");
+    }
+
+    #[test]
+    fn test_in_flight_tracks_messages_until_settled() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        let consumer = queue.consumer();
+        for n in 0..3 {
+            producer.send(n, "topic".to_string()).unwrap();
+        }
+
+        let first = consumer.try_recv().unwrap();
+        let second = consumer.try_recv().unwrap();
+        assert_eq!(queue.metrics().in_flight, 2);
+
+        consumer.ack(first);
+        consumer.nack(second).unwrap();
+        let metrics = queue.metrics();
+        assert_eq!((metrics.in_flight, metrics.messages_acked), (0, 1));
+
+        // The nacked message is handed out again
+        let retried = consumer.try_recv().unwrap();
+        assert_eq!(queue.metrics().in_flight, 1);
+        consumer.ack(retried);
+        let metrics = queue.metrics();
+        assert_eq!((metrics.in_flight, metrics.messages_acked), (0, 2));
+    }
+
+    #[test]
+    fn test_exhausted_message_is_dropped_without_dead_letter_queue() {
+        let config = RusqConfig {
+            enable_dead_letter: false,
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let consumer = queue.consumer();
+        let mut message = Message::new("job", "topic".to_string());
+        message.retry_count = 3;
+
+        consumer.nack(message).unwrap();
+        let metrics = queue.metrics();
+        assert_eq!(metrics.messages_dropped, 1);
+        assert_eq!(metrics.messages_failed, 1);
+        assert_eq!(queue.depths().dead_letter, 0);
+        assert_eq!(
+            queue.dead_letter_queue().try_recv().unwrap_err(),
+            RusqError::DeadLetterDisabled
+        );
+    }
+
+    #[test]
+    fn test_try_send_full_returns_message() {
+        let config = RusqConfig {
+            capacity: Some(1),
+            ..Default::default()
+        };
+        let queue = MpmcQueue::new(config);
+        let producer = queue.producer();
+
+        producer.try_send(Message::new(1, "t".to_string())).unwrap();
+        let rejected = Message::new(2, "retry-me".to_string());
+        let rejected_id = rejected.id;
+        let err = producer.try_send(rejected).unwrap_err();
+
+        assert_eq!(err.error, RusqError::QueueFull);
+        assert!(err.to_string().starts_with("Queue is full"));
+        let message = err.into_message();
+        assert_eq!(message.id, rejected_id);
+        assert_eq!(message.payload, 2);
+        assert_eq!(message.topic, "retry-me");
+    }
+
+    #[test]
+    fn test_try_send_after_shutdown_returns_message() {
+        let queue = MpmcQueue::new(RusqConfig::default());
+        let producer = queue.producer();
+        queue.shutdown();
+
+        let err = producer.try_send(Message::new("x", "t".to_string())).unwrap_err();
+        assert_eq!(RusqError::from(err.clone()), RusqError::QueueShutdown);
+        assert_eq!(err.message.payload, "x");
+    }
+
+    #[test]
+    fn test_error_conversions() {
+        use std::error::Error;
+
+        assert_eq!(RusqError::from(TryRecvError::Empty), RusqError::Empty);
+        assert_eq!(RusqError::from(TryRecvError::Disconnected), RusqError::QueueShutdown);
+        assert_eq!(RusqError::from(RecvTimeoutError::Timeout), RusqError::Timeout);
+
+        let err = SendError::from(TrySendError::Full(Message::new(7, "t".to_string())));
+        assert_eq!(err.error, RusqError::QueueFull);
+        assert_eq!(err.source().unwrap().to_string(), "Queue is full");
     }
 
     #[test]
@@ -1,4 +1,24 @@
+use crate::rusq::Priority;
+use base64::prelude::{Engine, BASE64_STANDARD};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+/// Version of this agent build, reported with every result
+pub const AGENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Operating system the agent was built for: `windows`, `macos`, `linux` or `unknown`.
+pub fn current_platform() -> &'static str {
+    if cfg!(windows) {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "macos"
+    } else if cfg!(target_os = "linux") {
+        "linux"
+    } else {
+        "unknown"
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestCase {
@@ -7,6 +27,51 @@ pub struct TestCase {
     pub expected: Option<String>,
     #[serde(default)]
     pub timeout_ms: Option<u64>,
+    /// Kill the program once its resident memory exceeds this many KiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit_kb: Option<u64>,
+    /// Flag the case as `slow` (without failing it) when it runs longer than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft_time_limit_ms: Option<u64>,
+    /// Pipeline mode: feed the previous case's stdout as stdin instead of `input`
+    #[serde(default)]
+    pub input_from_previous: bool,
+    /// Subtask this case belongs to; a subtask only scores if all of its cases pass
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// How `input` encodes the bytes fed to stdin
+    #[serde(default, skip_serializing_if = "InputEncoding::is_text")]
+    pub input_encoding: InputEncoding,
+    /// Command-line arguments passed to the program, after the language's own `run_args`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+impl TestCase {
+    /// The exact bytes fed to the program's stdin, after decoding `input`.
+    pub fn stdin_bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        match self.input_encoding {
+            InputEncoding::Text => Ok(self.input.clone().into_bytes()),
+            InputEncoding::Base64 => BASE64_STANDARD.decode(&self.input),
+        }
+    }
+}
+
+/// Encoding of a test case's `input` field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputEncoding {
+    /// The input is used as-is
+    #[default]
+    Text,
+    /// The input is standard base64, for binary stdin
+    Base64,
+}
+
+impl InputEncoding {
+    pub fn is_text(&self) -> bool {
+        *self == InputEncoding::Text
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,25 +79,607 @@ pub struct ExecuteRequest {
     pub language: String,
     pub code: String,
     pub testcases: Vec<TestCase>,
+    /// Scheduling priority; higher-priority jobs are picked up first
+    #[serde(default)]
+    pub priority: Priority,
+    /// How stdout is compared against each case's expected output
+    #[serde(flatten)]
+    pub comparison: Comparison,
+    /// Optimization preset of the language's compile step (e.g. `debug`, `release`);
+    /// ignored for languages without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimization: Option<String>,
+    /// Points awarded for each fully passed subtask (see `TestCase::group`); groups not
+    /// listed are worth 1
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub group_weights: HashMap<String, u32>,
+    /// Fixed seed exposed to every test case's run as `SEED` and `PYTHONHASHSEED`, for
+    /// reproducible verdicts from programs that use randomness
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Report the exact stdin each case received as `stdin_base64` in its result; off by
+    /// default to avoid repeating large inputs
+    #[serde(default)]
+    pub echo_stdin: bool,
+    /// Capture stdout and stderr through one pipe, so each case's `output` keeps them
+    /// interleaved in the order the program wrote them; that text is then what gets graded
+    #[serde(default)]
+    pub merge_output: bool,
+    /// Program whose output on each case's input becomes that case's expected output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<ReferenceSolution>,
+    /// Write each case's stdout to a file instead of memory, and keep only this many bytes
+    /// of it in `stdout`; the full output is graded by streaming the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_output_bytes: Option<usize>,
+    /// Extra environment variables for the compile step and every case's run, e.g.
+    /// `GOMAXPROCS`; they take precedence over those derived from `seed`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Only compile: the response carries the compiler's diagnostics in `message` and no
+    /// results, and nothing is run
+    #[serde(default)]
+    pub compile_only: bool,
+    /// More source files written next to the entry file, e.g. headers or modules that it
+    /// includes or imports; one named like the language's `file_name` is the entry file
+    /// itself, and `code` is then left empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<SourceFile>,
+    /// Deadline for compiling and running the whole submission. When it passes, the running
+    /// case is killed, the rest are not run, and the status is `timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_timeout_ms: Option<u64>,
+}
+
+/// One file of a multi-file submission, written to `name` relative to the work directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub name: String,
+    pub content: String,
+}
+
+/// An instructor's reference solution, run to produce the expected outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReferenceSolution {
+    /// Defaults to the submission's language
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub code: String,
+}
+
+/// Normalization applied to expected and actual output before comparing them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonMode {
+    /// Byte-for-byte equality
+    #[default]
+    Exact,
+    /// Ignore trailing whitespace on each line and a trailing newline on the whole output
+    TrimTrailingWhitespace,
+    /// Compare whitespace-separated tokens, ignoring how they are spaced
+    IgnoreAllWhitespace,
+}
+
+/// Grading options for comparing a case's stdout with its expected output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comparison {
+    #[serde(default)]
+    pub comparison_mode: ComparisonMode,
+    /// Compare case-insensitively ("YES" matches "yes"); applies per line or per token
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// In `exact` mode, also require the trailing newline to match. By default one missing
+    /// or extra `\n` at the very end is ignored.
+    #[serde(default)]
+    pub strict_trailing_newline: bool,
+}
+
+impl Comparison {
+    /// Whether `actual` matches `expected` under these options.
+    pub fn matches(&self, expected: &str, actual: &str) -> bool {
+        match self.comparison_mode {
+            ComparisonMode::Exact if self.strict_trailing_newline => self.eq(expected, actual),
+            ComparisonMode::Exact => self.eq(
+                expected.strip_suffix('\n').unwrap_or(expected),
+                actual.strip_suffix('\n').unwrap_or(actual),
+            ),
+            ComparisonMode::TrimTrailingWhitespace => {
+                let mut exp = expected.lines().map(str::trim_end);
+                let mut act = actual.lines().map(str::trim_end);
+                loop {
+                    match (exp.next(), act.next()) {
+                        (None, None) => return true,
+                        (Some(e), Some(a)) if self.eq(e, a) => {}
+                        _ => return false,
+                    }
+                }
+            }
+            ComparisonMode::IgnoreAllWhitespace => {
+                let mut exp = expected.split_whitespace();
+                let mut act = actual.split_whitespace();
+                loop {
+                    match (exp.next(), act.next()) {
+                        (None, None) => return true,
+                        (Some(e), Some(a)) if self.eq(e, a) => {}
+                        _ => return false,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like `matches`, but reads `actual` one line at a time, so an output too large to
+    /// hold in memory can be graded from a file.
+    pub fn matches_reader(&self, expected: &str, actual: impl BufRead) -> io::Result<bool> {
+        match self.comparison_mode {
+            ComparisonMode::Exact => {
+                let expected = if self.strict_trailing_newline {
+                    expected
+                } else {
+                    expected.strip_suffix('\n').unwrap_or(expected)
+                };
+                let mut exp = expected.split('\n');
+                let mut act = SplitLines::new(actual, true, self.strict_trailing_newline);
+                loop {
+                    match (exp.next(), act.next_line()?) {
+                        (None, None) => return Ok(true),
+                        (Some(e), Some(a)) if self.eq(e, &a) => {}
+                        _ => return Ok(false),
+                    }
+                }
+            }
+            ComparisonMode::TrimTrailingWhitespace => {
+                let mut exp = expected.lines();
+                let mut act = SplitLines::new(actual, false, false);
+                loop {
+                    match (exp.next(), act.next_line()?) {
+                        (None, None) => return Ok(true),
+                        (Some(e), Some(a)) if self.eq(e.trim_end(), a.trim_end()) => {}
+                        _ => return Ok(false),
+                    }
+                }
+            }
+            ComparisonMode::IgnoreAllWhitespace => {
+                let mut exp = expected.split_whitespace();
+                let mut act = SplitLines::new(actual, false, false);
+                while let Some(line) = act.next_line()? {
+                    for a in line.split_whitespace() {
+                        match exp.next() {
+                            Some(e) if self.eq(e, a) => {}
+                            _ => return Ok(false),
+                        }
+                    }
+                }
+                Ok(exp.next().is_none())
+            }
+        }
+    }
+
+    /// The first line where `actual` differs from `expected` under these options, or `None`
+    /// when they match.
+    pub fn first_mismatch(&self, expected: &str, actual: &str) -> Option<LineMismatch> {
+        match self.comparison_mode {
+            ComparisonMode::Exact => {
+                let (expected, actual) = if self.strict_trailing_newline {
+                    (expected, actual)
+                } else {
+                    (
+                        expected.strip_suffix('\n').unwrap_or(expected),
+                        actual.strip_suffix('\n').unwrap_or(actual),
+                    )
+                };
+                first_differing_line(expected.split('\n'), actual.split('\n'), |e, a| {
+                    self.eq(e, a)
+                })
+            }
+            ComparisonMode::TrimTrailingWhitespace => {
+                first_differing_line(expected.lines(), actual.lines(), |e, a| {
+                    self.eq(e.trim_end(), a.trim_end())
+                })
+            }
+            ComparisonMode::IgnoreAllWhitespace => {
+                // Report the lines holding the first differing token
+                let mut exp = tokens_by_line(expected);
+                let mut act = tokens_by_line(actual);
+                loop {
+                    match (exp.next(), act.next()) {
+                        (None, None) => return None,
+                        (Some((_, e, _)), Some((_, a, _))) if self.eq(e, a) => {}
+                        (e, a) => {
+                            let line = match a {
+                                Some((line, _, _)) => line,
+                                None => actual.lines().count() + 1,
+                            };
+                            return Some(LineMismatch {
+                                line,
+                                expected: e.map(|(_, _, line)| line.to_string()),
+                                actual: a.map(|(_, _, line)| line.to_string()),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn eq(&self, expected: &str, actual: &str) -> bool {
+        if self.ignore_case {
+            expected.to_lowercase() == actual.to_lowercase()
+        } else {
+            expected == actual
+        }
+    }
+}
+
+// Lines of a reader, split at `\n` like `str::split('\n')` or `str::lines()` would split
+// the whole text, without holding more than one line in memory
+struct SplitLines<R> {
+    reader: R,
+    buf: Vec<u8>,
+    /// Yield one empty line for empty input, as `split` does
+    empty_input_line: bool,
+    /// Yield the empty line after a final `\n`, as `split` does
+    line_after_final_newline: bool,
+    read_any: bool,
+    ended_with_newline: bool,
+    done: bool,
+}
+
+impl<R: BufRead> SplitLines<R> {
+    fn new(reader: R, empty_input_line: bool, line_after_final_newline: bool) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            empty_input_line,
+            line_after_final_newline,
+            read_any: false,
+            ended_with_newline: false,
+            done: false,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        if self.done {
+            return Ok(None);
+        }
+        self.buf.clear();
+        if self.reader.read_until(b'\n', &mut self.buf)? == 0 {
+            self.done = true;
+            let trailing = if self.read_any {
+                self.ended_with_newline && self.line_after_final_newline
+            } else {
+                self.empty_input_line
+            };
+            return Ok(trailing.then(String::new));
+        }
+        self.read_any = true;
+        self.ended_with_newline = self.buf.last() == Some(&b'\n');
+        if self.ended_with_newline {
+            self.buf.pop();
+        }
+        Ok(Some(String::from_utf8_lossy(&self.buf).into_owned()))
+    }
+}
+
+/// Where a case's output first departs from the expected output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineMismatch {
+    /// 1-based line number; in `ignore_all_whitespace` mode, the line of the actual output
+    /// holding the first differing token
+    pub line: usize,
+    /// The expected line, or `None` when the output has more lines than expected
+    pub expected: Option<String>,
+    /// The actual line, or `None` when the output ended early
+    pub actual: Option<String>,
+}
+
+/// Longest piece of a line quoted in `LineMismatch::summary`, in characters.
+const MAX_SUMMARY_LINE_CHARS: usize = 80;
+
+impl LineMismatch {
+    /// One-line description for learners, e.g. "line 3: expected `foo` got `bar`". Long
+    /// lines are cut short so the summary stays small.
+    pub fn summary(&self) -> String {
+        let quote = |line: &Option<String>| match line {
+            Some(line) if line.chars().count() > MAX_SUMMARY_LINE_CHARS => {
+                let cut: String = line.chars().take(MAX_SUMMARY_LINE_CHARS).collect();
+                format!("`{}`...", cut)
+            }
+            Some(line) => format!("`{}`", line),
+            None => "end of output".to_string(),
+        };
+        format!(
+            "line {}: expected {} got {}",
+            self.line,
+            quote(&self.expected),
+            quote(&self.actual)
+        )
+    }
+}
+
+// Walk two sequences of lines in step and report the first pair `same` rejects
+fn first_differing_line<'a>(
+    mut expected: impl Iterator<Item = &'a str>,
+    mut actual: impl Iterator<Item = &'a str>,
+    same: impl Fn(&str, &str) -> bool,
+) -> Option<LineMismatch> {
+    let mut line = 0;
+    loop {
+        line += 1;
+        match (expected.next(), actual.next()) {
+            (None, None) => return None,
+            (Some(e), Some(a)) if same(e, a) => {}
+            (e, a) => {
+                return Some(LineMismatch {
+                    line,
+                    expected: e.map(String::from),
+                    actual: a.map(String::from),
+                })
+            }
+        }
+    }
+}
+
+// Whitespace-separated tokens of `text`, each with its 1-based line number and line
+fn tokens_by_line(text: &str) -> impl Iterator<Item = (usize, &str, &str)> {
+    text.lines().enumerate().flat_map(|(i, line)| {
+        line.split_whitespace()
+            .map(move |token| (i + 1, token, line))
+    })
+}
+
+/// Outcome of a single test case, separating correctness failures from execution failures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Ran successfully and the output matched
+    Accepted,
+    /// Ran successfully but the output did not match
+    WrongAnswer,
+    /// Crashed or exited with a non-zero status
+    RuntimeError,
+    /// Killed after exceeding the time limit
+    TimedOut,
+    /// Killed after exceeding the memory limit
+    MemoryLimitExceeded,
+    /// Ran successfully but there was no expected output to compare against
+    #[default]
+    NotJudged,
+    /// Skipped because the agent was shutting down or the total timeout had passed
+    NotRun,
+}
+
+impl Verdict {
+    pub fn is_not_judged(&self) -> bool {
+        *self == Verdict::NotJudged
+    }
+
+    /// Classify a case from how the process ended and, if judged, whether its output matched.
+    pub fn classify(timed_out: bool, exited_ok: bool, output_matched: Option<bool>) -> Self {
+        if timed_out {
+            Verdict::TimedOut
+        } else if !exited_ok {
+            Verdict::RuntimeError
+        } else {
+            match output_matched {
+                Some(true) => Verdict::Accepted,
+                Some(false) => Verdict::WrongAnswer,
+                None => Verdict::NotJudged,
+            }
+        }
+    }
 }
 
+/// Result of one test case. Cases without an expected output are only run, not judged:
+/// the grading fields (`passed`, `expected`, `verdict`) are then left out of the JSON.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaseResult {
     pub id: i32,
     pub ok: bool,
-    pub passed: bool,
+    /// Whether the output matched; `None` when there was nothing to compare against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passed: Option<bool>,
     pub input: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expected: Option<String>,
     pub stdout: String,
     pub stderr: String,
     pub timed_out: bool,
+    /// Killed for exceeding the case's memory limit
+    #[serde(default)]
+    pub memory_exceeded: bool,
+    /// Killed for writing more than the agent's output limit; the output is cut off there
+    #[serde(default)]
+    pub output_truncated: bool,
+    /// Finished, but took longer than the case's `soft_time_limit_ms`
+    #[serde(default)]
+    pub slow: bool,
     pub duration_ms: u64,
+    /// Peak resident memory of the program's process in KiB, sampled while it runs
     pub memory_kb: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exit_code: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub term_signal: Option<i32>,
+    /// Wall-clock start of the case, in milliseconds since the Unix epoch
+    #[serde(default)]
+    pub started_at: u64,
+    /// Wall-clock end of the case, in milliseconds since the Unix epoch
+    #[serde(default)]
+    pub finished_at: u64,
+    #[serde(default, skip_serializing_if = "Verdict::is_not_judged")]
+    pub verdict: Verdict,
+    /// Bytes the program actually received on stdin, base64-encoded; only reported when
+    /// the request sets `echo_stdin`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdin_base64: Option<String>,
+    /// Interleaved stdout and stderr, reported instead of the separate `stdout` and
+    /// `stderr` (left empty) when the request sets `merge_output`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// First differing line when the output did not match the expected output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_mismatch: Option<LineMismatch>,
+    /// `first_mismatch` in words, e.g. "line 3: expected `foo` got `bar`"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    /// Full size of stdout when it was longer than the request's `spill_output_bytes`;
+    /// `stdout` then holds only the start of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spilled_stdout_bytes: Option<u64>,
+}
+
+impl CaseResult {
+    /// Placeholder for a case that was never started.
+    pub fn not_run(tc: &TestCase) -> Self {
+        Self {
+            id: tc.id,
+            ok: false,
+            passed: None,
+            input: tc.input.clone(),
+            expected: tc.expected.clone(),
+            stdout: String::new(),
+            stderr: String::new(),
+            timed_out: false,
+            memory_exceeded: false,
+            output_truncated: false,
+            slow: false,
+            duration_ms: 0,
+            memory_kb: 0,
+            exit_code: None,
+            term_signal: None,
+            started_at: 0,
+            finished_at: 0,
+            verdict: Verdict::NotRun,
+            stdin_base64: None,
+            output: None,
+            first_mismatch: None,
+            diff: None,
+            spilled_stdout_bytes: None,
+        }
+    }
+}
+
+/// Outcome of one subtask (a group of test cases).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupResult {
+    pub group: String,
+    pub case_ids: Vec<i32>,
+    /// Whether every case in the group ran and passed
+    pub passed: bool,
+    pub weight: u32,
+    /// `weight` if the group passed, otherwise 0
+    pub score: u32,
+}
+
+/// All-or-nothing subtask scoring of a run, reported when any test case has a group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubtaskScore {
+    /// Groups in the order they first appear in the test cases
+    pub groups: Vec<GroupResult>,
+    pub score: u32,
+    pub max_score: u32,
+}
+
+impl SubtaskScore {
+    /// Score the grouped `testcases` against their `results`, matched by case id. Cases
+    /// that were not run or not judged count as failed. `None` when no case has a group.
+    pub fn compute(
+        testcases: &[TestCase],
+        results: &[CaseResult],
+        weights: &HashMap<String, u32>,
+    ) -> Option<Self> {
+        let mut groups: Vec<GroupResult> = Vec::new();
+        for tc in testcases {
+            let Some(name) = &tc.group else {
+                continue;
+            };
+            let passed = results
+                .iter()
+                .any(|r| r.id == tc.id && r.passed == Some(true));
+            match groups.iter_mut().find(|g| &g.group == name) {
+                Some(group) => {
+                    group.case_ids.push(tc.id);
+                    group.passed &= passed;
+                }
+                None => groups.push(GroupResult {
+                    group: name.clone(),
+                    case_ids: vec![tc.id],
+                    passed,
+                    weight: weights.get(name).copied().unwrap_or(1),
+                    score: 0,
+                }),
+            }
+        }
+        if groups.is_empty() {
+            return None;
+        }
+
+        for group in &mut groups {
+            if group.passed {
+                group.score = group.weight;
+            }
+        }
+        Some(Self {
+            score: groups.iter().map(|g| g.score).sum(),
+            max_score: groups.iter().map(|g| g.weight).sum(),
+            groups,
+        })
+    }
+}
+
+/// Verdict counts and overall score of a run, for consumers that don't need every case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scoreboard {
+    /// Test cases in the request; the category counts add up to it
+    pub total: u32,
+    pub accepted: u32,
+    pub wrong_answer: u32,
+    pub runtime_error: u32,
+    pub timed_out: u32,
+    pub memory_limit_exceeded: u32,
+    /// Cases that never ran because compilation failed
+    pub compile_error: u32,
+    /// Cases that ran without an expected output to compare against
+    pub not_judged: u32,
+    /// Cases skipped because the agent shut down
+    pub not_run: u32,
+    /// The subtask score when cases are grouped, otherwise one point per accepted case
+    pub score: u32,
+    pub max_score: u32,
+}
+
+impl Scoreboard {
+    /// Tally the run's `results`; `compile_errors` is the number of cases that never ran
+    /// because the build failed.
+    pub fn compute(
+        results: &[CaseResult],
+        compile_errors: usize,
+        subtasks: Option<&SubtaskScore>,
+    ) -> Self {
+        let mut board = Self {
+            compile_error: compile_errors as u32,
+            ..Self::default()
+        };
+        for result in results {
+            let count = match result.verdict {
+                Verdict::Accepted => &mut board.accepted,
+                Verdict::WrongAnswer => &mut board.wrong_answer,
+                Verdict::RuntimeError => &mut board.runtime_error,
+                Verdict::TimedOut => &mut board.timed_out,
+                Verdict::MemoryLimitExceeded => &mut board.memory_limit_exceeded,
+                Verdict::NotJudged => &mut board.not_judged,
+                Verdict::NotRun => &mut board.not_run,
+            };
+            *count += 1;
+        }
+        board.total = results.len() as u32 + board.compile_error;
+        (board.score, board.max_score) = match subtasks {
+            Some(subtasks) => (subtasks.score, subtasks.max_score),
+            None => (board.accepted, board.total - board.not_judged),
+        };
+        board
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,7 +690,13 @@ pub enum ExecutionStatus {
     Timeout,
     CompileError,
     RuntimeError,
+    /// At least one case went over its memory limit
+    MemoryLimitExceeded,
     UnsupportedLanguage,
+    /// The program was prepared (and compiled, if needed) but the request had no test cases
+    NoTestsRun,
+    /// The agent shut down part way through; the remaining cases were not run
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +710,36 @@ pub struct ExecuteResponse {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub results: Vec<CaseResult>,
     pub total_duration_ms: u64,
+    /// Retained work directory, only reported when the agent keeps failed runs for debugging
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub work_dir: Option<String>,
+    /// Exit code of the compile step, when the language has one and it exited normally
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub compile_exit_code: Option<i32>,
+    /// Platform (`windows`/`macos`/`linux`) and agent build that produced this result
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub agent_version: String,
+    /// Subtask scoring, present when the request grouped its test cases
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub subtasks: Option<SubtaskScore>,
+    /// Verdict counts and overall score across all test cases
+    #[serde(default)]
+    pub scoreboard: Scoreboard,
+    /// Commands actually spawned, only reported when the agent runs with
+    /// `BUILDIT_REPORT_COMMANDS`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub commands: Option<ResolvedCommands>,
+}
+
+/// Program and arguments of each spawned command after platform-specific resolution,
+/// including the `cmd /C` wrapper on Windows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedCommands {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub compile: Option<Vec<String>>,
+    pub run: Vec<String>,
 }
 
 #[cfg(test)]
@@ -70,6 +753,12 @@ mod tests {
             input: "hello".to_string(),
             expected: Some("world".to_string()),
             timeout_ms: Some(5000),
+            input_from_previous: false,
+            group: None,
+            input_encoding: InputEncoding::Text,
+            memory_limit_kb: None,
+            soft_time_limit_ms: None,
+            args: vec![],
         };
 
         let json = serde_json::to_string(&test_case).unwrap();
@@ -86,6 +775,7 @@ mod tests {
         let json = r#"{"id":1,"input":"test","expected":null}"#;
         let test_case: TestCase = serde_json::from_str(json).unwrap();
         assert_eq!(test_case.timeout_ms, None);
+        assert!(!test_case.input_from_previous);
     }
 
     #[test]
@@ -99,8 +789,27 @@ mod tests {
                     input: "".to_string(),
                     expected: Some("hello".to_string()),
                     timeout_ms: None,
+                    input_from_previous: false,
+                    group: None,
+                    input_encoding: InputEncoding::Text,
+                    memory_limit_kb: None,
+                    soft_time_limit_ms: None,
+                    args: vec![],
                 }
             ],
+            priority: Priority::High,
+            comparison: Comparison::default(),
+            optimization: None,
+            group_weights: HashMap::new(),
+            seed: None,
+            echo_stdin: false,
+            merge_output: false,
+            reference: None,
+            spill_output_bytes: None,
+            env: HashMap::new(),
+            compile_only: false,
+            files: vec![],
+            total_timeout_ms: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -109,6 +818,286 @@ mod tests {
         assert_eq!(deserialized.language, "python3");
         assert_eq!(deserialized.code, "print('hello')");
         assert_eq!(deserialized.testcases.len(), 1);
+        assert_eq!(deserialized.priority, Priority::High);
+    }
+
+    #[test]
+    fn test_execute_request_default_priority() {
+        let json = r#"{"language":"python3","code":"print(1)","testcases":[]}"#;
+        let request: ExecuteRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(request.priority, Priority::Normal);
+        assert_eq!(request.comparison, Comparison::default());
+        assert_eq!(request.optimization, None);
+    }
+
+    #[test]
+    fn test_execute_request_comparison_fields() {
+        let json = r#"{"language":"python3","code":"print(1)","testcases":[],
+            "comparison_mode":"trim_trailing_whitespace","ignore_case":true}"#;
+        let request: ExecuteRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(request.comparison.comparison_mode, ComparisonMode::TrimTrailingWhitespace);
+        assert!(request.comparison.ignore_case);
+    }
+
+    fn comparison(comparison_mode: ComparisonMode, ignore_case: bool) -> Comparison {
+        Comparison {
+            comparison_mode,
+            ignore_case,
+            ..Comparison::default()
+        }
+    }
+
+    #[test]
+    fn test_ignore_case_exact() {
+        let cmp = comparison(ComparisonMode::Exact, true);
+        assert!(cmp.matches("YES\n", "yes\n"));
+        assert!(!cmp.matches("YES\n", "no\n"));
+        // Case folding does not relax anything else
+        assert!(!cmp.matches("YES\n", "yes \n"));
+        assert!(!comparison(ComparisonMode::Exact, false).matches("YES\n", "yes\n"));
+    }
+
+    #[test]
+    fn test_exact_ignores_one_trailing_newline_by_default() {
+        let cmp = Comparison::default();
+        // Expected has the newline, actual doesn't, and vice versa
+        assert!(cmp.matches("42\n", "42"));
+        assert!(cmp.matches("42", "42\n"));
+        // Only a single final newline is forgiven
+        assert!(!cmp.matches("42\n", "42\n\n\n"));
+        assert!(!cmp.matches("4\n2", "42"));
+    }
+
+    #[test]
+    fn test_strict_trailing_newline_requires_exact_bytes() {
+        let cmp = Comparison {
+            strict_trailing_newline: true,
+            ..Comparison::default()
+        };
+        assert!(cmp.matches("42\n", "42\n"));
+        assert!(!cmp.matches("42\n", "42"));
+        assert!(!cmp.matches("42", "42\n"));
+
+        let json = r#"{"language":"c","code":"","testcases":[],"strict_trailing_newline":true}"#;
+        let request: ExecuteRequest = serde_json::from_str(json).unwrap();
+        assert!(request.comparison.strict_trailing_newline);
+    }
+
+    #[test]
+    fn test_whitespace_modes() {
+        let trim = comparison(ComparisonMode::TrimTrailingWhitespace, false);
+        assert!(trim.matches("42\n", "42"));
+        assert!(trim.matches("1 2 3\n", "1 2 3 \n"));
+        assert!(trim.matches("1\n2 \n3\n", "1  \n2\n3"));
+        // Leading and inner whitespace still count
+        assert!(!trim.matches("1 2 3\n", " 1 2 3\n"));
+        assert!(!trim.matches("1 2 3\n", "1  2 3\n"));
+        assert!(!trim.matches("1\n2\n", "1\n\n2\n"));
+
+        let tokens = comparison(ComparisonMode::IgnoreAllWhitespace, false);
+        assert!(tokens.matches("42\n", "42"));
+        assert!(tokens.matches("1 2 3\n", "1\t2\n\n 3 "));
+        assert!(tokens.matches("1\n2\n3\n", "1 2 3"));
+        assert!(!tokens.matches("1 2 3\n", "12 3\n"));
+
+        let exact = Comparison::default();
+        assert!(!exact.matches("1 2 3\n", "1 2 3 \n"));
+        assert!(!exact.matches("1\n2\n", "1 \n2\n"));
+    }
+
+    #[test]
+    fn test_ignore_case_with_trailing_whitespace_trim() {
+        let cmp = comparison(ComparisonMode::TrimTrailingWhitespace, true);
+        assert!(cmp.matches("Yes\nNo\n", "YES  \nno"));
+        assert!(!cmp.matches("Yes\nNo\n", "YES\nmaybe\n"));
+        assert!(!cmp.matches("Yes\nNo\n", "YES\n"));
+        assert!(!comparison(ComparisonMode::TrimTrailingWhitespace, false)
+            .matches("Yes\n", "YES\n"));
+    }
+
+    #[test]
+    fn test_ignore_case_per_token() {
+        let cmp = comparison(ComparisonMode::IgnoreAllWhitespace, true);
+        assert!(cmp.matches("Alice BOB\n", "alice\n  bob"));
+        assert!(!cmp.matches("Alice BOB\n", "alicebob"));
+        assert!(!cmp.matches("Alice BOB\n", "alice bob carol"));
+    }
+
+    #[test]
+    fn test_matches_reader_agrees_with_matches() {
+        let outputs = [
+            "", "\n", "42", "42\n", "42\n\n", "1\n2\n3\n", "1 2\n3\n", "1 2  \r\n3\n",
+            "YES\nno", "a\n\nb\n",
+        ];
+        let strict = Comparison {
+            strict_trailing_newline: true,
+            ..Comparison::default()
+        };
+        let comparisons = [
+            Comparison::default(),
+            strict,
+            comparison(ComparisonMode::Exact, true),
+            comparison(ComparisonMode::TrimTrailingWhitespace, false),
+            comparison(ComparisonMode::IgnoreAllWhitespace, true),
+        ];
+        for cmp in &comparisons {
+            for expected in outputs {
+                for actual in outputs {
+                    assert_eq!(
+                        cmp.matches_reader(expected, actual.as_bytes()).unwrap(),
+                        cmp.matches(expected, actual),
+                        "{:?}: {:?} vs {:?}",
+                        cmp,
+                        expected,
+                        actual
+                    );
+                }
+            }
+        }
+    }
+
+    fn mismatch(line: usize, expected: Option<&str>, actual: Option<&str>) -> LineMismatch {
+        LineMismatch {
+            line,
+            expected: expected.map(String::from),
+            actual: actual.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_first_mismatch_reports_differing_line() {
+        let exact = Comparison::default();
+        assert_eq!(exact.first_mismatch("1\n2\n3\n", "1\n2\n3"), None);
+        assert_eq!(
+            exact.first_mismatch("1\n2\n3\n", "1\n20\n3\n"),
+            Some(mismatch(2, Some("2"), Some("20")))
+        );
+        // Missing and extra lines
+        assert_eq!(
+            exact.first_mismatch("1\n2\n3\n", "1\n2\n"),
+            Some(mismatch(3, Some("3"), None))
+        );
+        assert_eq!(
+            exact.first_mismatch("1\n", "1\n2\n"),
+            Some(mismatch(2, None, Some("2")))
+        );
+        // Trailing spaces count in exact mode, but not once trimmed
+        assert_eq!(
+            exact.first_mismatch("a\nb\n", "a  \nc\n"),
+            Some(mismatch(1, Some("a"), Some("a  ")))
+        );
+        let trim = comparison(ComparisonMode::TrimTrailingWhitespace, false);
+        assert_eq!(
+            trim.first_mismatch("a\nb\n", "a  \nc\n"),
+            Some(mismatch(2, Some("b"), Some("c")))
+        );
+    }
+
+    #[test]
+    fn test_mismatch_summary() {
+        assert_eq!(
+            mismatch(3, Some("foo"), Some("bar")).summary(),
+            "line 3: expected `foo` got `bar`"
+        );
+        assert_eq!(
+            mismatch(2, Some("2"), None).summary(),
+            "line 2: expected `2` got end of output"
+        );
+        assert_eq!(
+            mismatch(4, None, Some("extra")).summary(),
+            "line 4: expected end of output got `extra`"
+        );
+        let long = "é".repeat(1000);
+        let summary = mismatch(1, Some("short"), Some(&long)).summary();
+        assert!(summary.ends_with(&format!("`{}`...", "é".repeat(80))), "{}", summary);
+    }
+
+    #[test]
+    fn test_first_mismatch_by_token_reports_containing_lines() {
+        let tokens = comparison(ComparisonMode::IgnoreAllWhitespace, true);
+        assert_eq!(tokens.first_mismatch("Alice BOB\n", "alice\n  bob"), None);
+        assert_eq!(
+            tokens.first_mismatch("1 2\n3 4\n", "1\n2\n3\n5\n"),
+            Some(mismatch(4, Some("3 4"), Some("5")))
+        );
+        assert_eq!(
+            tokens.first_mismatch("1 2 3\n", "1 2\n"),
+            Some(mismatch(2, Some("1 2 3"), None))
+        );
+    }
+
+    #[test]
+    fn test_run_mode_omits_grading_fields() {
+        let result = CaseResult {
+            id: 1,
+            ok: true,
+            passed: None,
+            input: "".to_string(),
+            expected: None,
+            stdout: "hi\n".to_string(),
+            stderr: "".to_string(),
+            timed_out: false,
+            memory_exceeded: false,
+            output_truncated: false,
+            slow: false,
+            duration_ms: 3,
+            memory_kb: 0,
+            exit_code: Some(0),
+            term_signal: None,
+            started_at: 0,
+            finished_at: 3,
+            verdict: Verdict::NotJudged,
+            stdin_base64: None,
+            output: None,
+            first_mismatch: None,
+            diff: None,
+            spilled_stdout_bytes: None,
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        for field in ["passed", "expected", "verdict"] {
+            assert!(json.get(field).is_none(), "{} should be omitted", field);
+        }
+        for field in ["stdout", "stderr", "exit_code", "duration_ms"] {
+            assert!(json.get(field).is_some(), "{} should be present", field);
+        }
+
+        let back: CaseResult = serde_json::from_value(json).unwrap();
+        assert_eq!(back.passed, None);
+        assert_eq!(back.verdict, Verdict::NotJudged);
+    }
+
+    #[test]
+    fn test_judged_failure_keeps_grading_fields() {
+        let result = CaseResult {
+            id: 1,
+            ok: true,
+            passed: Some(false),
+            input: "".to_string(),
+            expected: Some("yes\n".to_string()),
+            stdout: "no\n".to_string(),
+            stderr: "".to_string(),
+            timed_out: false,
+            memory_exceeded: false,
+            output_truncated: false,
+            slow: false,
+            duration_ms: 3,
+            memory_kb: 0,
+            exit_code: Some(0),
+            term_signal: None,
+            started_at: 0,
+            finished_at: 3,
+            verdict: Verdict::WrongAnswer,
+            stdin_base64: None,
+            output: None,
+            first_mismatch: None,
+            diff: None,
+            spilled_stdout_bytes: None,
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["verdict"], "wrong_answer");
     }
 
     #[test]
@@ -116,25 +1105,95 @@ mod tests {
         let result = CaseResult {
             id: 1,
             ok: true,
-            passed: true,
+            passed: Some(true),
             input: "test input".to_string(),
             expected: Some("expected output".to_string()),
             stdout: "actual output".to_string(),
             stderr: "".to_string(),
             timed_out: false,
+            memory_exceeded: false,
+            output_truncated: false,
+            slow: false,
             duration_ms: 100,
             memory_kb: 1024,
             exit_code: Some(0),
             term_signal: None,
+            started_at: 1_700_000_000_000,
+            finished_at: 1_700_000_000_100,
+            verdict: Verdict::Accepted,
+            stdin_base64: None,
+            output: None,
+            first_mismatch: None,
+            diff: None,
+            spilled_stdout_bytes: None,
         };
 
         assert_eq!(result.id, 1);
         assert!(result.ok);
-        assert!(result.passed);
+        assert_eq!(result.passed, Some(true));
         assert!(!result.timed_out);
         assert_eq!(result.duration_ms, 100);
     }
 
+    #[test]
+    fn test_case_result_timestamps_serialization() {
+        let result = CaseResult {
+            id: 1,
+            ok: true,
+            passed: Some(true),
+            input: "".to_string(),
+            expected: None,
+            stdout: "".to_string(),
+            stderr: "".to_string(),
+            timed_out: false,
+            memory_exceeded: false,
+            output_truncated: false,
+            slow: false,
+            duration_ms: 100,
+            memory_kb: 0,
+            exit_code: Some(0),
+            term_signal: None,
+            started_at: 1_700_000_000_000,
+            finished_at: 1_700_000_000_100,
+            verdict: Verdict::Accepted,
+            stdin_base64: None,
+            output: None,
+            first_mismatch: None,
+            diff: None,
+            spilled_stdout_bytes: None,
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["started_at"], 1_700_000_000_000u64);
+        assert_eq!(json["finished_at"], 1_700_000_000_100u64);
+
+        let deserialized: CaseResult = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            deserialized.finished_at - deserialized.started_at,
+            deserialized.duration_ms
+        );
+    }
+
+    #[test]
+    fn test_verdict_classification() {
+        assert_eq!(Verdict::classify(false, true, Some(true)), Verdict::Accepted);
+        assert_eq!(Verdict::classify(false, true, Some(false)), Verdict::WrongAnswer);
+        assert_eq!(Verdict::classify(false, false, Some(false)), Verdict::RuntimeError);
+        assert_eq!(Verdict::classify(false, false, Some(true)), Verdict::RuntimeError);
+        assert_eq!(Verdict::classify(true, false, Some(false)), Verdict::TimedOut);
+        assert_eq!(Verdict::classify(false, true, None), Verdict::NotJudged);
+    }
+
+    #[test]
+    fn test_verdict_serialization() {
+        assert_eq!(serde_json::to_string(&Verdict::WrongAnswer).unwrap(), "\"wrong_answer\"");
+        assert_eq!(serde_json::to_string(&Verdict::RuntimeError).unwrap(), "\"runtime_error\"");
+        assert_eq!(
+            serde_json::to_string(&Verdict::MemoryLimitExceeded).unwrap(),
+            "\"memory_limit_exceeded\""
+        );
+    }
+
     #[test]
     fn test_execution_status_serialization() {
         let statuses = vec![
@@ -143,13 +1202,24 @@ mod tests {
             ExecutionStatus::Timeout,
             ExecutionStatus::CompileError,
             ExecutionStatus::RuntimeError,
+            ExecutionStatus::MemoryLimitExceeded,
             ExecutionStatus::UnsupportedLanguage,
+            ExecutionStatus::NoTestsRun,
         ];
 
         for status in statuses {
             let json = serde_json::to_string(&status).unwrap();
             let _deserialized: ExecutionStatus = serde_json::from_str(&json).unwrap();
         }
+        assert_eq!(
+            serde_json::to_string(&ExecutionStatus::MemoryLimitExceeded).unwrap(),
+            "\"memory_limit_exceeded\""
+        );
+
+        assert_eq!(
+            serde_json::to_string(&ExecutionStatus::NoTestsRun).unwrap(),
+            "\"no_tests_run\""
+        );
     }
 
     #[test]
@@ -163,19 +1233,37 @@ mod tests {
                 CaseResult {
                     id: 1,
                     ok: true,
-                    passed: true,
+                    passed: Some(true),
                     input: "".to_string(),
                     expected: None,
                     stdout: "output".to_string(),
                     stderr: "".to_string(),
                     timed_out: false,
+                    memory_exceeded: false,
+                    output_truncated: false,
+                    slow: false,
                     duration_ms: 50,
                     memory_kb: 512,
                     exit_code: Some(0),
                     term_signal: None,
+                    started_at: 0,
+                    finished_at: 50,
+                    verdict: Verdict::Accepted,
+                    stdin_base64: None,
+                    output: None,
+                    first_mismatch: None,
+                    diff: None,
+                    spilled_stdout_bytes: None,
                 }
             ],
             total_duration_ms: 50,
+            work_dir: None,
+            compile_exit_code: None,
+            platform: current_platform().to_string(),
+            agent_version: AGENT_VERSION.to_string(),
+            subtasks: None,
+            scoreboard: Scoreboard::default(),
+            commands: None,
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -195,9 +1283,178 @@ mod tests {
             message: Some("Language not supported".to_string()),
             results: vec![],
             total_duration_ms: 0,
+            work_dir: None,
+            compile_exit_code: None,
+            platform: current_platform().to_string(),
+            agent_version: AGENT_VERSION.to_string(),
+            subtasks: None,
+            scoreboard: Scoreboard::default(),
+            commands: None,
         };
 
         let json = serde_json::to_string(&response).unwrap();
         assert!(!json.contains("\"results\""));
+        assert!(!json.contains("compile_exit_code"));
+    }
+
+    #[test]
+    fn test_execute_response_reports_platform_and_version() {
+        let response = ExecuteResponse {
+            compiled: false,
+            language: "python3".to_string(),
+            status: Some(ExecutionStatus::NoTestsRun),
+            message: None,
+            results: vec![],
+            total_duration_ms: 0,
+            work_dir: None,
+            compile_exit_code: None,
+            platform: current_platform().to_string(),
+            agent_version: AGENT_VERSION.to_string(),
+            subtasks: None,
+            scoreboard: Scoreboard::default(),
+            commands: None,
+        };
+
+        let json: serde_json::Value = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["platform"], current_platform());
+        assert_eq!(json["agent_version"], env!("CARGO_PKG_VERSION"));
+        assert!(["windows", "macos", "linux", "unknown"].contains(&current_platform()));
+
+        // Results stored by older agents still deserialize
+        let old = r#"{"compiled":true,"language":"java","total_duration_ms":1}"#;
+        let parsed: ExecuteResponse = serde_json::from_str(old).unwrap();
+        assert!(parsed.platform.is_empty());
+    }
+
+    fn grouped_case(id: i32, group: Option<&str>) -> TestCase {
+        TestCase {
+            id,
+            input: "".to_string(),
+            expected: Some("ok\n".to_string()),
+            timeout_ms: None,
+            input_from_previous: false,
+            group: group.map(str::to_string),
+            input_encoding: InputEncoding::Text,
+            memory_limit_kb: None,
+            soft_time_limit_ms: None,
+            args: vec![],
+        }
+    }
+
+    fn case_outcome(id: i32, passed: bool) -> CaseResult {
+        CaseResult {
+            id,
+            ok: true,
+            passed: Some(passed),
+            input: "".to_string(),
+            expected: Some("ok\n".to_string()),
+            stdout: "".to_string(),
+            stderr: "".to_string(),
+            timed_out: false,
+            memory_exceeded: false,
+            output_truncated: false,
+            slow: false,
+            duration_ms: 0,
+            memory_kb: 0,
+            exit_code: Some(0),
+            term_signal: None,
+            started_at: 0,
+            finished_at: 0,
+            verdict: Verdict::classify(false, true, Some(passed)),
+            stdin_base64: None,
+            output: None,
+            first_mismatch: None,
+            diff: None,
+            spilled_stdout_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_subtask_scores_zero_when_one_case_fails() {
+        let testcases = vec![
+            grouped_case(1, Some("small")),
+            grouped_case(2, Some("small")),
+            grouped_case(3, Some("large")),
+            grouped_case(4, Some("large")),
+            grouped_case(5, None),
+        ];
+        let results: Vec<CaseResult> = [(1, true), (2, true), (3, true), (4, false), (5, true)]
+            .into_iter()
+            .map(|(id, passed)| case_outcome(id, passed))
+            .collect();
+        let weights = HashMap::from([("large".to_string(), 70)]);
+
+        let score = SubtaskScore::compute(&testcases, &results, &weights).unwrap();
+        assert_eq!(score.groups.len(), 2);
+        assert_eq!(score.groups[0].group, "small");
+        assert_eq!(score.groups[0].case_ids, vec![1, 2]);
+        assert!(score.groups[0].passed);
+        assert_eq!(score.groups[0].score, 1);
+        assert_eq!(score.groups[1].group, "large");
+        assert!(!score.groups[1].passed);
+        assert_eq!(score.groups[1].weight, 70);
+        assert_eq!(score.groups[1].score, 0);
+        assert_eq!(score.score, 1);
+        assert_eq!(score.max_score, 71);
+    }
+
+    #[test]
+    fn test_subtask_case_without_result_fails_group() {
+        let testcases = vec![grouped_case(1, Some("a")), grouped_case(2, Some("a"))];
+        let results = vec![case_outcome(1, true)];
+        let score = SubtaskScore::compute(&testcases, &results, &HashMap::new()).unwrap();
+        assert!(!score.groups[0].passed);
+        assert_eq!(score.score, 0);
+    }
+
+    #[test]
+    fn test_no_groups_means_no_subtask_score() {
+        let testcases = vec![grouped_case(1, None)];
+        let results = vec![case_outcome(1, true)];
+        assert!(SubtaskScore::compute(&testcases, &results, &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_base64_input_decodes_to_stdin_bytes() {
+        let case = TestCase {
+            input: "AP8KaGk=".to_string(),
+            input_encoding: InputEncoding::Base64,
+            ..grouped_case(1, None)
+        };
+        assert_eq!(case.stdin_bytes().unwrap(), b"\x00\xff\nhi");
+
+        let bad = TestCase {
+            input: "not base64!".to_string(),
+            ..case
+        };
+        assert!(bad.stdin_bytes().is_err());
+
+        let json = serde_json::to_value(grouped_case(1, None)).unwrap();
+        assert!(json.get("input_encoding").is_none());
+    }
+
+    #[test]
+    fn test_scoreboard_counts_each_verdict() {
+        let crashed = CaseResult {
+            ok: false,
+            exit_code: Some(1),
+            verdict: Verdict::RuntimeError,
+            ..case_outcome(3, false)
+        };
+        let results = vec![case_outcome(1, true), case_outcome(2, false), crashed];
+        let board = Scoreboard::compute(&results, 0, None);
+        assert_eq!((board.accepted, board.wrong_answer, board.runtime_error), (1, 1, 1));
+        assert_eq!(board.total, 3);
+        assert_eq!((board.score, board.max_score), (1, 3));
+    }
+
+    #[test]
+    fn test_scoreboard_uses_subtask_score_and_counts_unrun_cases() {
+        let testcases = vec![grouped_case(1, Some("a")), grouped_case(2, Some("b"))];
+        let weights = HashMap::from([("a".to_string(), 30), ("b".to_string(), 70)]);
+        let subtasks = SubtaskScore::compute(&testcases, &[], &weights).unwrap();
+        let board = Scoreboard::compute(&[], testcases.len(), Some(&subtasks));
+        assert_eq!((board.compile_error, board.total), (2, 2));
+        assert_eq!((board.score, board.max_score), (0, 100));
     }
 }
@@ -0,0 +1,407 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Delivery settings for the monitor's alert webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Alerts buffered while the endpoint is down; beyond this the oldest is dropped
+    pub max_pending: usize,
+    /// Delivery attempts per alert before giving up on it
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Timeout of a single POST
+    pub request_timeout: Duration,
+    /// How often an unchanged detection is alerted again
+    pub debounce: Duration,
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            max_pending: 100,
+            max_attempts: 8,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            request_timeout: Duration::from_secs(10),
+            debounce: Duration::from_secs(60),
+        }
+    }
+
+    /// Webhook from `BUILDIT_WEBHOOK_URL`; alerts are disabled when it is unset.
+    /// `BUILDIT_WEBHOOK_DEBOUNCE_SECS` overrides how often a repeated detection is re-sent.
+    pub fn from_env() -> Option<Self> {
+        let mut config = std::env::var("BUILDIT_WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .map(Self::new)?;
+        if let Some(secs) = std::env::var("BUILDIT_WEBHOOK_DEBOUNCE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config.debounce = Duration::from_secs(secs);
+        }
+        Some(config)
+    }
+}
+
+/// Delivery counters of a `WebhookAlerter`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct WebhookStats {
+    pub delivered: u64,
+    /// Dropped unsent because the buffer overflowed
+    pub dropped: u64,
+    /// Given up on after `max_attempts` failed deliveries
+    pub abandoned: u64,
+    pub pending: usize,
+}
+
+struct Outbox {
+    // Sequence numbers let the sender tell whether the alert it was delivering is
+    // still at the front after an overflow evicted it mid-request.
+    pending: VecDeque<(u64, serde_json::Value)>,
+    next_seq: u64,
+    max_pending: usize,
+    stats: WebhookStats,
+}
+
+/// Buffers alerts and POSTs them as JSON to a webhook from a background task, retrying
+/// failed deliveries with exponential backoff so a flaky endpoint neither loses alerts
+/// nor gets hammered.
+#[derive(Clone)]
+pub struct WebhookAlerter {
+    outbox: Arc<Mutex<Outbox>>,
+    notify: Arc<Notify>,
+    debounce: Duration,
+    last_detection: Arc<Mutex<Option<SentDetection>>>,
+}
+
+/// The detected set of the last detection alert, and when it was queued.
+struct SentDetection {
+    detected: Vec<String>,
+    at: Instant,
+}
+
+impl WebhookAlerter {
+    /// Start the delivery task; must be called from within a Tokio runtime.
+    pub fn spawn(config: WebhookConfig) -> Self {
+        let alerter = Self {
+            outbox: Arc::new(Mutex::new(Outbox {
+                pending: VecDeque::new(),
+                next_seq: 0,
+                max_pending: config.max_pending.max(1),
+                stats: WebhookStats::default(),
+            })),
+            notify: Arc::new(Notify::new()),
+            debounce: config.debounce,
+            last_detection: Arc::new(Mutex::new(None)),
+        };
+        tokio::spawn(alerter.clone().deliver_loop(config));
+        alerter
+    }
+
+    /// Queue an alert for delivery. When the buffer is full the oldest pending alert is
+    /// dropped (and counted) to make room.
+    pub fn send<T: Serialize>(&self, alert: &T) {
+        let alert = match serde_json::to_value(alert) {
+            Ok(alert) => alert,
+            Err(e) => {
+                eprintln!("Failed to serialize webhook alert: {}", e);
+                return;
+            }
+        };
+        {
+            let mut outbox = self.outbox.lock().unwrap();
+            if outbox.pending.len() >= outbox.max_pending {
+                outbox.pending.pop_front();
+                outbox.stats.dropped += 1;
+                eprintln!(
+                    "⚠️ Webhook buffer full; dropped oldest alert ({} dropped so far)",
+                    outbox.stats.dropped
+                );
+            }
+            let seq = outbox.next_seq;
+            outbox.next_seq += 1;
+            outbox.pending.push_back((seq, alert));
+        }
+        self.notify.notify_one();
+    }
+
+    /// Queue `alert` for a scan that found `detected`, unless it would repeat the previous
+    /// detection alert: a changed set is always sent (including one that clears an earlier
+    /// detection), an unchanged non-empty set at most once per `debounce`.
+    pub fn send_detection<T: Serialize>(&self, detected: &[String], alert: &T) {
+        if self.should_send_detection(detected, Instant::now()) {
+            self.send(alert);
+        }
+    }
+
+    fn should_send_detection(&self, detected: &[String], now: Instant) -> bool {
+        let mut detected = detected.to_vec();
+        detected.sort();
+        let mut last = self.last_detection.lock().unwrap();
+        let send = match &*last {
+            None => !detected.is_empty(),
+            Some(previous) if previous.detected != detected => true,
+            Some(previous) => {
+                !detected.is_empty() && now.saturating_duration_since(previous.at) >= self.debounce
+            }
+        };
+        if send {
+            *last = Some(SentDetection { detected, at: now });
+        }
+        send
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn stats(&self) -> WebhookStats {
+        let outbox = self.outbox.lock().unwrap();
+        WebhookStats {
+            pending: outbox.pending.len(),
+            ..outbox.stats
+        }
+    }
+
+    // Remove the alert `seq` if it is still at the front, updating the counters
+    fn finish(&self, seq: u64, delivered: bool) {
+        let mut outbox = self.outbox.lock().unwrap();
+        if outbox.pending.front().is_some_and(|(s, _)| *s == seq) {
+            outbox.pending.pop_front();
+            if delivered {
+                outbox.stats.delivered += 1;
+            } else {
+                outbox.stats.abandoned += 1;
+            }
+        }
+    }
+
+    async fn deliver_loop(self, config: WebhookConfig) {
+        let client = reqwest::Client::new();
+        let mut backoff = config.initial_backoff;
+        let mut attempts = 0;
+        let mut current = None;
+
+        loop {
+            let next = self.outbox.lock().unwrap().pending.front().cloned();
+            let Some((seq, alert)) = next else {
+                self.notify.notified().await;
+                continue;
+            };
+            if current != Some(seq) {
+                current = Some(seq);
+                attempts = 0;
+            }
+
+            let result = client
+                .post(&config.url)
+                .timeout(config.request_timeout)
+                .json(&alert)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+            match result {
+                Ok(_) => {
+                    self.finish(seq, true);
+                    // Endpoint is back: flush the rest without waiting
+                    backoff = config.initial_backoff;
+                }
+                Err(e) => {
+                    attempts += 1;
+                    if attempts >= config.max_attempts {
+                        eprintln!(
+                            "Giving up on webhook alert after {} attempts: {}",
+                            attempts, e
+                        );
+                        self.finish(seq, false);
+                    } else {
+                        eprintln!(
+                            "Webhook delivery failed (attempt {}/{}): {}; retrying in {:?}",
+                            attempts, config.max_attempts, e, backoff
+                        );
+                    }
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(config.max_backoff);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct MockEndpoint {
+        // Number of requests to fail before accepting
+        failures_left: Arc<AtomicUsize>,
+        received: Arc<Mutex<Vec<serde_json::Value>>>,
+    }
+
+    async fn mock_handler(
+        State(mock): State<MockEndpoint>,
+        Json(body): Json<serde_json::Value>,
+    ) -> StatusCode {
+        let failing = mock
+            .failures_left
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        mock.received.lock().unwrap().push(body);
+        StatusCode::OK
+    }
+
+    async fn serve_mock(failures: usize) -> (String, MockEndpoint) {
+        let mock = MockEndpoint::default();
+        mock.failures_left.store(failures, Ordering::SeqCst);
+        let app = Router::new()
+            .route("/hook", post(mock_handler))
+            .with_state(mock.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, mock)
+    }
+
+    fn fast_config(url: String) -> WebhookConfig {
+        WebhookConfig {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+            ..WebhookConfig::new(url)
+        }
+    }
+
+    async fn wait_for(alerter: &WebhookAlerter, done: impl Fn(WebhookStats) -> bool) {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while !done(alerter.stats()) {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "timed out: {:?}",
+                alerter.stats()
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alert_delivered_after_endpoint_recovers() {
+        let (url, mock) = serve_mock(3).await;
+        let alerter = WebhookAlerter::spawn(fast_config(url));
+
+        alerter.send(&serde_json::json!({"forbidden_processes": ["obs"]}));
+        alerter.send(&serde_json::json!({"forbidden_processes": ["vim"]}));
+        wait_for(&alerter, |s| s.delivered == 2).await;
+
+        let received = mock.received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0]["forbidden_processes"][0], "obs");
+        assert_eq!(received[1]["forbidden_processes"][0], "vim");
+        assert_eq!(alerter.stats().abandoned, 0);
+    }
+
+    #[tokio::test]
+    async fn test_alert_abandoned_after_max_attempts() {
+        let (url, mock) = serve_mock(usize::MAX).await;
+        let alerter = WebhookAlerter::spawn(WebhookConfig {
+            max_attempts: 3,
+            ..fast_config(url)
+        });
+
+        alerter.send(&serde_json::json!({"n": 1}));
+        wait_for(&alerter, |s| s.abandoned == 1).await;
+
+        assert_eq!(alerter.stats().pending, 0);
+        assert!(mock.received.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_overflow_drops_oldest_and_counts() {
+        let (url, _mock) = serve_mock(usize::MAX).await;
+        let alerter = WebhookAlerter::spawn(WebhookConfig {
+            max_pending: 2,
+            initial_backoff: Duration::from_secs(60),
+            ..WebhookConfig::new(url)
+        });
+
+        for n in 0..5 {
+            alerter.send(&serde_json::json!({ "n": n }));
+        }
+
+        let stats = alerter.stats();
+        assert_eq!(stats.dropped, 3);
+        assert_eq!(stats.pending, 2);
+        let pending: Vec<_> = alerter
+            .outbox
+            .lock()
+            .unwrap()
+            .pending
+            .iter()
+            .map(|(_, alert)| alert["n"].clone())
+            .collect();
+        assert_eq!(pending, vec![3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_detections_are_debounced() {
+        let (url, mock) = serve_mock(0).await;
+        let alerter = WebhookAlerter::spawn(fast_config(url));
+        let alert = |processes: &[&str]| {
+            serde_json::json!({
+                "timestamp": "2026-01-01T00:00:00Z",
+                "platform": "linux",
+                "forbidden_processes": processes,
+            })
+        };
+        let detected = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        // Nothing found yet: nothing to report
+        alerter.send_detection(&[], &alert(&[]));
+        alerter.send_detection(&detected(&["obs"]), &alert(&["obs"]));
+        alerter.send_detection(&detected(&["obs"]), &alert(&["obs"]));
+        alerter.send_detection(&detected(&["obs", "vim"]), &alert(&["obs", "vim"]));
+        // The all-clear is sent once
+        alerter.send_detection(&[], &alert(&[]));
+        alerter.send_detection(&[], &alert(&[]));
+        wait_for(&alerter, |s| s.delivered == 3 && s.pending == 0).await;
+
+        let received = mock.received.lock().unwrap().clone();
+        assert_eq!(received.len(), 3);
+        assert_eq!(
+            received[0]["forbidden_processes"],
+            serde_json::json!(["obs"])
+        );
+        assert_eq!(received[0]["platform"], "linux");
+        assert!(received[0]["timestamp"].is_string());
+        assert_eq!(
+            received[1]["forbidden_processes"],
+            serde_json::json!(["obs", "vim"])
+        );
+        assert_eq!(received[2]["forbidden_processes"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_detection_is_resent_after_debounce() {
+        let (url, _mock) = serve_mock(0).await;
+        let alerter = WebhookAlerter::spawn(WebhookConfig {
+            debounce: Duration::from_secs(30),
+            ..fast_config(url)
+        });
+        let obs = vec!["obs".to_string()];
+        let start = Instant::now();
+
+        assert!(alerter.should_send_detection(&obs, start));
+        assert!(!alerter.should_send_detection(&obs, start + Duration::from_secs(29)));
+        assert!(alerter.should_send_detection(&obs, start + Duration::from_secs(30)));
+        assert!(alerter.should_send_detection(&[], start + Duration::from_secs(31)));
+        assert!(!alerter.should_send_detection(&[], start + Duration::from_secs(90)));
+    }
+}
@@ -1,5 +1,6 @@
 // Integration tests for build-it-agent
 use build_it_agent::*;
+use std::collections::HashMap;
 
 #[cfg(test)]
 mod type_integration_tests {
@@ -14,12 +15,24 @@ mod type_integration_tests {
                 input: "5\n10\n".to_string(),
                 expected: Some("15\n".to_string()),
                 timeout_ms: Some(1000),
+                input_from_previous: false,
+                group: None,
+                input_encoding: InputEncoding::Text,
+                memory_limit_kb: None,
+                soft_time_limit_ms: None,
+                args: vec![],
             },
             TestCase {
                 id: 2,
                 input: "3\n7\n".to_string(),
                 expected: Some("10\n".to_string()),
                 timeout_ms: Some(1000),
+                input_from_previous: false,
+                group: None,
+                input_encoding: InputEncoding::Text,
+                memory_limit_kb: None,
+                soft_time_limit_ms: None,
+                args: vec![],
             },
         ];
 
@@ -28,6 +41,19 @@ mod type_integration_tests {
             language: "python3".to_string(),
             code: "a = int(input())\nb = int(input())\nprint(a + b)".to_string(),
             testcases: test_cases,
+            priority: Priority::Normal,
+            comparison: Comparison::default(),
+            optimization: None,
+            group_weights: HashMap::new(),
+            seed: None,
+            echo_stdin: false,
+            merge_output: false,
+            reference: None,
+            spill_output_bytes: None,
+            env: HashMap::new(),
+            compile_only: false,
+            files: vec![],
+            total_timeout_ms: None,
         };
 
         // Serialize and deserialize
@@ -49,24 +75,42 @@ mod type_integration_tests {
                 CaseResult {
                     id: 1,
                     ok: true,
-                    passed: true,
+                    passed: Some(true),
                     input: "input".to_string(),
                     expected: Some("output".to_string()),
                     stdout: "output".to_string(),
                     stderr: "".to_string(),
                     timed_out: false,
+                    memory_exceeded: false,
+                    output_truncated: false,
+                    slow: false,
                     duration_ms: 100,
                     memory_kb: 2048,
                     exit_code: Some(0),
                     term_signal: None,
+                    started_at: 0,
+                    finished_at: 100,
+                    verdict: Verdict::Accepted,
+                    stdin_base64: None,
+                    output: None,
+                    first_mismatch: None,
+                    diff: None,
+                    spilled_stdout_bytes: None,
                 }
             ],
             total_duration_ms: 150,
+            work_dir: None,
+            compile_exit_code: None,
+            platform: current_platform().to_string(),
+            agent_version: AGENT_VERSION.to_string(),
+            subtasks: None,
+            scoreboard: Scoreboard::default(),
+            commands: None,
         };
 
         assert!(response.compiled);
         assert_eq!(response.results.len(), 1);
-        assert!(response.results[0].passed);
+        assert_eq!(response.results[0].passed, Some(true));
     }
 }
 
@@ -247,8 +291,27 @@ mod cross_module_integration_tests {
                     input: "".to_string(),
                     expected: Some("Hello, World!".to_string()),
                     timeout_ms: Some(1000),
+                    input_from_previous: false,
+                    group: None,
+                    input_encoding: InputEncoding::Text,
+                    memory_limit_kb: None,
+                    soft_time_limit_ms: None,
+                    args: vec![],
                 }
             ],
+            priority: Priority::Normal,
+            comparison: Comparison::default(),
+            optimization: None,
+            group_weights: HashMap::new(),
+            seed: None,
+            echo_stdin: false,
+            merge_output: false,
+            reference: None,
+            spill_output_bytes: None,
+            env: HashMap::new(),
+            compile_only: false,
+            files: vec![],
+            total_timeout_ms: None,
         };
 
         // Verify request language matches a valid config
@@ -268,6 +331,19 @@ mod cross_module_integration_tests {
             language: "python3".to_string(),
             code: "print('test')".to_string(),
             testcases: vec![],
+            priority: Priority::High,
+            comparison: Comparison::default(),
+            optimization: None,
+            group_weights: HashMap::new(),
+            seed: None,
+            echo_stdin: false,
+            merge_output: false,
+            reference: None,
+            spill_output_bytes: None,
+            env: HashMap::new(),
+            compile_only: false,
+            files: vec![],
+            total_timeout_ms: None,
         };
 
         // Send execute request through queue
@@ -276,6 +352,7 @@ mod cross_module_integration_tests {
         // Receive and verify
         let received = consumer.try_recv().unwrap();
         assert_eq!(received.payload.language, "python3");
+        assert_eq!(received.payload.priority, Priority::High);
         assert_eq!(received.topic, "execute_queue");
     }
 
@@ -287,7 +364,9 @@ mod cross_module_integration_tests {
             ExecutionStatus::Timeout,
             ExecutionStatus::CompileError,
             ExecutionStatus::RuntimeError,
+            ExecutionStatus::MemoryLimitExceeded,
             ExecutionStatus::UnsupportedLanguage,
+            ExecutionStatus::NoTestsRun,
         ];
 
         for status in statuses {
@@ -300,19 +379,37 @@ mod cross_module_integration_tests {
                     CaseResult {
                         id: 1,
                         ok: true,
-                        passed: true,
+                        passed: Some(true),
                         input: "".to_string(),
                         expected: None,
                         stdout: "".to_string(),
                         stderr: "".to_string(),
                         timed_out: false,
+                        memory_exceeded: false,
+                        output_truncated: false,
+                        slow: false,
                         duration_ms: 0,
                         memory_kb: 0,
                         exit_code: Some(0),
                         term_signal: None,
+                        started_at: 0,
+                        finished_at: 0,
+                        verdict: Verdict::Accepted,
+                        stdin_base64: None,
+                        output: None,
+                        first_mismatch: None,
+                        diff: None,
+                        spilled_stdout_bytes: None,
                     }
                 ],
                 total_duration_ms: 0,
+                work_dir: None,
+                compile_exit_code: None,
+                platform: current_platform().to_string(),
+                agent_version: AGENT_VERSION.to_string(),
+                subtasks: None,
+                scoreboard: Scoreboard::default(),
+                commands: None,
             };
 
             // Serialize and verify